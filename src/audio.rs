@@ -5,6 +5,37 @@ use std::sync::{Arc, Mutex};
 use crate::synthesizer::Synthesizer;
 use crossbeam_channel::{bounded, Receiver};
 
+/// Frames rendered by the generation thread each time it locks the synthesizer.
+const PLAYBACK_BLOCK_SIZE: usize = 256;
+
+/// Returns the sample for one device channel of a stereo frame.
+///
+/// Mono devices get both channels summed; on devices with more than two channels the first
+/// two carry left and right and the rest are silent.
+///
+/// # Parameters
+/// - `left`, `right`: The stereo frame.
+/// - `channel`: The device channel, counting from 0.
+/// - `channels`: The number of device channels.
+pub fn channel_sample(left: f32, right: f32, channel: usize, channels: usize) -> f32 {
+    match (channels, channel) {
+        (1, _) => 0.5 * (left + right),
+        (_, 0) => left,
+        (_, 1) => right,
+        _ => 0.0,
+    }
+}
+
+/// Fills an interleaved device buffer with frames from the generation thread.
+fn write_frames<T>(data: &mut [T], channels: usize, receiver: &Receiver<(f32, f32)>, convert: impl Fn(f32) -> T) {
+    for frame in data.chunks_mut(channels) {
+        let (left, right) = receiver.recv().unwrap_or((0.0, 0.0)); // Fetch one frame for all channels
+        for (channel, sample) in frame.iter_mut().enumerate() {
+            *sample = convert(channel_sample(left, right, channel, channels));
+        }
+    }
+}
+
 pub fn play_audio(synth: Arc<Mutex<Synthesizer>>) -> Result<(), Box<dyn std::error::Error>> {
     let host = cpal::default_host(); // Get the default audio host
     let device = host.default_output_device().ok_or("No output device available")?; // Get the default output device
@@ -12,28 +43,35 @@ pub fn play_audio(synth: Arc<Mutex<Synthesizer>>) -> Result<(), Box<dyn std::err
     let sample_format = supported_config.sample_format(); // Determine the sample format
     let config: cpal::StreamConfig = supported_config.into(); // Convert to a stream configuration
 
-    let (sender, receiver): (crossbeam_channel::Sender<f32>, Receiver<f32>) = bounded(1024); // Create a channel for audio samples
+    let channels = config.channels as usize; // Interleaved samples per frame
+
+    let (sender, receiver) = bounded::<(f32, f32)>(1024); // Create a channel for stereo frames
 
     // Audio generation thread
     let synth_clone = Arc::clone(&synth);
+    let sample_rate = config.sample_rate.0; // Device sample rate in Hz
+    synth.lock().map_err(|e| e.to_string())?.set_sample_rate(sample_rate as f32); // Size the effect buffers before the audio thread starts
     std::thread::spawn(move || {
-        let mut time = 0.0; // Initialize time for sample generation
-        let time_step = 1.0 / config.sample_rate.0 as f32; // Calculate the time step based on the sample rate
+        let mut sample_index: u64 = 0; // Count samples rather than accumulating f32 time, which loses precision
+        let (mut left, mut right) = ([0.0; PLAYBACK_BLOCK_SIZE], [0.0; PLAYBACK_BLOCK_SIZE]);
         loop {
-            let synth = match synth_clone.lock() {
-                Ok(s) => s, // Lock the synthesizer for thread-safe access
-                Err(e) => {
-                    eprintln!("Synthesizer lock error: {}", e);
-                    return;
-                }
-            };
-            for _ in 0..1024 {
-                let sample = synth.generate_mixed_sample(time); // Generate a mixed audio sample
-                if sender.send(sample).is_err() {
+            {
+                let mut synth = match synth_clone.lock() {
+                    Ok(s) => s, // Lock the synthesizer for thread-safe access
+                    Err(e) => {
+                        eprintln!("Synthesizer lock error: {}", e);
+                        return;
+                    }
+                };
+                let time = sample_index as f64 / sample_rate as f64; // Timeline position in seconds
+                synth.render_mixed_block(time, &mut left, &mut right);
+            } // Release the lock before waiting on the device, so the UI stays responsive
+            for frame in left.iter().copied().zip(right.iter().copied()) {
+                if sender.send(frame).is_err() {
                     return; // Exit if the receiver is dropped
                 }
-                time += time_step; // Increment time
             }
+            sample_index += PLAYBACK_BLOCK_SIZE as u64; // Advance by one block
         }
     });
 
@@ -41,20 +79,14 @@ pub fn play_audio(synth: Arc<Mutex<Synthesizer>>) -> Result<(), Box<dyn std::err
     let stream = match sample_format {
         cpal::SampleFormat::F32 => device.build_output_stream(
             &config,
-            move |data: &mut [f32], _| {
-                for sample in data.iter_mut() {
-                    *sample = receiver.recv().unwrap_or(0.0); // Fetch samples from the channel
-                }
-            },
+            move |data: &mut [f32], _| write_frames(data, channels, &receiver, |sample| sample),
             |err| eprintln!("Stream error: {}", err), // Handle stream errors
             None,
         )?,
         cpal::SampleFormat::I16 => device.build_output_stream(
             &config,
             move |data: &mut [i16], _| {
-                for sample in data.iter_mut() {
-                    *sample = (receiver.recv().unwrap_or(0.0) * i16::MAX as f32) as i16; // Convert to i16
-                }
+                write_frames(data, channels, &receiver, |sample| (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16) // Convert to i16 without wrapping
             },
             |err| eprintln!("Stream error: {}", err),
             None,
@@ -62,9 +94,7 @@ pub fn play_audio(synth: Arc<Mutex<Synthesizer>>) -> Result<(), Box<dyn std::err
         cpal::SampleFormat::U16 => device.build_output_stream(
             &config,
             move |data: &mut [u16], _| {
-                for sample in data.iter_mut() {
-                    *sample = ((receiver.recv().unwrap_or(0.0) + 1.0) * 0.5 * u16::MAX as f32) as u16; // Convert to u16
-                }
+                write_frames(data, channels, &receiver, |sample| ((sample.clamp(-1.0, 1.0) + 1.0) * 0.5 * u16::MAX as f32) as u16) // Convert to u16 without wrapping
             },
             |err| eprintln!("Stream error: {}", err),
             None,
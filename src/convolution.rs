@@ -0,0 +1,214 @@
+use std::sync::Arc;
+use rustfft::{Fft, FftPlanner, num_complex::Complex};
+use serde::{Serialize, Deserialize};
+use crate::effects::{Effect, EffectContext};
+use crate::sample::AudioBuffer;
+
+/// Samples per partition of the impulse response. This is also the latency of the effect.
+pub const PARTITION_SIZE: usize = 256;
+
+/// FFT length used for each partition (overlap-save needs twice the partition size).
+const FFT_SIZE: usize = PARTITION_SIZE * 2;
+
+/// Convolution state for one output channel.
+#[derive(Clone)]
+struct ConvolutionChannel {
+    partitions: Vec<Vec<Complex<f32>>>, // Spectra of the impulse response partitions
+    history: Vec<Vec<Complex<f32>>>,    // Spectra of recent input blocks, used as a ring
+    input: Vec<f32>,                    // Previous and current input blocks
+    output: Vec<f32>,                   // Wet samples of the last computed block
+    frame: Vec<Complex<f32>>,           // Scratch for the forward transform
+    accumulator: Vec<Complex<f32>>,     // Sum of the partition products
+}
+
+impl ConvolutionChannel {
+    /// Splits an impulse response into partitions and transforms each one.
+    fn new(impulse: &[f32], forward: &dyn Fft<f32>, scratch: &mut [Complex<f32>]) -> Self {
+        let partitions: Vec<Vec<Complex<f32>>> = impulse
+            .chunks(PARTITION_SIZE)
+            .map(|chunk| {
+                let mut spectrum = vec![Complex { re: 0.0, im: 0.0 }; FFT_SIZE];
+                for (bin, &sample) in spectrum.iter_mut().zip(chunk) {
+                    bin.re = sample;
+                }
+                forward.process_with_scratch(&mut spectrum, scratch);
+                spectrum
+            })
+            .collect();
+        let history = vec![vec![Complex { re: 0.0, im: 0.0 }; FFT_SIZE]; partitions.len()];
+        ConvolutionChannel {
+            partitions,
+            history,
+            input: vec![0.0; FFT_SIZE],
+            output: vec![0.0; PARTITION_SIZE],
+            frame: vec![Complex { re: 0.0, im: 0.0 }; FFT_SIZE],
+            accumulator: vec![Complex { re: 0.0, im: 0.0 }; FFT_SIZE],
+        }
+    }
+
+    /// Convolves the input block that was just completed, filling `output`.
+    fn compute_block(&mut self, history_index: usize, engine: &mut ConvolutionEngine) {
+        for (bin, &sample) in self.frame.iter_mut().zip(&self.input) {
+            *bin = Complex { re: sample, im: 0.0 };
+        }
+        engine.forward.process_with_scratch(&mut self.frame, &mut engine.scratch);
+        self.history[history_index].copy_from_slice(&self.frame);
+
+        // Multiply each partition by the input spectrum from as many blocks ago, and sum
+        let count = self.partitions.len();
+        self.accumulator.fill(Complex { re: 0.0, im: 0.0 });
+        for (age, partition) in self.partitions.iter().enumerate() {
+            let spectrum = &self.history[(history_index + count - age) % count];
+            for ((sum, &x), &h) in self.accumulator.iter_mut().zip(spectrum).zip(partition) {
+                *sum += x * h;
+            }
+        }
+        engine.inverse.process_with_scratch(&mut self.accumulator, &mut engine.scratch);
+
+        // Overlap-save: the second half of the frame holds the valid output
+        for (output, bin) in self.output.iter_mut().zip(&self.accumulator[PARTITION_SIZE..]) {
+            *output = bin.re / FFT_SIZE as f32;
+        }
+        self.input.copy_within(PARTITION_SIZE.., 0);
+    }
+}
+
+/// Transforms, scratch space and per-channel state, built once the sample rate is known.
+#[derive(Clone)]
+struct ConvolutionEngine {
+    forward: Arc<dyn Fft<f32>>,
+    inverse: Arc<dyn Fft<f32>>,
+    scratch: Vec<Complex<f32>>,
+    channels: Vec<ConvolutionChannel>, // Left and right
+    position: usize,                   // Position within the current block
+    history_index: usize,              // Ring position of the newest input spectrum
+}
+
+impl std::fmt::Debug for ConvolutionEngine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConvolutionEngine")
+            .field("partitions", &self.channels.first().map_or(0, |channel| channel.partitions.len()))
+            .finish()
+    }
+}
+
+/// Convolution reverb that applies a recorded impulse response, such as a room or a speaker cabinet.
+///
+/// Uses uniformly partitioned overlap-save FFT convolution, so long impulse responses run at
+/// a fixed cost per block. Everything is allocated in `prepare`; processing never allocates.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Convolution {
+    pub filename: String, // Impulse response WAV file, reloaded with the project
+    pub mix: f32,         // Wet/dry balance, from 0.0 (dry) to 1.0 (only the convolved signal)
+    #[serde(skip)]
+    impulse: Option<Arc<AudioBuffer>>, // Decoded impulse response
+    #[serde(skip)]
+    engine: Option<ConvolutionEngine>, // Prepared convolution state
+}
+
+impl Convolution {
+    /// Creates a convolution effect from a decoded impulse response.
+    ///
+    /// # Parameters
+    /// - `filename`: The file the impulse response came from.
+    /// - `impulse`: The impulse response. Mono files are used for both channels; otherwise
+    ///   the first two channels are used for left and right.
+    pub fn new(filename: &str, impulse: Arc<AudioBuffer>) -> Self {
+        Convolution {
+            filename: filename.to_string(),
+            mix: 0.3,
+            impulse: Some(impulse),
+            engine: None,
+        }
+    }
+
+    /// Replaces the impulse response, for example after loading a project. The latency changes
+    /// with the impulse response, so prepare the chain holding the effect again afterwards.
+    pub fn set_impulse(&mut self, impulse: Arc<AudioBuffer>) {
+        self.impulse = Some(impulse);
+        self.engine = None;
+    }
+}
+
+impl Effect for Convolution {
+    fn process(&mut self, left: &mut [f32], right: &mut [f32], _context: &EffectContext) {
+        let Some(engine) = &mut self.engine else {
+            return; // No impulse response loaded, or not prepared yet
+        };
+        let mix = self.mix.clamp(0.0, 1.0);
+        let mut channels = std::mem::take(&mut engine.channels);
+
+        for (left, right) in left.iter_mut().zip(right.iter_mut()) {
+            let position = engine.position;
+            for (channel, sample) in channels.iter_mut().zip([&mut *left, &mut *right]) {
+                channel.input[PARTITION_SIZE + position] = *sample;
+                let dry = channel.input[position]; // The input from one block ago lines up with the wet output
+                *sample = dry * (1.0 - mix) + channel.output[position] * mix;
+            }
+            engine.position += 1;
+            if engine.position == PARTITION_SIZE {
+                for channel in &mut channels {
+                    channel.compute_block(engine.history_index, engine);
+                }
+                engine.history_index = (engine.history_index + 1) % channels[0].partitions.len();
+                engine.position = 0;
+            }
+        }
+        engine.channels = channels; // Moving the channels back does not allocate
+    }
+
+    /// Clears the input history so the tail stops.
+    fn reset(&mut self) {
+        if let Some(engine) = &mut self.engine {
+            for channel in &mut engine.channels {
+                channel.input.fill(0.0);
+                channel.output.fill(0.0);
+                for spectrum in &mut channel.history {
+                    spectrum.fill(Complex { re: 0.0, im: 0.0 });
+                }
+            }
+            engine.position = 0;
+            engine.history_index = 0;
+        }
+    }
+
+    /// Resamples the impulse response to the engine rate and allocates the convolution state.
+    ///
+    /// The impulse response is scaled to unit energy so that rooms of any length play back at a
+    /// similar level.
+    fn prepare(&mut self, sample_rate: f32) {
+        let Some(impulse) = self.impulse.as_ref().filter(|impulse| impulse.frames() > 0) else {
+            self.engine = None;
+            return;
+        };
+
+        let ratio = impulse.sample_rate as f64 / sample_rate as f64;
+        let length = (impulse.frames() as f64 / ratio).ceil() as usize;
+        let channels: Vec<Vec<f32>> = (0..2)
+            .map(|channel| (0..length).map(|i| impulse.read(channel, i as f64 * ratio, ratio)).collect())
+            .collect();
+        let energy = channels.iter().flatten().map(|sample| sample * sample).sum::<f32>() / 2.0;
+        let scale = if energy > 0.0 { 1.0 / energy.sqrt() } else { 0.0 };
+
+        let mut planner = FftPlanner::new();
+        let forward = planner.plan_fft_forward(FFT_SIZE);
+        let inverse = planner.plan_fft_inverse(FFT_SIZE);
+        let scratch_length = forward.get_inplace_scratch_len().max(inverse.get_inplace_scratch_len());
+        let mut scratch = vec![Complex { re: 0.0, im: 0.0 }; scratch_length];
+        let channels = channels
+            .iter()
+            .map(|samples| {
+                let scaled: Vec<f32> = samples.iter().map(|sample| sample * scale).collect();
+                ConvolutionChannel::new(&scaled, forward.as_ref(), &mut scratch)
+            })
+            .collect();
+        self.engine = Some(ConvolutionEngine { forward, inverse, scratch, channels, position: 0, history_index: 0 });
+    }
+
+    fn latency(&self) -> usize {
+        match &self.impulse {
+            Some(impulse) if impulse.frames() > 0 => PARTITION_SIZE,
+            _ => 0, // Without an impulse response the input passes straight through
+        }
+    }
+}
@@ -0,0 +1,324 @@
+use serde::{Serialize, Deserialize};
+use crate::effects::{db_to_gain, Effect, EffectContext};
+
+/// Filter taps per unit of oversampling factor. The anti-aliasing filters then delay the signal
+/// by the same 16 samples at every factor.
+const TAPS_PER_FACTOR: usize = 16;
+
+/// How many times faster than the stream a nonlinear stage runs.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Oversampling {
+    Off, // Shape at the stream rate
+    X2,  // Shape at twice the stream rate
+    X4,  // Shape at four times the stream rate
+}
+
+impl Oversampling {
+    /// Every oversampling setting, in display order.
+    pub const ALL: [Oversampling; 3] = [Oversampling::Off, Oversampling::X2, Oversampling::X4];
+
+    /// Returns the rate multiplier.
+    pub fn factor(&self) -> usize {
+        match self {
+            Oversampling::Off => 1,
+            Oversampling::X2 => 2,
+            Oversampling::X4 => 4,
+        }
+    }
+
+    /// Returns the delay added by the anti-aliasing filters, in samples at the stream rate.
+    pub fn latency(&self) -> usize {
+        match self {
+            Oversampling::Off => 0,
+            Oversampling::X2 | Oversampling::X4 => TAPS_PER_FACTOR, // The up and down filters each delay by half their length
+        }
+    }
+}
+
+/// Linear-phase FIR lowpass with a doubled history buffer, so the window is always contiguous.
+#[derive(Clone, Debug)]
+struct FirFilter {
+    taps: Vec<f32>,
+    history: Vec<f32>, // Twice the number of taps; each sample is written to both halves
+    index: usize,      // Oldest position of the window
+}
+
+impl FirFilter {
+    /// Designs a Blackman-windowed sinc lowpass.
+    ///
+    /// # Parameters
+    /// - `length`: The number of taps, odd so the delay is a whole number of samples.
+    /// - `cutoff`: The cutoff as a fraction of the filter's sample rate.
+    fn lowpass(length: usize, cutoff: f64) -> Self {
+        let middle = (length - 1) as f64 / 2.0;
+        let mut taps: Vec<f64> = (0..length)
+            .map(|i| {
+                let x = i as f64 - middle;
+                let sinc = if x == 0.0 { 1.0 } else { (2.0 * std::f64::consts::PI * cutoff * x).sin() / (std::f64::consts::PI * x) / (2.0 * cutoff) };
+                let phase = 2.0 * std::f64::consts::PI * i as f64 / (length - 1) as f64;
+                let window = 0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos();
+                sinc * window
+            })
+            .collect();
+        let sum: f64 = taps.iter().sum();
+        for tap in &mut taps {
+            *tap /= sum; // Unity gain at DC
+        }
+        FirFilter { taps: taps.into_iter().map(|tap| tap as f32).collect(), history: vec![0.0; 2 * length], index: 0 }
+    }
+
+    fn push(&mut self, input: f32) {
+        let length = self.taps.len();
+        self.history[self.index] = input;
+        self.history[self.index + length] = input;
+        self.index = (self.index + 1) % length;
+    }
+
+    fn output(&self) -> f32 {
+        let window = &self.history[self.index..self.index + self.taps.len()];
+        window.iter().zip(&self.taps).map(|(sample, tap)| sample * tap).sum() // Symmetric taps, so the order does not matter
+    }
+}
+
+/// Runs a waveshaping function on one channel at a higher rate, filtering out the harmonics
+/// above the stream's Nyquist frequency before they fold back as aliasing.
+#[derive(Clone, Debug)]
+struct Oversampler {
+    factor: usize,
+    up: FirFilter,   // Interpolates the zero-stuffed input
+    down: FirFilter, // Removes the harmonics before decimation
+}
+
+impl Oversampler {
+    fn new(factor: usize) -> Self {
+        let length = TAPS_PER_FACTOR * factor + 1;
+        let cutoff = 0.45 / factor as f64; // Just below the stream's Nyquist frequency
+        Oversampler { factor, up: FirFilter::lowpass(length, cutoff), down: FirFilter::lowpass(length, cutoff) }
+    }
+
+    fn process(&mut self, input: f32, shape: &impl Fn(f32) -> f32) -> f32 {
+        let mut output = 0.0;
+        for step in 0..self.factor {
+            let stuffed = if step == 0 { input * self.factor as f32 } else { 0.0 }; // Zero-stuffing spreads the energy over `factor` samples
+            self.up.push(stuffed);
+            self.down.push(shape(self.up.output()));
+            if step == 0 {
+                output = self.down.output(); // Keep the samples in step with the input, so the delay is whole
+            }
+        }
+        output
+    }
+}
+
+/// Shapes a block through a nonlinear function, oversampled when requested.
+fn process_shaped(
+    oversamplers: &mut Vec<Oversampler>,
+    oversampling: Oversampling,
+    left: &mut [f32],
+    right: &mut [f32],
+    shape: impl Fn(f32) -> f32,
+) {
+    let factor = oversampling.factor();
+    if factor == 1 {
+        oversamplers.clear();
+        for sample in left.iter_mut().chain(right.iter_mut()) {
+            *sample = shape(*sample);
+        }
+        return;
+    }
+    if oversamplers.first().map(|oversampler| oversampler.factor) != Some(factor) {
+        *oversamplers = vec![Oversampler::new(factor), Oversampler::new(factor)]; // Only when the setting changes
+    }
+    for (oversampler, channel) in oversamplers.iter_mut().zip([left, right]) {
+        for sample in channel {
+            *sample = oversampler.process(*sample, &shape);
+        }
+    }
+}
+
+/// Transfer curve of the saturation effect.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum SaturationMode {
+    Tanh,     // Smooth tape-like saturation
+    SoftClip, // Cubic curve that reaches full level at an input of 1.0
+    HardClip, // Flat limit at full level
+    Foldback, // Reflects the signal back down when it passes full level
+}
+
+impl SaturationMode {
+    /// Every saturation mode, in display order.
+    pub const ALL: [SaturationMode; 4] = [SaturationMode::Tanh, SaturationMode::SoftClip, SaturationMode::HardClip, SaturationMode::Foldback];
+
+    /// Applies the transfer curve to one sample.
+    pub fn shape(&self, input: f32) -> f32 {
+        match self {
+            SaturationMode::Tanh => input.tanh(),
+            SaturationMode::SoftClip => {
+                let x = input.clamp(-1.0, 1.0);
+                1.5 * (x - x * x * x / 3.0)
+            }
+            SaturationMode::HardClip => input.clamp(-1.0, 1.0),
+            SaturationMode::Foldback => ((input - 1.0).rem_euclid(4.0) - 2.0).abs() - 1.0, // Triangle fold between -1 and 1
+        }
+    }
+}
+
+/// Drive-controlled saturation and clipping.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Saturation {
+    pub mode: SaturationMode,
+    pub drive: f32,  // Gain into the curve, in dB
+    pub output: f32, // Gain after the curve, in dB
+    pub oversampling: Oversampling,
+    #[serde(skip)]
+    oversamplers: Vec<Oversampler>, // Left and right, built when oversampling is on
+}
+
+impl Default for Saturation {
+    fn default() -> Self {
+        Saturation::new(SaturationMode::Tanh, 6.0)
+    }
+}
+
+impl Saturation {
+    /// Creates a saturation stage with 2x oversampling and no output gain.
+    ///
+    /// # Parameters
+    /// - `mode`: The transfer curve.
+    /// - `drive`: The gain into the curve, in dB.
+    pub fn new(mode: SaturationMode, drive: f32) -> Self {
+        Saturation { mode, drive, output: 0.0, oversampling: Oversampling::X2, oversamplers: Vec::new() }
+    }
+}
+
+impl Effect for Saturation {
+    fn process(&mut self, left: &mut [f32], right: &mut [f32], _context: &EffectContext) {
+        let (mode, drive, output) = (self.mode, db_to_gain(self.drive), db_to_gain(self.output));
+        process_shaped(&mut self.oversamplers, self.oversampling, left, right, |x| mode.shape(x * drive) * output);
+    }
+
+    fn reset(&mut self) {
+        self.oversamplers.clear();
+    }
+
+    fn latency(&self) -> usize {
+        self.oversampling.latency()
+    }
+}
+
+/// Waveshaper with a user-drawn transfer curve.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Waveshaper {
+    pub curve: Vec<f32>, // Output levels at evenly spaced inputs from -1.0 to 1.0
+    pub drive: f32,      // Gain into the curve, in dB
+    pub output: f32,     // Gain after the curve, in dB
+    pub oversampling: Oversampling,
+    #[serde(skip)]
+    oversamplers: Vec<Oversampler>, // Left and right, built when oversampling is on
+}
+
+impl Default for Waveshaper {
+    /// A straight line through nine points, ready to be bent.
+    fn default() -> Self {
+        Waveshaper::new((0..9).map(|i| i as f32 / 4.0 - 1.0).collect())
+    }
+}
+
+impl Waveshaper {
+    /// Creates a waveshaper with 2x oversampling and no drive or output gain.
+    ///
+    /// # Parameters
+    /// - `curve`: Output levels at evenly spaced inputs from -1.0 to 1.0, at least two.
+    pub fn new(curve: Vec<f32>) -> Self {
+        Waveshaper { curve, drive: 0.0, output: 0.0, oversampling: Oversampling::X2, oversamplers: Vec::new() }
+    }
+
+    /// Looks up the curve for one sample, interpolating linearly between points.
+    /// Inputs outside -1.0..1.0 take the value of the nearest end.
+    pub fn shape(&self, input: f32) -> f32 {
+        shape_curve(&self.curve, input)
+    }
+}
+
+fn shape_curve(curve: &[f32], input: f32) -> f32 {
+    match curve.len() {
+        0 => input,
+        1 => curve[0],
+        length => {
+            let position = (input.clamp(-1.0, 1.0) + 1.0) / 2.0 * (length - 1) as f32;
+            let index = (position as usize).min(length - 2);
+            let fraction = position - index as f32;
+            curve[index] + (curve[index + 1] - curve[index]) * fraction
+        }
+    }
+}
+
+impl Effect for Waveshaper {
+    fn process(&mut self, left: &mut [f32], right: &mut [f32], _context: &EffectContext) {
+        let (curve, drive, output) = (&self.curve, db_to_gain(self.drive), db_to_gain(self.output));
+        process_shaped(&mut self.oversamplers, self.oversampling, left, right, |x| shape_curve(curve, x * drive) * output);
+    }
+
+    fn reset(&mut self) {
+        self.oversamplers.clear();
+    }
+
+    fn latency(&self) -> usize {
+        self.oversampling.latency()
+    }
+}
+
+/// Lo-fi effect that reduces the bit depth and holds samples to lower the sample rate.
+///
+/// Not oversampled: the aliasing of the rate reduction is the sound it is used for.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Bitcrusher {
+    pub bits: f32, // Bit depth of the output, from 1.0 to 24.0; fractional values step smoothly
+    pub rate: f32, // Rate at which new samples are taken, in Hz
+    #[serde(skip)]
+    held: [f32; 2], // Current held samples
+    #[serde(skip)]
+    countdown: f32, // Fraction of a held sample left before the next one is taken
+}
+
+impl Default for Bitcrusher {
+    fn default() -> Self {
+        Bitcrusher::new(8.0, 11025.0)
+    }
+}
+
+impl Bitcrusher {
+    /// Creates a bitcrusher.
+    ///
+    /// # Parameters
+    /// - `bits`: The output bit depth.
+    /// - `rate`: The rate at which new samples are taken, in Hz.
+    pub fn new(bits: f32, rate: f32) -> Self {
+        Bitcrusher { bits, rate, held: [0.0; 2], countdown: 0.0 }
+    }
+
+    /// Rounds a sample to the nearest level of the bit depth.
+    pub fn quantize(&self, input: f32) -> f32 {
+        let levels = 2f32.powf(self.bits.clamp(1.0, 24.0) - 1.0); // Steps on each side of zero
+        (input * levels).round() / levels
+    }
+}
+
+impl Effect for Bitcrusher {
+    fn process(&mut self, left: &mut [f32], right: &mut [f32], context: &EffectContext) {
+        let step = (self.rate / context.sample_rate).clamp(0.0, 1.0);
+        for (left, right) in left.iter_mut().zip(right.iter_mut()) {
+            if self.countdown <= 0.0 {
+                self.held = [self.quantize(*left), self.quantize(*right)];
+                self.countdown += 1.0;
+            }
+            self.countdown -= step;
+            (*left, *right) = (self.held[0], self.held[1]);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.held = [0.0; 2];
+        self.countdown = 0.0;
+    }
+}
@@ -0,0 +1,305 @@
+use serde::{Serialize, Deserialize};
+use crate::effects::{db_to_gain, gain_to_db, Effect, EffectContext};
+
+/// Returns the one-pole smoothing coefficient for a time constant in milliseconds.
+fn time_coefficient(milliseconds: f32, sample_rate: f32) -> f32 {
+    (-1.0 / (milliseconds.max(0.01) * 0.001 * sample_rate)).exp()
+}
+
+/// Returns the stereo-linked level of a frame, from the sidechain key when there is one.
+/// Frames past the end of a shorter key count as silence.
+fn key_level(left: f32, right: f32, key: Option<(&[f32], &[f32])>, index: usize) -> f32 {
+    match key {
+        Some((key_left, key_right)) => {
+            let level = |channel: &[f32]| channel.get(index).map_or(0.0, |sample| sample.abs());
+            level(key_left).max(level(key_right))
+        }
+        None => left.abs().max(right.abs()),
+    }
+}
+
+/// Feed-forward compressor with a soft knee and stereo-linked detection.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Compressor {
+    pub threshold: f32, // Level above which the gain is reduced, in dBFS
+    pub ratio: f32,     // Input change in dB for each 1 dB of output change above the threshold
+    pub attack: f32,    // Time to react to a rise in level, in milliseconds
+    pub release: f32,   // Time to recover after the level falls, in milliseconds
+    pub knee: f32,      // Width of the soft knee around the threshold, in dB
+    pub makeup: f32,    // Gain added after compression, in dB
+    #[serde(skip)]
+    gain_reduction: f32, // Smoothed gain reduction in dB
+}
+
+impl Default for Compressor {
+    fn default() -> Self {
+        Compressor { threshold: -18.0, ratio: 4.0, attack: 10.0, release: 100.0, knee: 6.0, makeup: 0.0, gain_reduction: 0.0 }
+    }
+}
+
+impl Compressor {
+    /// Returns the output level for an input level on the static curve, both in dB.
+    pub fn static_curve(&self, level: f32) -> f32 {
+        let over = level - self.threshold;
+        let slope = 1.0 / self.ratio.max(1.0) - 1.0;
+        let knee = self.knee.max(0.0);
+        if 2.0 * over < -knee {
+            level // Below the knee
+        } else if knee > 0.0 && 2.0 * over.abs() <= knee {
+            level + slope * (over + knee / 2.0).powi(2) / (2.0 * knee) // Quadratic blend inside the knee
+        } else {
+            self.threshold + over / self.ratio.max(1.0)
+        }
+    }
+
+    /// Returns the current gain reduction in dB, for metering.
+    pub fn gain_reduction(&self) -> f32 {
+        self.gain_reduction
+    }
+
+    /// Compresses a block, detecting the level on an external sidechain key.
+    ///
+    /// # Parameters
+    /// - `left`, `right`: The audio to compress, in place.
+    /// - `key_left`, `key_right`: The sidechain signal that drives the gain reduction. Frames
+    ///   past the end of the key are treated as silence.
+    /// - `context`: The sample rate and tempo of the stream.
+    pub fn process_sidechain(&mut self, left: &mut [f32], right: &mut [f32], key_left: &[f32], key_right: &[f32], context: &EffectContext) {
+        self.process_frames(left, right, Some((key_left, key_right)), context.sample_rate);
+    }
+
+    fn process_frames(&mut self, left: &mut [f32], right: &mut [f32], key: Option<(&[f32], &[f32])>, sample_rate: f32) {
+        let attack = time_coefficient(self.attack, sample_rate);
+        let release = time_coefficient(self.release, sample_rate);
+        for (index, (left, right)) in left.iter_mut().zip(right.iter_mut()).enumerate() {
+            let level = gain_to_db(key_level(*left, *right, key, index));
+            let target = level - self.static_curve(level);
+            let coefficient = if target > self.gain_reduction { attack } else { release };
+            self.gain_reduction = target + (self.gain_reduction - target) * coefficient;
+            let gain = db_to_gain(self.makeup - self.gain_reduction);
+            *left *= gain;
+            *right *= gain;
+        }
+    }
+}
+
+impl Effect for Compressor {
+    fn process(&mut self, left: &mut [f32], right: &mut [f32], context: &EffectContext) {
+        self.process_frames(left, right, None, context.sample_rate);
+    }
+
+    fn reset(&mut self) {
+        self.gain_reduction = 0.0;
+    }
+}
+
+/// Longest lookahead a limiter's buffers are sized for, in milliseconds.
+pub const MAX_LOOKAHEAD_MS: f32 = 20.0;
+
+/// Brickwall limiter with lookahead.
+///
+/// The gain needed for each incoming sample is held at its minimum across the lookahead window
+/// and then averaged over the same window, so the gain has fully ramped down by the time the
+/// delayed peak reaches the output and no sample ever exceeds the ceiling.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Limiter {
+    pub ceiling: f32,   // Highest output level, in dBFS
+    pub release: f32,   // Time to recover after a peak, in milliseconds
+    pub lookahead: f32, // How far ahead peaks are seen, in milliseconds, up to `MAX_LOOKAHEAD_MS`; this is also the latency
+    pub makeup: f32,    // Gain added before limiting, in dB
+    #[serde(skip)]
+    delay: [Vec<f32>; 2], // Delayed audio, read `lookahead_samples` later; sized for the longest lookahead
+    #[serde(skip)]
+    required: Vec<f32>, // Gain each recent sample needs to stay under the ceiling
+    #[serde(skip)]
+    smoothing: Vec<f32>, // Recent held gains, averaged to shape the attack
+    #[serde(skip)]
+    smoothing_sum: f64, // Running sum of `smoothing`
+    #[serde(skip)]
+    required_index: usize, // Next position written in `required`
+    #[serde(skip)]
+    index: usize, // Next position written in `delay` and `smoothing`
+    #[serde(skip)]
+    released_gain: f32, // Held gain after the release has been applied
+    #[serde(skip)]
+    gain: f32, // Gain applied to the last output sample, for metering
+    #[serde(skip)]
+    sample_rate: f32, // Rate the buffers were sized for
+    #[serde(skip)]
+    lookahead_samples: usize, // Part of the buffers in use
+}
+
+impl Default for Limiter {
+    fn default() -> Self {
+        Limiter::new(-0.3)
+    }
+}
+
+impl Limiter {
+    /// Creates a limiter with a 5 ms lookahead and a 100 ms release.
+    ///
+    /// # Parameters
+    /// - `ceiling`: The highest output level, in dBFS.
+    pub fn new(ceiling: f32) -> Self {
+        Limiter {
+            ceiling,
+            release: 100.0,
+            lookahead: 5.0,
+            makeup: 0.0,
+            delay: [Vec::new(), Vec::new()],
+            required: Vec::new(),
+            smoothing: Vec::new(),
+            smoothing_sum: 0.0,
+            required_index: 0,
+            index: 0,
+            released_gain: 1.0,
+            gain: 1.0,
+            sample_rate: 0.0,
+            lookahead_samples: 0,
+        }
+    }
+
+    /// Returns the current gain reduction in dB, for metering.
+    pub fn gain_reduction(&self) -> f32 {
+        -gain_to_db(self.gain)
+    }
+
+    /// Returns the lookahead in samples at the prepared rate, clamped to the buffer size.
+    fn window_length(&self) -> usize {
+        ((self.lookahead.clamp(0.0, MAX_LOOKAHEAD_MS) * 0.001 * self.sample_rate) as usize).max(1)
+    }
+
+    /// Clears the buffers and restarts the window at the current lookahead, without allocating.
+    fn clear(&mut self) {
+        let length = self.window_length();
+        for channel in &mut self.delay {
+            channel.fill(0.0);
+        }
+        self.required.fill(1.0);
+        self.smoothing.fill(1.0);
+        self.smoothing_sum = length as f64;
+        self.required_index = 0;
+        self.index = 0;
+        self.released_gain = 1.0;
+        self.gain = 1.0;
+        self.lookahead_samples = length;
+    }
+}
+
+impl Effect for Limiter {
+    fn process(&mut self, left: &mut [f32], right: &mut [f32], context: &EffectContext) {
+        if self.delay[0].is_empty() {
+            return; // Not prepared yet
+        }
+        let length = self.window_length();
+        if self.lookahead_samples != length {
+            self.clear(); // The buffers already hold the longest lookahead
+        }
+        let ceiling = db_to_gain(self.ceiling);
+        let makeup = db_to_gain(self.makeup);
+        let release = time_coefficient(self.release, context.sample_rate);
+
+        for (left, right) in left.iter_mut().zip(right.iter_mut()) {
+            let (input_left, input_right) = (*left * makeup, *right * makeup);
+            let peak = input_left.abs().max(input_right.abs());
+            let required = if peak > ceiling { ceiling / peak } else { 1.0 };
+            self.required[self.required_index] = required;
+            self.required_index = (self.required_index + 1) % (length + 1); // The window includes the current sample
+            let held = self.required[..=length].iter().copied().fold(1.0, f32::min); // Lowest gain needed within the lookahead
+
+            // Drop instantly, recover with the release time
+            self.released_gain = if held < self.released_gain { held } else { held + (self.released_gain - held) * release };
+
+            let slot = self.index;
+            self.smoothing_sum += (self.released_gain - self.smoothing[slot]) as f64;
+            self.smoothing[slot] = self.released_gain;
+            self.gain = (self.smoothing_sum / length as f64) as f32;
+
+            let delayed_left = std::mem::replace(&mut self.delay[0][slot], input_left);
+            let delayed_right = std::mem::replace(&mut self.delay[1][slot], input_right);
+            *left = (delayed_left * self.gain).clamp(-ceiling, ceiling); // The clamp only catches rounding error
+            *right = (delayed_right * self.gain).clamp(-ceiling, ceiling);
+            self.index = (self.index + 1) % length;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.clear();
+    }
+
+    fn prepare(&mut self, sample_rate: f32) {
+        let length = ((MAX_LOOKAHEAD_MS * 0.001 * sample_rate) as usize).max(1);
+        self.delay = [vec![0.0; length], vec![0.0; length]];
+        self.required = vec![1.0; length + 1];
+        self.smoothing = vec![1.0; length];
+        self.sample_rate = sample_rate;
+        self.clear();
+    }
+
+    fn latency(&self) -> usize {
+        self.lookahead_samples // Known once the limiter is prepared
+    }
+}
+
+/// Noise gate that attenuates the signal while it stays below the threshold.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Gate {
+    pub threshold: f32, // Level at which the gate opens, in dBFS
+    pub range: f32,     // Attenuation while closed, in dB
+    pub attack: f32,    // Time to open, in milliseconds
+    pub hold: f32,      // Time the gate stays open after the level drops, in milliseconds
+    pub release: f32,   // Time to close, in milliseconds
+    #[serde(skip)]
+    gain: f32, // Smoothed gain
+    #[serde(skip)]
+    hold_remaining: f32, // Seconds left before the gate may close
+}
+
+impl Default for Gate {
+    fn default() -> Self {
+        Gate { threshold: -50.0, range: 80.0, attack: 1.0, hold: 20.0, release: 100.0, gain: 0.0, hold_remaining: 0.0 }
+    }
+}
+
+impl Gate {
+    /// Gates a block, opening on the level of an external sidechain key.
+    ///
+    /// # Parameters
+    /// - `left`, `right`: The audio to gate, in place.
+    /// - `key_left`, `key_right`: The sidechain signal that opens the gate. Frames past the end
+    ///   of the key are treated as silence.
+    /// - `context`: The sample rate and tempo of the stream.
+    pub fn process_sidechain(&mut self, left: &mut [f32], right: &mut [f32], key_left: &[f32], key_right: &[f32], context: &EffectContext) {
+        self.process_frames(left, right, Some((key_left, key_right)), context.sample_rate);
+    }
+
+    fn process_frames(&mut self, left: &mut [f32], right: &mut [f32], key: Option<(&[f32], &[f32])>, sample_rate: f32) {
+        let attack = time_coefficient(self.attack, sample_rate);
+        let release = time_coefficient(self.release, sample_rate);
+        let threshold = db_to_gain(self.threshold);
+        let closed = db_to_gain(-self.range.max(0.0));
+        for (index, (left, right)) in left.iter_mut().zip(right.iter_mut()).enumerate() {
+            if key_level(*left, *right, key, index) >= threshold {
+                self.hold_remaining = self.hold * 0.001; // Zero crossings must not close the gate
+            } else {
+                self.hold_remaining -= 1.0 / sample_rate;
+            }
+            let target = if self.hold_remaining > 0.0 { 1.0 } else { closed };
+            let coefficient = if target > self.gain { attack } else { release };
+            self.gain = target + (self.gain - target) * coefficient;
+            *left *= self.gain;
+            *right *= self.gain;
+        }
+    }
+}
+
+impl Effect for Gate {
+    fn process(&mut self, left: &mut [f32], right: &mut [f32], context: &EffectContext) {
+        self.process_frames(left, right, None, context.sample_rate);
+    }
+
+    fn reset(&mut self) {
+        self.gain = 0.0;
+        self.hold_remaining = 0.0;
+    }
+}
@@ -1,28 +1,1043 @@
-pub struct Effects {
-    pub reverb: f32, // Reverb effect intensity, ranges from 0.0 (no effect) to 1.0 (maximum effect)
-    pub delay: f32,  // Delay effect intensity, ranges from 0.0 (no effect) to 1.0 (maximum effect)
+use serde::{Serialize, Deserialize};
+use crate::convolution::Convolution;
+use crate::distortion::{Bitcrusher, Saturation, Waveshaper};
+use crate::dynamics::{Compressor, Gate, Limiter};
+use crate::eq::Equalizer;
+use crate::modulation::LfoRate;
+
+/// Converts a level in decibels to a linear gain.
+pub fn db_to_gain(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+/// Converts a linear gain to decibels. Silence maps to -180 dB rather than negative infinity.
+pub fn gain_to_db(gain: f32) -> f32 {
+    20.0 * gain.abs().max(1e-9).log10()
+}
+
+/// Information an effect needs about the stream it is processing.
+#[derive(Clone, Copy, Debug)]
+pub struct EffectContext {
+    pub sample_rate: f32, // Sample rate in Hz
+    pub tempo: f32,       // Project tempo in beats per minute, for tempo-synced effects
 }
 
-impl Effects {
-    /// Applies the reverb and delay effects to a given audio sample.
-    /// 
+/// Stateful audio processor that can be placed in an `EffectChain`.
+pub trait Effect {
+    /// Processes a block of stereo audio in place.
+    ///
     /// # Parameters
-    /// - `sample`: The input audio sample to which effects will be applied.
-    /// 
+    /// - `left`, `right`: The channel buffers, of equal length.
+    /// - `context`: The sample rate and tempo of the stream.
+    fn process(&mut self, left: &mut [f32], right: &mut [f32], context: &EffectContext);
+
+    /// Clears the internal state, such as delay lines and reverb tails.
+    fn reset(&mut self);
+
+    /// Sizes buffers and tunes filters for a sample rate. Chains call this when the rate is
+    /// set, outside the audio callback, so that `process` does not allocate.
+    fn prepare(&mut self, _sample_rate: f32) {}
+
+    /// Returns how many samples the effect delays its output by.
+    fn latency(&self) -> usize {
+        0
+    }
+}
+
+/// Every effect that can be placed in a chain.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum EffectKind {
+    Delay(Delay),
+    Reverb(Reverb),
+    Convolution(Convolution),
+    Compressor(Compressor),
+    Limiter(Limiter),
+    Gate(Gate),
+    Equalizer(Equalizer),
+    Chorus(Chorus),
+    Flanger(Flanger),
+    Phaser(Phaser),
+    Tremolo(Tremolo),
+    AutoPan(AutoPan),
+    Saturation(Saturation),
+    Waveshaper(Waveshaper),
+    Bitcrusher(Bitcrusher),
+}
+
+impl EffectKind {
+    /// Returns the display name of the effect.
+    pub fn name(&self) -> &'static str {
+        match self {
+            EffectKind::Delay(_) => "Delay",
+            EffectKind::Reverb(_) => "Reverb",
+            EffectKind::Convolution(_) => "Convolution",
+            EffectKind::Compressor(_) => "Compressor",
+            EffectKind::Limiter(_) => "Limiter",
+            EffectKind::Gate(_) => "Gate",
+            EffectKind::Equalizer(_) => "EQ",
+            EffectKind::Chorus(_) => "Chorus",
+            EffectKind::Flanger(_) => "Flanger",
+            EffectKind::Phaser(_) => "Phaser",
+            EffectKind::Tremolo(_) => "Tremolo",
+            EffectKind::AutoPan(_) => "Auto-Pan",
+            EffectKind::Saturation(_) => "Saturation",
+            EffectKind::Waveshaper(_) => "Waveshaper",
+            EffectKind::Bitcrusher(_) => "Bitcrusher",
+        }
+    }
+
+    /// Returns the effect's own wet/dry mix, for effects that have one.
+    pub fn mix_mut(&mut self) -> Option<&mut f32> {
+        match self {
+            EffectKind::Delay(delay) => Some(&mut delay.mix),
+            EffectKind::Reverb(reverb) => Some(&mut reverb.mix),
+            EffectKind::Convolution(convolution) => Some(&mut convolution.mix),
+            EffectKind::Chorus(chorus) => Some(&mut chorus.mix),
+            EffectKind::Flanger(flanger) => Some(&mut flanger.mix),
+            EffectKind::Phaser(phaser) => Some(&mut phaser.mix),
+            EffectKind::Tremolo(tremolo) => Some(&mut tremolo.mix),
+            EffectKind::AutoPan(auto_pan) => Some(&mut auto_pan.mix),
+            EffectKind::Compressor(_)
+            | EffectKind::Limiter(_)
+            | EffectKind::Gate(_)
+            | EffectKind::Equalizer(_)
+            | EffectKind::Saturation(_)
+            | EffectKind::Waveshaper(_)
+            | EffectKind::Bitcrusher(_) => None, // Use the slot mix for parallel processing
+        }
+    }
+
+    /// Returns `true` for effects that can follow a sidechain key instead of their own input.
+    pub fn accepts_sidechain(&self) -> bool {
+        matches!(self, EffectKind::Compressor(_) | EffectKind::Gate(_))
+    }
+
+    /// Processes a block, detecting the level on the sidechain key when the effect accepts one.
+    fn process_keyed(&mut self, left: &mut [f32], right: &mut [f32], key: Option<(&[f32], &[f32])>, context: &EffectContext) {
+        match (self, key) {
+            (EffectKind::Compressor(compressor), Some((key_left, key_right))) => {
+                compressor.process_sidechain(left, right, key_left, key_right, context)
+            }
+            (EffectKind::Gate(gate), Some((key_left, key_right))) => gate.process_sidechain(left, right, key_left, key_right, context),
+            (effect, _) => effect.process(left, right, context),
+        }
+    }
+
+    fn as_effect(&self) -> &dyn Effect {
+        match self {
+            EffectKind::Delay(delay) => delay,
+            EffectKind::Reverb(reverb) => reverb,
+            EffectKind::Convolution(convolution) => convolution,
+            EffectKind::Compressor(compressor) => compressor,
+            EffectKind::Limiter(limiter) => limiter,
+            EffectKind::Gate(gate) => gate,
+            EffectKind::Equalizer(equalizer) => equalizer,
+            EffectKind::Chorus(chorus) => chorus,
+            EffectKind::Flanger(flanger) => flanger,
+            EffectKind::Phaser(phaser) => phaser,
+            EffectKind::Tremolo(tremolo) => tremolo,
+            EffectKind::AutoPan(auto_pan) => auto_pan,
+            EffectKind::Saturation(saturation) => saturation,
+            EffectKind::Waveshaper(waveshaper) => waveshaper,
+            EffectKind::Bitcrusher(bitcrusher) => bitcrusher,
+        }
+    }
+
+    fn as_effect_mut(&mut self) -> &mut dyn Effect {
+        match self {
+            EffectKind::Delay(delay) => delay,
+            EffectKind::Reverb(reverb) => reverb,
+            EffectKind::Convolution(convolution) => convolution,
+            EffectKind::Compressor(compressor) => compressor,
+            EffectKind::Limiter(limiter) => limiter,
+            EffectKind::Gate(gate) => gate,
+            EffectKind::Equalizer(equalizer) => equalizer,
+            EffectKind::Chorus(chorus) => chorus,
+            EffectKind::Flanger(flanger) => flanger,
+            EffectKind::Phaser(phaser) => phaser,
+            EffectKind::Tremolo(tremolo) => tremolo,
+            EffectKind::AutoPan(auto_pan) => auto_pan,
+            EffectKind::Saturation(saturation) => saturation,
+            EffectKind::Waveshaper(waveshaper) => waveshaper,
+            EffectKind::Bitcrusher(bitcrusher) => bitcrusher,
+        }
+    }
+}
+
+impl Effect for EffectKind {
+    fn process(&mut self, left: &mut [f32], right: &mut [f32], context: &EffectContext) {
+        self.as_effect_mut().process(left, right, context);
+    }
+
+    fn reset(&mut self) {
+        self.as_effect_mut().reset();
+    }
+
+    fn prepare(&mut self, sample_rate: f32) {
+        self.as_effect_mut().prepare(sample_rate);
+    }
+
+    fn latency(&self) -> usize {
+        self.as_effect().latency()
+    }
+}
+
+/// Position in an effect chain, with its own bypass and wet/dry balance.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EffectSlot {
+    pub effect: EffectKind,
+    pub bypass: bool, // Skip the effect entirely
+    pub mix: f32,     // Wet/dry balance of the slot, from 0.0 (dry) to 1.0 (only the effect)
+    #[serde(default)]
+    pub sidechain: String, // ID of the mixer track whose source keys the effect; empty to follow the input
+    #[serde(skip)]
+    key: Option<usize>, // Index of the key track, resolved by `EffectChain::resolve_sidechains`
+    #[serde(skip)]
+    dry: [Vec<f32>; 2], // Copy of the input block, mixed back in after processing
+    #[serde(skip)]
+    dry_delay: [Vec<f32>; 2], // Delays the dry signal by the effect latency so the two line up
+    #[serde(skip)]
+    dry_delay_index: usize, // Next position written in `dry_delay`
+}
+
+impl EffectSlot {
+    /// Creates an active, fully wet slot.
+    pub fn new(effect: EffectKind) -> Self {
+        EffectSlot {
+            effect,
+            bypass: false,
+            mix: 1.0,
+            sidechain: String::new(),
+            key: None,
+            dry: [Vec::new(), Vec::new()],
+            dry_delay: [Vec::new(), Vec::new()],
+            dry_delay_index: 0,
+        }
+    }
+
+    /// Prepares the effect for a sample rate and sizes the dry delay for its latency.
+    fn prepare(&mut self, sample_rate: f32) {
+        self.effect.prepare(sample_rate);
+        let latency = self.effect.latency();
+        self.dry_delay = [vec![0.0; latency], vec![0.0; latency]];
+        self.dry_delay_index = 0;
+    }
+
+    /// Processes a block through the effect and blends it with the dry input.
+    fn process(&mut self, left: &mut [f32], right: &mut [f32], key: Option<(&[f32], &[f32])>, context: &EffectContext) {
+        if self.bypass {
+            return;
+        }
+        let mix = self.mix.clamp(0.0, 1.0);
+        if mix >= 1.0 {
+            self.effect.process_keyed(left, right, key, context);
+            return;
+        }
+
+        for (dry, input) in self.dry.iter_mut().zip([&*left, &*right]) {
+            dry.clear();
+            dry.extend_from_slice(input); // Keeps its capacity, so only the first blocks allocate
+        }
+        let latency = self.dry_delay[0].len(); // Resized by `prepare`, never on the audio thread
+        if latency > 0 {
+            for i in 0..left.len() {
+                for (dry, delay) in self.dry.iter_mut().zip(self.dry_delay.iter_mut()) {
+                    std::mem::swap(&mut dry[i], &mut delay[self.dry_delay_index]);
+                }
+                self.dry_delay_index = (self.dry_delay_index + 1) % latency;
+            }
+        }
+
+        self.effect.process_keyed(left, right, key, context);
+        for (output, dry) in [left, right].into_iter().zip(&self.dry) {
+            for (sample, dry) in output.iter_mut().zip(dry) {
+                *sample = dry * (1.0 - mix) + *sample * mix;
+            }
+        }
+    }
+}
+
+/// Ordered list of effects applied one after another.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EffectChain {
+    pub slots: Vec<EffectSlot>,
+    #[serde(skip)]
+    sample_rate: f32, // Rate the slots were prepared for, used for effects added later
+}
+
+impl Default for EffectChain {
+    /// A delay followed by a reverb, both silent until their mix is raised.
+    fn default() -> Self {
+        EffectChain {
+            slots: vec![
+                EffectSlot::new(EffectKind::Delay(Delay::default())),
+                EffectSlot::new(EffectKind::Reverb(Reverb::default())),
+            ],
+            sample_rate: 0.0,
+        }
+    }
+}
+
+impl EffectChain {
+    /// Creates an empty chain.
+    pub fn new() -> Self {
+        EffectChain { slots: Vec::new(), sample_rate: 0.0 }
+    }
+
+    /// Prepares every effect for a sample rate. Effects added afterwards are prepared at the
+    /// same rate; until a chain is prepared, its delays and reverbs pass their input through.
+    pub fn prepare(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        for slot in &mut self.slots {
+            slot.prepare(sample_rate);
+        }
+    }
+
+    /// Prepares one slot again after a setting that changes its buffer sizes or latency, such as
+    /// a limiter's lookahead. Call it outside the audio callback, since it allocates.
+    pub fn prepare_slot(&mut self, index: usize) {
+        if self.sample_rate > 0.0 {
+            if let Some(slot) = self.slots.get_mut(index) {
+                slot.prepare(self.sample_rate);
+            }
+        }
+    }
+
+    /// Appends an effect to the end of the chain.
+    pub fn add(&mut self, effect: EffectKind) {
+        let mut slot = EffectSlot::new(effect);
+        if self.sample_rate > 0.0 {
+            slot.prepare(self.sample_rate);
+        }
+        self.slots.push(slot);
+    }
+
+    /// Removes the effect at `index`, if there is one.
+    pub fn remove(&mut self, index: usize) {
+        if index < self.slots.len() {
+            self.slots.remove(index);
+        }
+    }
+
+    /// Moves the effect at `from` so that it ends up at position `to`.
+    pub fn move_slot(&mut self, from: usize, to: usize) {
+        if from < self.slots.len() && to < self.slots.len() {
+            let slot = self.slots.remove(from);
+            self.slots.insert(to, slot);
+        }
+    }
+
+    /// Processes a block of stereo audio through every active slot in order. Slots with a
+    /// sidechain follow their own input instead.
+    pub fn process(&mut self, left: &mut [f32], right: &mut [f32], context: &EffectContext) {
+        self.process_keyed(left, right, &[], context);
+    }
+
+    /// Processes a block like `process`, feeding each slot with a sidechain its key track.
+    ///
+    /// # Parameters
+    /// - `keys`: The left and right key signal of each mixer track, by track index.
+    pub fn process_keyed(&mut self, left: &mut [f32], right: &mut [f32], keys: &[[Vec<f32>; 2]], context: &EffectContext) {
+        for slot in &mut self.slots {
+            let key = slot.key.and_then(|index| keys.get(index)).map(|[key_left, key_right]| (&key_left[..], &key_right[..]));
+            slot.process(left, right, key, context);
+        }
+    }
+
+    /// Looks up the key track of every slot with a sidechain, by its ID.
+    ///
     /// # Returns
-    /// - The modified audio sample after applying the effects.
+    /// - `true` if any slot follows a key track.
+    pub fn resolve_sidechains(&mut self, find_track: impl Fn(&str) -> Option<usize>) -> bool {
+        let mut keyed = false;
+        for slot in &mut self.slots {
+            let wanted = !slot.sidechain.is_empty() && slot.effect.accepts_sidechain();
+            slot.key = if wanted { find_track(&slot.sidechain) } else { None };
+            keyed |= slot.key.is_some();
+        }
+        keyed
+    }
+
+    /// Clears the state of every effect.
+    pub fn reset(&mut self) {
+        for slot in &mut self.slots {
+            slot.effect.reset();
+        }
+    }
+
+    /// Returns the wet/dry mix of the first effect with the given name, ignoring case.
+    pub fn find_mix(&mut self, name: &str) -> Option<&mut f32> {
+        self.slots
+            .iter_mut()
+            .find(|slot| slot.effect.name().eq_ignore_ascii_case(name))
+            .and_then(|slot| slot.effect.mix_mut())
+    }
+
+    /// Returns the total latency of the active slots, in samples.
+    pub fn latency(&self) -> usize {
+        self.slots.iter().filter(|slot| !slot.bypass).map(|slot| slot.effect.latency()).sum()
+    }
+}
+
+/// Longest delay time the delay line can hold, in seconds.
+const MAX_DELAY_SECONDS: f32 = 4.0;
+
+/// Time constant of the glide towards a new delay time, in seconds.
+const DELAY_SMOOTHING_SECONDS: f32 = 0.2;
+
+/// Length of a delay.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum DelayTime {
+    Milliseconds(f32), // Free-running time
+    Synced(f32),       // Length in beats, following the project tempo
+}
+
+impl DelayTime {
+    /// Returns the delay in seconds for the given tempo in beats per minute.
+    pub fn seconds(&self, tempo: f32) -> f32 {
+        let seconds = match *self {
+            DelayTime::Milliseconds(ms) => ms / 1000.0,
+            DelayTime::Synced(beats) => beats * 60.0 / tempo.max(1.0),
+        };
+        seconds.clamp(0.0, MAX_DELAY_SECONDS)
+    }
+}
+
+/// Stereo feedback delay built on a pair of circular buffers.
+///
+/// Changes to the delay time glide smoothly, which bends the pitch of the echoes like a tape
+/// delay rather than jumping to a new read position and clicking.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Delay {
+    pub time: DelayTime,
+    pub feedback: f32,    // Portion of each echo fed back into the line, from 0.0 to 0.95
+    pub mix: f32,         // Wet/dry balance, from 0.0 (dry) to 1.0 (only echoes)
+    pub ping_pong: bool,  // Bounce echoes between the left and right channels
+    #[serde(skip)]
+    buffers: [Vec<f32>; 2], // Circular buffers for the left and right channels
+    #[serde(skip)]
+    write_index: usize,   // Next position written in both buffers
+    #[serde(skip)]
+    current_delay: Option<f32>, // Smoothed delay in samples; `None` starts straight at the set time
+}
+
+impl Default for Delay {
+    fn default() -> Self {
+        Delay::new(DelayTime::Milliseconds(250.0), 0.4, 0.0)
+    }
+}
+
+impl Delay {
+    /// Creates a delay. The buffers are allocated by `prepare`, once the sample rate is known;
+    /// until then the delay passes its input through.
+    ///
+    /// # Parameters
+    /// - `time`: The delay time.
+    /// - `feedback`: The portion of each echo fed back, from 0.0 to 0.95.
+    /// - `mix`: The wet/dry balance, from 0.0 to 1.0.
+    pub fn new(time: DelayTime, feedback: f32, mix: f32) -> Self {
+        Delay {
+            time,
+            feedback,
+            mix,
+            ping_pong: false,
+            buffers: [Vec::new(), Vec::new()],
+            write_index: 0,
+            current_delay: None,
+        }
+    }
+
+    /// Processes one stereo sample.
+    ///
+    /// # Parameters
+    /// - `left`, `right`: The input samples.
+    /// - `sample_rate`: The sample rate in Hz.
+    /// - `tempo`: The project tempo in beats per minute, used by synced delay times.
     ///
-    /// # Notes
-    /// - The reverb effect is simplified as a scaled version of the sample. In a real-world implementation, the reverb effect would be more complex, e.g. using a digital signal processing algorithm.
-    /// - The delay effect is also simplified as a scaled version of the sample. In a real-world implementation, the delay effect would use a circular buffer to store the audio samples and then read them back with a delay.
-    pub fn apply(&self, sample: f32) -> f32 {
-        // Apply the reverb effect (simplified as a scaled version of the sample)
-        let reverb_effect = sample * self.reverb * 0.1;
+    /// # Returns
+    /// - The `(left, right)` output samples.
+    pub fn process_frame(&mut self, left: f32, right: f32, sample_rate: f32, tempo: f32) -> (f32, f32) {
+        let length = self.buffers[0].len();
+        if length == 0 {
+            return (left, right); // Not prepared yet
+        }
+        let target = (self.time.seconds(tempo) * sample_rate).min((length - 2) as f32); // Stay within the buffers
+        let current = self.current_delay.unwrap_or(target);
+        let smoothing = 1.0 - (-1.0 / (DELAY_SMOOTHING_SECONDS * sample_rate)).exp();
+        let current = current + (target - current) * smoothing;
+        self.current_delay = Some(current);
+
+        let delay = current.max(1.0); // At least one sample, so the echo is never read before it is written
+        let echo_left = self.read(0, delay);
+        let echo_right = self.read(1, delay);
+        let feedback = self.feedback.clamp(0.0, 0.95);
+        let (write_left, write_right) = if self.ping_pong {
+            // The input enters on the left, and each echo crosses to the other side
+            ((left + right) * 0.5 + echo_right * feedback, echo_left * feedback)
+        } else {
+            (left + echo_left * feedback, right + echo_right * feedback)
+        };
+        self.buffers[0][self.write_index] = write_left;
+        self.buffers[1][self.write_index] = write_right;
+        self.write_index = (self.write_index + 1) % length;
+
+        let mix = self.mix.clamp(0.0, 1.0);
+        (left * (1.0 - mix) + echo_left * mix, right * (1.0 - mix) + echo_right * mix)
+    }
+
+    /// Reads a buffer `delay` samples behind the write position, with linear interpolation.
+    fn read(&self, channel: usize, delay: f32) -> f32 {
+        let buffer = &self.buffers[channel];
+        let length = buffer.len();
+        let position = self.write_index as f32 - delay;
+        let position = position.rem_euclid(length as f32);
+        let index = position as usize % length;
+        let next = (index + 1) % length;
+        let fraction = position - position.floor();
+        buffer[index] + (buffer[next] - buffer[index]) * fraction
+    }
+}
+
+impl Effect for Delay {
+    fn process(&mut self, left: &mut [f32], right: &mut [f32], context: &EffectContext) {
+        for (left, right) in left.iter_mut().zip(right.iter_mut()) {
+            (*left, *right) = self.process_frame(*left, *right, context.sample_rate, context.tempo);
+        }
+    }
+
+    /// Clears the echoes held in the buffers.
+    fn reset(&mut self) {
+        for buffer in &mut self.buffers {
+            buffer.fill(0.0);
+        }
+        self.write_index = 0;
+        self.current_delay = None; // Jump straight to the delay time on the next sample
+    }
+
+    /// Sizes the buffers to hold the longest delay time.
+    fn prepare(&mut self, sample_rate: f32) {
+        let length = (MAX_DELAY_SECONDS * sample_rate) as usize + 2; // Room for the interpolation neighbour
+        self.buffers = [vec![0.0; length], vec![0.0; length]];
+        self.reset();
+    }
+}
+
+/// Comb filter delay lengths of the Freeverb design, in samples at 44.1 kHz.
+const COMB_TUNINGS: [usize; 8] = [1116, 1188, 1277, 1356, 1422, 1491, 1557, 1617];
+
+/// All-pass filter delay lengths of the Freeverb design, in samples at 44.1 kHz.
+const ALLPASS_TUNINGS: [usize; 4] = [556, 441, 341, 225];
+
+/// Extra delay added to the right channel's filters to decorrelate it from the left.
+const STEREO_SPREAD: usize = 23;
+
+/// Longest pre-delay the reverb can hold, in seconds.
+const MAX_PRE_DELAY_SECONDS: f32 = 0.25;
+
+/// Lowpass-feedback comb filter, the building block of the reverb tail.
+#[derive(Clone, Debug)]
+struct Comb {
+    buffer: Vec<f32>,
+    index: usize,
+    filter_store: f32, // State of the one-pole lowpass in the feedback path
+}
+
+impl Comb {
+    fn new(length: usize) -> Self {
+        Comb { buffer: vec![0.0; length.max(1)], index: 0, filter_store: 0.0 }
+    }
+
+    fn process(&mut self, input: f32, feedback: f32, damping: f32) -> f32 {
+        let output = self.buffer[self.index];
+        self.filter_store = output * (1.0 - damping) + self.filter_store * damping; // Damping darkens the tail over time
+        self.buffer[self.index] = input + self.filter_store * feedback;
+        self.index = (self.index + 1) % self.buffer.len();
+        output
+    }
+
+    fn clear(&mut self) {
+        self.buffer.fill(0.0);
+        self.index = 0;
+        self.filter_store = 0.0;
+    }
+}
 
-        // Apply the delay effect (simplified as a scaled version of the sample)
-        let delay_effect = sample * self.delay * 0.01;
+/// Schroeder all-pass filter that diffuses the comb output.
+#[derive(Clone, Debug)]
+struct Allpass {
+    buffer: Vec<f32>,
+    index: usize,
+}
+
+impl Allpass {
+    fn new(length: usize) -> Self {
+        Allpass { buffer: vec![0.0; length.max(1)], index: 0 }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let delayed = self.buffer[self.index];
+        self.buffer[self.index] = input + delayed * 0.5;
+        self.index = (self.index + 1) % self.buffer.len();
+        delayed - input
+    }
+
+    fn clear(&mut self) {
+        self.buffer.fill(0.0);
+        self.index = 0;
+    }
+}
+
+/// Comb and all-pass filters for one output channel.
+#[derive(Clone, Debug)]
+struct ReverbChannel {
+    combs: Vec<Comb>,
+    allpasses: Vec<Allpass>,
+}
+
+impl ReverbChannel {
+    fn new(sample_rate: f32, spread: usize) -> Self {
+        let scale = |length: usize| ((length + spread) as f32 * sample_rate / 44100.0) as usize; // Keep the tunings in seconds
+        ReverbChannel {
+            combs: COMB_TUNINGS.iter().map(|&length| Comb::new(scale(length))).collect(),
+            allpasses: ALLPASS_TUNINGS.iter().map(|&length| Allpass::new(scale(length))).collect(),
+        }
+    }
+
+    fn process(&mut self, input: f32, feedback: f32, damping: f32) -> f32 {
+        let mut output: f32 = self.combs.iter_mut().map(|comb| comb.process(input, feedback, damping)).sum(); // Parallel combs
+        for allpass in &mut self.allpasses {
+            output = allpass.process(output); // Series all-passes
+        }
+        output
+    }
+
+    fn clear(&mut self) {
+        self.combs.iter_mut().for_each(Comb::clear);
+        self.allpasses.iter_mut().for_each(Allpass::clear);
+    }
+}
+
+/// Freeverb-style stereo reverb: eight parallel lowpass-feedback combs followed by four
+/// series all-passes per channel, with the right channel slightly detuned for width.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Reverb {
+    pub room_size: f32, // Length of the tail, from 0.0 to 1.0
+    pub damping: f32,   // High-frequency absorption, from 0.0 (bright) to 1.0 (dark)
+    pub pre_delay: f32, // Gap before the tail starts, in milliseconds
+    pub width: f32,     // Stereo width of the tail, from 0.0 (mono) to 1.0
+    pub mix: f32,       // Wet/dry balance, from 0.0 (dry) to 1.0 (only reverb)
+    #[serde(skip)]
+    channels: Vec<ReverbChannel>, // Left and right filters, built by `prepare` once the sample rate is known
+    #[serde(skip)]
+    pre_delay_buffer: Vec<f32>,   // Circular buffer for the pre-delay
+    #[serde(skip)]
+    pre_delay_index: usize,       // Next position written in `pre_delay_buffer`
+}
+
+impl Default for Reverb {
+    fn default() -> Self {
+        Reverb::new(0.5, 0.5, 0.0)
+    }
+}
+
+impl Reverb {
+    /// Creates a full-width reverb without pre-delay. The filters are built by `prepare`;
+    /// until then the reverb passes its input through.
+    ///
+    /// # Parameters
+    /// - `room_size`: The length of the tail, from 0.0 to 1.0.
+    /// - `damping`: The high-frequency absorption, from 0.0 to 1.0.
+    /// - `mix`: The wet/dry balance, from 0.0 to 1.0.
+    pub fn new(room_size: f32, damping: f32, mix: f32) -> Self {
+        Reverb {
+            room_size,
+            damping,
+            pre_delay: 0.0,
+            width: 1.0,
+            mix,
+            channels: Vec::new(),
+            pre_delay_buffer: Vec::new(),
+            pre_delay_index: 0,
+        }
+    }
+
+    /// Processes one stereo sample.
+    ///
+    /// # Parameters
+    /// - `left`, `right`: The input samples.
+    /// - `sample_rate`: The sample rate in Hz.
+    ///
+    /// # Returns
+    /// - The `(left, right)` output samples.
+    pub fn process_frame(&mut self, left: f32, right: f32, sample_rate: f32) -> (f32, f32) {
+        if self.channels.is_empty() {
+            return (left, right); // Not prepared yet
+        }
+
+        // The tail is fed a mono sum, delayed by the pre-delay
+        let length = self.pre_delay_buffer.len();
+        self.pre_delay_buffer[self.pre_delay_index] = (left + right) * 0.015; // Freeverb's fixed input gain
+        let delay = ((self.pre_delay / 1000.0 * sample_rate) as usize).min(length - 1);
+        let input = self.pre_delay_buffer[(self.pre_delay_index + length - delay) % length];
+        self.pre_delay_index = (self.pre_delay_index + 1) % length;
+
+        let feedback = self.room_size.clamp(0.0, 1.0) * 0.28 + 0.7;
+        let damping = self.damping.clamp(0.0, 1.0) * 0.4;
+        let wet_left = self.channels[0].process(input, feedback, damping);
+        let wet_right = self.channels[1].process(input, feedback, damping);
+
+        let mix = self.mix.clamp(0.0, 1.0);
+        let width = self.width.clamp(0.0, 1.0);
+        let wet = mix * 3.0; // Freeverb's wet scale brings the tail up to roughly the input level
+        let direct = wet * (0.5 + width / 2.0); // Each side's own tail
+        let cross = wet * (1.0 - width) / 2.0; // Blend of the other side's tail, narrowing the image
+        let dry = 1.0 - mix;
+        (
+            left * dry + wet_left * direct + wet_right * cross,
+            right * dry + wet_right * direct + wet_left * cross,
+        )
+    }
+}
+
+impl Effect for Reverb {
+    fn process(&mut self, left: &mut [f32], right: &mut [f32], context: &EffectContext) {
+        for (left, right) in left.iter_mut().zip(right.iter_mut()) {
+            (*left, *right) = self.process_frame(*left, *right, context.sample_rate);
+        }
+    }
+
+    /// Silences the tail.
+    fn reset(&mut self) {
+        self.channels.iter_mut().for_each(ReverbChannel::clear);
+        self.pre_delay_buffer.fill(0.0);
+        self.pre_delay_index = 0;
+    }
+
+    /// Builds the filters with the Freeverb tunings scaled to the sample rate.
+    fn prepare(&mut self, sample_rate: f32) {
+        self.channels = vec![ReverbChannel::new(sample_rate, 0), ReverbChannel::new(sample_rate, STEREO_SPREAD)];
+        self.pre_delay_buffer = vec![0.0; (MAX_PRE_DELAY_SECONDS * sample_rate) as usize + 1];
+        self.pre_delay_index = 0;
+    }
+}
+
+/// Sine LFO shared by the modulation effects.
+#[derive(Clone, Debug, Default)]
+struct Sweep {
+    phase: f64, // Normalized phase in the range 0.0..1.0
+}
+
+impl Sweep {
+    /// Returns the sine value at the current phase plus `offset` cycles, in the range `-1.0..=1.0`.
+    fn value(&self, offset: f64) -> f32 {
+        (2.0 * std::f64::consts::PI * (self.phase + offset)).sin() as f32
+    }
+
+    fn advance(&mut self, rate: LfoRate, context: &EffectContext) {
+        self.phase = (self.phase + rate.frequency(context.tempo) as f64 / context.sample_rate as f64).rem_euclid(1.0);
+    }
+}
+
+/// Stereo delay line read at a fractional, changing position.
+#[derive(Clone, Debug, Default)]
+struct ModulatedDelay {
+    buffers: [Vec<f32>; 2], // Circular buffers for the left and right channels
+    write_index: usize,     // Next position written in both buffers
+}
+
+impl ModulatedDelay {
+    /// Sizes the buffers to hold `max_seconds` at the sample rate.
+    fn prepare(&mut self, max_seconds: f32, sample_rate: f32) {
+        let length = (max_seconds * sample_rate) as usize + 2; // Room for the interpolation neighbour
+        self.buffers = [vec![0.0; length], vec![0.0; length]];
+        self.write_index = 0;
+    }
+
+    /// Reads a channel `delay` samples behind the write position, with linear interpolation.
+    fn read(&self, channel: usize, delay: f32) -> f32 {
+        let buffer = &self.buffers[channel];
+        let length = buffer.len();
+        let position = (self.write_index as f32 - delay.clamp(1.0, (length - 2) as f32)).rem_euclid(length as f32);
+        let index = position as usize % length;
+        let next = (index + 1) % length;
+        let fraction = position - position.floor();
+        buffer[index] + (buffer[next] - buffer[index]) * fraction
+    }
+
+    fn write(&mut self, left: f32, right: f32) {
+        self.buffers[0][self.write_index] = left;
+        self.buffers[1][self.write_index] = right;
+        self.write_index = (self.write_index + 1) % self.buffers[0].len();
+    }
+
+    fn reset(&mut self) {
+        for buffer in &mut self.buffers {
+            buffer.fill(0.0);
+        }
+        self.write_index = 0;
+    }
+}
+
+/// Runs a modulated delay over a block: the delay of each channel sweeps between `base` and
+/// `base + sweep` milliseconds, and the delayed signal is fed back and blended with the input.
+fn process_modulated_delay(
+    line: &mut ModulatedDelay,
+    lfo: &mut Sweep,
+    settings: (LfoRate, f32, f32, f32), // Rate, depth, feedback and mix
+    (base, sweep): (f32, f32),
+    left: &mut [f32],
+    right: &mut [f32],
+    context: &EffectContext,
+) {
+    let (rate, depth, feedback, mix) = settings;
+    if line.buffers[0].is_empty() {
+        return; // Not prepared yet
+    }
+    let depth = depth.clamp(0.0, 1.0);
+    let mix = mix.clamp(0.0, 1.0);
+    let to_samples = context.sample_rate / 1000.0;
+    for (left, right) in left.iter_mut().zip(right.iter_mut()) {
+        // The right channel runs a quarter cycle behind, which widens the image
+        let delay_left = (base + sweep * depth * (lfo.value(0.0) + 1.0) / 2.0) * to_samples;
+        let delay_right = (base + sweep * depth * (lfo.value(-0.25) + 1.0) / 2.0) * to_samples;
+        let wet_left = line.read(0, delay_left);
+        let wet_right = line.read(1, delay_right);
+        line.write(*left + wet_left * feedback, *right + wet_right * feedback);
+        *left = *left * (1.0 - mix) + wet_left * mix;
+        *right = *right * (1.0 - mix) + wet_right * mix;
+        lfo.advance(rate, context);
+    }
+}
+
+/// Shortest delay of the chorus, in milliseconds.
+const CHORUS_DELAY_MS: f32 = 12.0;
+
+/// Range the chorus delay sweeps over at full depth, in milliseconds.
+const CHORUS_SWEEP_MS: f32 = 8.0;
+
+/// Chorus that thickens the sound by mixing in copies with a slowly wandering delay.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Chorus {
+    pub rate: LfoRate,
+    pub depth: f32,    // Portion of the delay sweep used, from 0.0 to 1.0
+    pub feedback: f32, // Portion of the delayed signal fed back, from 0.0 to 0.9
+    pub mix: f32,      // Wet/dry balance, from 0.0 (dry) to 1.0 (only the delayed copy)
+    #[serde(skip)]
+    line: ModulatedDelay,
+    #[serde(skip)]
+    lfo: Sweep,
+}
+
+impl Default for Chorus {
+    fn default() -> Self {
+        Chorus { rate: LfoRate::Hertz(0.8), depth: 0.5, feedback: 0.0, mix: 0.5, line: ModulatedDelay::default(), lfo: Sweep::default() }
+    }
+}
+
+impl Effect for Chorus {
+    fn process(&mut self, left: &mut [f32], right: &mut [f32], context: &EffectContext) {
+        let settings = (self.rate, self.depth, self.feedback.clamp(0.0, 0.9), self.mix);
+        process_modulated_delay(&mut self.line, &mut self.lfo, settings, (CHORUS_DELAY_MS, CHORUS_SWEEP_MS), left, right, context);
+    }
+
+    fn reset(&mut self) {
+        self.line.reset();
+        self.lfo = Sweep::default();
+    }
+
+    fn prepare(&mut self, sample_rate: f32) {
+        self.line.prepare((CHORUS_DELAY_MS + CHORUS_SWEEP_MS) / 1000.0, sample_rate);
+    }
+}
+
+/// Shortest delay of the flanger, in milliseconds.
+const FLANGER_DELAY_MS: f32 = 0.5;
+
+/// Range the flanger delay sweeps over at full depth, in milliseconds.
+const FLANGER_SWEEP_MS: f32 = 5.0;
+
+/// Flanger that sweeps a comb filter through the sound with a very short modulated delay.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Flanger {
+    pub rate: LfoRate,
+    pub depth: f32,    // Portion of the delay sweep used, from 0.0 to 1.0
+    pub feedback: f32, // Portion of the delayed signal fed back, from -0.95 to 0.95; negative values hollow out the sound
+    pub mix: f32,      // Wet/dry balance, from 0.0 (dry) to 1.0 (only the delayed copy)
+    #[serde(skip)]
+    line: ModulatedDelay,
+    #[serde(skip)]
+    lfo: Sweep,
+}
+
+impl Default for Flanger {
+    fn default() -> Self {
+        Flanger { rate: LfoRate::Hertz(0.2), depth: 0.7, feedback: 0.5, mix: 0.5, line: ModulatedDelay::default(), lfo: Sweep::default() }
+    }
+}
+
+impl Effect for Flanger {
+    fn process(&mut self, left: &mut [f32], right: &mut [f32], context: &EffectContext) {
+        let settings = (self.rate, self.depth, self.feedback.clamp(-0.95, 0.95), self.mix);
+        process_modulated_delay(&mut self.line, &mut self.lfo, settings, (FLANGER_DELAY_MS, FLANGER_SWEEP_MS), left, right, context);
+    }
+
+    fn reset(&mut self) {
+        self.line.reset();
+        self.lfo = Sweep::default();
+    }
+
+    fn prepare(&mut self, sample_rate: f32) {
+        self.line.prepare((FLANGER_DELAY_MS + FLANGER_SWEEP_MS) / 1000.0, sample_rate);
+    }
+}
+
+/// Lowest all-pass corner frequency of the phaser sweep, in Hz.
+const PHASER_MIN_HZ: f32 = 200.0;
+
+/// Highest all-pass corner frequency of the phaser sweep at full depth, in Hz.
+const PHASER_MAX_HZ: f32 = 5000.0;
+
+/// Most all-pass stages the phaser can run.
+pub const MAX_PHASER_STAGES: usize = 12;
+
+/// Phaser that sweeps notches through the sound with a chain of first-order all-pass filters.
+///
+/// Each pair of stages adds one notch when the filtered signal is mixed with the dry input,
+/// so the deepest effect is at a mix of 0.5.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Phaser {
+    pub rate: LfoRate,
+    pub depth: f32,    // Portion of the frequency sweep used, from 0.0 to 1.0
+    pub feedback: f32, // Portion of the last stage fed back into the first, from -0.9 to 0.9
+    pub mix: f32,      // Wet/dry balance, from 0.0 (dry) to 1.0 (only the filtered signal)
+    pub stages: usize, // Number of all-pass stages, from 2 to `MAX_PHASER_STAGES`
+    #[serde(skip)]
+    states: [[f32; MAX_PHASER_STAGES]; 2], // All-pass state for the left and right channels
+    #[serde(skip)]
+    last: [f32; 2], // Output of the last stage, for feedback
+    #[serde(skip)]
+    lfo: Sweep,
+}
+
+impl Default for Phaser {
+    fn default() -> Self {
+        Phaser {
+            rate: LfoRate::Hertz(0.5),
+            depth: 0.7,
+            feedback: 0.3,
+            mix: 0.5,
+            stages: 4,
+            states: [[0.0; MAX_PHASER_STAGES]; 2],
+            last: [0.0; 2],
+            lfo: Sweep::default(),
+        }
+    }
+}
+
+impl Effect for Phaser {
+    fn process(&mut self, left: &mut [f32], right: &mut [f32], context: &EffectContext) {
+        let stages = self.stages.clamp(2, MAX_PHASER_STAGES);
+        let depth = self.depth.clamp(0.0, 1.0);
+        let feedback = self.feedback.clamp(-0.9, 0.9);
+        let mix = self.mix.clamp(0.0, 1.0);
+        let nyquist = context.sample_rate * 0.49;
+        for (left, right) in left.iter_mut().zip(right.iter_mut()) {
+            for (channel, sample) in [&mut *left, &mut *right].into_iter().enumerate() {
+                let sweep = (self.lfo.value(-0.25 * channel as f64) + 1.0) / 2.0; // Right a quarter cycle behind
+                let frequency = (PHASER_MIN_HZ * (PHASER_MAX_HZ / PHASER_MIN_HZ).powf(depth * sweep)).min(nyquist);
+                let t = (std::f32::consts::PI * frequency / context.sample_rate).tan();
+                let coefficient = (t - 1.0) / (t + 1.0);
+
+                let mut signal = *sample + self.last[channel] * feedback;
+                for state in &mut self.states[channel][..stages] {
+                    let output = coefficient * signal + *state;
+                    *state = signal - coefficient * output;
+                    signal = output;
+                }
+                self.last[channel] = signal;
+                *sample = *sample * (1.0 - mix) + signal * mix;
+            }
+            self.lfo.advance(self.rate, context);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.states = [[0.0; MAX_PHASER_STAGES]; 2];
+        self.last = [0.0; 2];
+        self.lfo = Sweep::default();
+    }
+}
+
+/// Tremolo that moves the volume up and down.
+///
+/// Unlike the delay-based effects there is no feedback control: the tremolo only scales the
+/// current sample, so it holds no past signal that could be fed back.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Tremolo {
+    pub rate: LfoRate,
+    pub depth: f32, // How far the volume dips, from 0.0 to 1.0 (silence at the trough)
+    pub mix: f32,   // Wet/dry balance, from 0.0 (dry) to 1.0 (only the modulated signal)
+    #[serde(skip)]
+    lfo: Sweep,
+}
+
+impl Default for Tremolo {
+    fn default() -> Self {
+        Tremolo { rate: LfoRate::Hertz(5.0), depth: 0.5, mix: 1.0, lfo: Sweep::default() }
+    }
+}
+
+impl Effect for Tremolo {
+    fn process(&mut self, left: &mut [f32], right: &mut [f32], context: &EffectContext) {
+        let depth = self.depth.clamp(0.0, 1.0);
+        let mix = self.mix.clamp(0.0, 1.0);
+        for (left, right) in left.iter_mut().zip(right.iter_mut()) {
+            let gain = 1.0 - depth * (1.0 - self.lfo.value(0.0)) / 2.0; // Full volume at the peak of the LFO
+            let gain = 1.0 - mix + gain * mix;
+            *left *= gain;
+            *right *= gain;
+            self.lfo.advance(self.rate, context);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.lfo = Sweep::default();
+    }
+}
+
+/// Auto-pan that swings the sound between the left and right speakers. It has no feedback
+/// control for the same reason as `Tremolo`: each sample is only weighted between the sides.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AutoPan {
+    pub rate: LfoRate,
+    pub depth: f32, // How far the sound swings, from 0.0 (centre) to 1.0 (hard left and right)
+    pub mix: f32,   // Wet/dry balance, from 0.0 (dry) to 1.0 (only the panned signal)
+    #[serde(skip)]
+    lfo: Sweep,
+}
+
+impl Default for AutoPan {
+    fn default() -> Self {
+        AutoPan { rate: LfoRate::Hertz(0.5), depth: 1.0, mix: 1.0, lfo: Sweep::default() }
+    }
+}
+
+impl Effect for AutoPan {
+    fn process(&mut self, left: &mut [f32], right: &mut [f32], context: &EffectContext) {
+        let depth = self.depth.clamp(0.0, 1.0);
+        let mix = self.mix.clamp(0.0, 1.0);
+        for (left, right) in left.iter_mut().zip(right.iter_mut()) {
+            // Equal-power gains, scaled so both sides pass at unity in the centre
+            let angle = (1.0 + depth * self.lfo.value(0.0)) * std::f32::consts::FRAC_PI_4;
+            let gain_left = angle.cos() * std::f32::consts::SQRT_2;
+            let gain_right = angle.sin() * std::f32::consts::SQRT_2;
+            *left *= 1.0 - mix + gain_left * mix;
+            *right *= 1.0 - mix + gain_right * mix;
+            self.lfo.advance(self.rate, context);
+        }
+    }
 
-        // Combine the original sample with the effects
-        sample + reverb_effect + delay_effect
+    fn reset(&mut self) {
+        self.lfo = Sweep::default();
     }
 }
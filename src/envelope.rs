@@ -0,0 +1,184 @@
+use serde::{Serialize, Deserialize};
+
+/// Shape of the envelope segments.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum EnvelopeCurve {
+    Linear,      // Straight-line segments
+    Exponential, // RC-style segments that move quickly at first and settle gently
+}
+
+/// Attack/decay/sustain/release settings.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Envelope {
+    pub attack: f32,          // Time to rise from silence to full level, in seconds
+    pub decay: f32,           // Time to fall from full level to the sustain level, in seconds
+    pub sustain: f32,         // Level held while the gate is open, from 0.0 to 1.0
+    pub release: f32,         // Time to fall to silence after the gate closes, in seconds
+    pub curve: EnvelopeCurve, // Segment shape
+}
+
+impl Default for Envelope {
+    /// Short attack and release so gates do not click, with full sustain.
+    fn default() -> Self {
+        Envelope {
+            attack: 0.005,
+            decay: 0.1,
+            sustain: 1.0,
+            release: 0.05,
+            curve: EnvelopeCurve::Linear,
+        }
+    }
+}
+
+impl Envelope {
+    /// Maps the progress through a segment to the fraction of the segment's travel covered.
+    ///
+    /// # Parameters
+    /// - `progress`: How far through the segment we are, from 0.0 to 1.0.
+    ///
+    /// # Returns
+    /// - The covered fraction, from 0.0 to 1.0.
+    fn shape(&self, progress: f32) -> f32 {
+        let progress = if progress.is_nan() { 1.0 } else { progress.clamp(0.0, 1.0) }; // Zero-length segments complete at once
+        match self.curve {
+            EnvelopeCurve::Linear => progress,
+            EnvelopeCurve::Exponential => {
+                const STEEPNESS: f32 = 5.0; // Number of time constants per segment
+                (1.0 - (-STEEPNESS * progress).exp()) / (1.0 - (-STEEPNESS).exp())
+            }
+        }
+    }
+
+    /// Returns the level while the gate is held, starting from silence.
+    fn held_level(&self, time: f32) -> f32 {
+        if time < self.attack {
+            self.shape(time / self.attack)
+        } else if time < self.attack + self.decay {
+            1.0 - (1.0 - self.sustain) * self.shape((time - self.attack) / self.decay)
+        } else {
+            self.sustain
+        }
+    }
+
+    /// Evaluates the envelope for a note with a known gate length, such as a timeline clip.
+    ///
+    /// # Parameters
+    /// - `time`: Time since the note started, in seconds.
+    /// - `gate_length`: How long the gate stays open, in seconds.
+    ///
+    /// # Returns
+    /// - The envelope level, from 0.0 to 1.0. The release rings past `gate_length`.
+    pub fn level_at(&self, time: f32, gate_length: f32) -> f32 {
+        if time < 0.0 {
+            0.0
+        } else if time < gate_length {
+            self.held_level(time)
+        } else {
+            let release_start = self.held_level(gate_length); // Release from wherever the gate closed
+            release_start * (1.0 - self.shape((time - gate_length) / self.release))
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+enum EnvelopeStage {
+    #[default]
+    Idle,
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+}
+
+/// Runs an `Envelope` in real time for voices whose gate length is not known in advance.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EnvelopeGenerator {
+    stage: EnvelopeStage,
+    level: f32,       // Current output level
+    stage_start: f32, // Level at the start of the current stage
+    elapsed: f32,     // Time spent in the current stage, in seconds
+}
+
+impl EnvelopeGenerator {
+    /// Creates an idle envelope generator.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opens the gate. The attack starts from the current level so retriggering does not click.
+    pub fn note_on(&mut self) {
+        self.enter(EnvelopeStage::Attack);
+    }
+
+    /// Closes the gate and starts the release from the current level.
+    pub fn note_off(&mut self) {
+        if self.stage != EnvelopeStage::Idle {
+            self.enter(EnvelopeStage::Release);
+        }
+    }
+
+    /// Returns `true` while the gate is open.
+    pub fn is_gate_open(&self) -> bool {
+        matches!(self.stage, EnvelopeStage::Attack | EnvelopeStage::Decay | EnvelopeStage::Sustain)
+    }
+
+    /// Returns `true` until the release has finished.
+    pub fn is_active(&self) -> bool {
+        self.stage != EnvelopeStage::Idle
+    }
+
+    /// Returns the current level without advancing.
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+
+    /// Silences the generator immediately.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    fn enter(&mut self, stage: EnvelopeStage) {
+        self.stage = stage;
+        self.stage_start = self.level;
+        self.elapsed = 0.0;
+    }
+
+    /// Advances the envelope by one sample.
+    ///
+    /// # Parameters
+    /// - `envelope`: The envelope settings to follow.
+    /// - `sample_rate`: The sample rate in Hz.
+    ///
+    /// # Returns
+    /// - The envelope level for this sample.
+    pub fn next_level(&mut self, envelope: &Envelope, sample_rate: f32) -> f32 {
+        self.elapsed += 1.0 / sample_rate;
+        match self.stage {
+            EnvelopeStage::Idle => self.level = 0.0,
+            EnvelopeStage::Attack => {
+                let progress = self.elapsed / envelope.attack;
+                self.level = self.stage_start + (1.0 - self.stage_start) * envelope.shape(progress);
+                if progress >= 1.0 {
+                    self.enter(EnvelopeStage::Decay);
+                }
+            }
+            EnvelopeStage::Decay => {
+                let progress = self.elapsed / envelope.decay;
+                self.level = 1.0 - (1.0 - envelope.sustain) * envelope.shape(progress);
+                if progress >= 1.0 {
+                    self.enter(EnvelopeStage::Sustain);
+                }
+            }
+            EnvelopeStage::Sustain => self.level = envelope.sustain, // Follows sustain changes while held
+            EnvelopeStage::Release => {
+                let progress = self.elapsed / envelope.release;
+                self.level = self.stage_start * (1.0 - envelope.shape(progress));
+                if progress >= 1.0 {
+                    self.level = 0.0;
+                    self.stage = EnvelopeStage::Idle;
+                }
+            }
+        }
+        self.level
+    }
+}
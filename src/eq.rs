@@ -0,0 +1,174 @@
+use std::f64::consts::PI;
+use serde::{Serialize, Deserialize};
+use crate::effects::{Effect, EffectContext};
+
+/// Response shape of an EQ band.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum BandType {
+    Peaking,   // Boost or cut around the frequency
+    LowShelf,  // Boost or cut below the frequency
+    HighShelf, // Boost or cut above the frequency
+    HighPass,  // Remove content below the frequency
+    LowPass,   // Remove content above the frequency
+}
+
+impl BandType {
+    /// Every band type, in display order.
+    pub const ALL: [BandType; 5] = [BandType::Peaking, BandType::LowShelf, BandType::HighShelf, BandType::HighPass, BandType::LowPass];
+}
+
+/// Normalized coefficients of a biquad section (`a0` is 1).
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BiquadCoefficients {
+    pub b0: f64,
+    pub b1: f64,
+    pub b2: f64,
+    pub a1: f64,
+    pub a2: f64,
+}
+
+impl BiquadCoefficients {
+    /// Coefficients that pass the signal unchanged.
+    pub const IDENTITY: BiquadCoefficients = BiquadCoefficients { b0: 1.0, b1: 0.0, b2: 0.0, a1: 0.0, a2: 0.0 };
+
+    /// Returns the linear gain of the section at a frequency, by evaluating the transfer function on the unit circle.
+    pub fn magnitude_response(&self, frequency: f32, sample_rate: f32) -> f32 {
+        let w = 2.0 * PI * frequency as f64 / sample_rate as f64;
+        let (cos1, sin1, cos2, sin2) = (w.cos(), w.sin(), (2.0 * w).cos(), (2.0 * w).sin());
+        let numerator = (self.b0 + self.b1 * cos1 + self.b2 * cos2, -self.b1 * sin1 - self.b2 * sin2);
+        let denominator = (1.0 + self.a1 * cos1 + self.a2 * cos2, -self.a1 * sin1 - self.a2 * sin2);
+        (numerator.0.hypot(numerator.1) / denominator.0.hypot(denominator.1)) as f32
+    }
+}
+
+/// One band of the parametric EQ.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct EqBand {
+    pub enabled: bool,
+    pub band_type: BandType,
+    pub frequency: f32, // Center, corner or cutoff frequency in Hz
+    pub gain: f32,      // Boost or cut in dB; unused by the pass filters
+    pub q: f32,         // Bandwidth of peaking bands and resonance of shelves and pass filters
+}
+
+impl EqBand {
+    /// Creates an enabled band.
+    pub fn new(band_type: BandType, frequency: f32, gain: f32, q: f32) -> Self {
+        EqBand { enabled: true, band_type, frequency, gain, q }
+    }
+
+    /// Computes the biquad coefficients with the formulas from Robert Bristow-Johnson's Audio EQ Cookbook.
+    pub fn coefficients(&self, sample_rate: f32) -> BiquadCoefficients {
+        if !self.enabled {
+            return BiquadCoefficients::IDENTITY;
+        }
+        let frequency = self.frequency.clamp(1.0, sample_rate * 0.49) as f64; // Stay below Nyquist
+        let w0 = 2.0 * PI * frequency / sample_rate as f64;
+        let (cos, sin) = (w0.cos(), w0.sin());
+        let alpha = sin / (2.0 * self.q.max(0.05) as f64);
+        let a = 10f64.powf(self.gain as f64 / 40.0);
+        let shelf = 2.0 * a.sqrt() * alpha;
+
+        let (b0, b1, b2, a0, a1, a2) = match self.band_type {
+            BandType::Peaking => (1.0 + alpha * a, -2.0 * cos, 1.0 - alpha * a, 1.0 + alpha / a, -2.0 * cos, 1.0 - alpha / a),
+            BandType::LowShelf => (
+                a * ((a + 1.0) - (a - 1.0) * cos + shelf),
+                2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                a * ((a + 1.0) - (a - 1.0) * cos - shelf),
+                (a + 1.0) + (a - 1.0) * cos + shelf,
+                -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                (a + 1.0) + (a - 1.0) * cos - shelf,
+            ),
+            BandType::HighShelf => (
+                a * ((a + 1.0) + (a - 1.0) * cos + shelf),
+                -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                a * ((a + 1.0) + (a - 1.0) * cos - shelf),
+                (a + 1.0) - (a - 1.0) * cos + shelf,
+                2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                (a + 1.0) - (a - 1.0) * cos - shelf,
+            ),
+            BandType::HighPass => ((1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0, 1.0 + alpha, -2.0 * cos, 1.0 - alpha),
+            BandType::LowPass => ((1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0, 1.0 + alpha, -2.0 * cos, 1.0 - alpha),
+        };
+        BiquadCoefficients { b0: b0 / a0, b1: b1 / a0, b2: b2 / a0, a1: a1 / a0, a2: a2 / a0 }
+    }
+}
+
+/// Biquad filter state in transposed direct form II.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Biquad {
+    z1: f64,
+    z2: f64,
+}
+
+impl Biquad {
+    pub(crate) fn process(&mut self, input: f32, c: &BiquadCoefficients) -> f32 {
+        let input = input as f64;
+        let output = c.b0 * input + self.z1;
+        self.z1 = c.b1 * input - c.a1 * output + self.z2;
+        self.z2 = c.b2 * input - c.a2 * output;
+        output as f32
+    }
+}
+
+/// Multi-band parametric equalizer made of biquad sections in series.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Equalizer {
+    pub bands: Vec<EqBand>,
+    #[serde(skip)]
+    filters: Vec<[Biquad; 2]>, // Left and right state for each band
+}
+
+impl Default for Equalizer {
+    /// Flat bands at the usual positions, with the pass filters switched off.
+    fn default() -> Self {
+        let mut high_pass = EqBand::new(BandType::HighPass, 30.0, 0.0, 0.707);
+        let mut low_pass = EqBand::new(BandType::LowPass, 18000.0, 0.0, 0.707);
+        high_pass.enabled = false;
+        low_pass.enabled = false;
+        Equalizer::new(vec![
+            high_pass,
+            EqBand::new(BandType::LowShelf, 100.0, 0.0, 0.707),
+            EqBand::new(BandType::Peaking, 1000.0, 0.0, 1.0),
+            EqBand::new(BandType::HighShelf, 8000.0, 0.0, 0.707),
+            low_pass,
+        ])
+    }
+}
+
+impl Equalizer {
+    /// Creates an equalizer from a list of bands.
+    pub fn new(bands: Vec<EqBand>) -> Self {
+        Equalizer { bands, filters: Vec::new() }
+    }
+
+    /// Returns the linear gain of all bands combined at a frequency, for drawing the EQ curve.
+    pub fn magnitude_response(&self, frequency: f32, sample_rate: f32) -> f32 {
+        self.bands
+            .iter()
+            .map(|band| band.coefficients(sample_rate).magnitude_response(frequency, sample_rate))
+            .product()
+    }
+}
+
+impl Effect for Equalizer {
+    fn process(&mut self, left: &mut [f32], right: &mut [f32], context: &EffectContext) {
+        if self.filters.len() != self.bands.len() {
+            self.filters.resize(self.bands.len(), [Biquad::default(); 2]); // Only when bands are added or removed
+        }
+        for (band, filters) in self.bands.iter().zip(self.filters.iter_mut()) {
+            if !band.enabled {
+                continue;
+            }
+            let coefficients = band.coefficients(context.sample_rate); // Once per block, so edits apply promptly
+            for (left, right) in left.iter_mut().zip(right.iter_mut()) {
+                *left = filters[0].process(*left, &coefficients);
+                *right = filters[1].process(*right, &coefficients);
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.filters.clear();
+    }
+}
@@ -0,0 +1,120 @@
+use std::f32::consts::PI;
+use serde::{Serialize, Deserialize};
+
+/// Response type of the filter.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum FilterMode {
+    LowPass,  // Passes frequencies below the cutoff
+    HighPass, // Passes frequencies above the cutoff
+    BandPass, // Passes frequencies around the cutoff
+    Notch,    // Removes frequencies around the cutoff
+}
+
+/// Filter parameters, kept separate from the filter state so they can be serialized and shared.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct FilterSettings {
+    pub enabled: bool,    // Whether the filter is in the signal path
+    pub mode: FilterMode, // Response type
+    pub cutoff: f32,      // Cutoff (or center) frequency in Hz
+    pub resonance: f32,   // Resonance from 0.0 (Q = 0.5) to 1.0 (Q = 50)
+}
+
+impl Default for FilterSettings {
+    fn default() -> Self {
+        FilterSettings {
+            enabled: false,
+            mode: FilterMode::LowPass,
+            cutoff: 1000.0,
+            resonance: 0.2,
+        }
+    }
+}
+
+impl FilterSettings {
+    /// Returns the damping factor `k = 1 / Q` for the current resonance.
+    pub fn damping(&self) -> f32 {
+        2.0 * (1.0 - 0.99 * self.resonance.clamp(0.0, 1.0))
+    }
+
+    /// Returns the filter's gain at a given frequency.
+    ///
+    /// # Parameters
+    /// - `frequency`: The frequency to evaluate, in Hz.
+    /// - `sample_rate`: The sample rate in Hz.
+    ///
+    /// # Returns
+    /// - The linear magnitude of the response (1.0 when the filter is disabled).
+    pub fn magnitude_response(&self, frequency: f32, sample_rate: f32) -> f32 {
+        if !self.enabled {
+            return 1.0;
+        }
+        let cutoff = self.cutoff.clamp(10.0, 0.49 * sample_rate);
+        // The trapezoidal filter matches the analog prototype at prewarped frequencies
+        let omega = (PI * frequency / sample_rate).tan() / (PI * cutoff / sample_rate).tan();
+        let k = self.damping();
+        let denominator = ((1.0 - omega * omega).powi(2) + (k * omega).powi(2)).sqrt();
+        let numerator = match self.mode {
+            FilterMode::LowPass => 1.0,
+            FilterMode::HighPass => omega * omega,
+            FilterMode::BandPass => k * omega,
+            FilterMode::Notch => (1.0 - omega * omega).abs(),
+        };
+        numerator / denominator
+    }
+}
+
+/// Resonant state-variable filter using the topology-preserving (trapezoidal) structure.
+///
+/// The coefficients are recomputed from the settings on every sample and the state holds
+/// only the integrator outputs, so the filter stays stable when the cutoff is swept quickly.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Filter {
+    ic1eq: f32, // State of the first integrator
+    ic2eq: f32, // State of the second integrator
+}
+
+impl Filter {
+    /// Creates a filter with cleared state.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clears the filter state.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Filters one sample.
+    ///
+    /// # Parameters
+    /// - `input`: The input sample.
+    /// - `settings`: The filter settings. A disabled filter passes the input through.
+    /// - `sample_rate`: The sample rate in Hz.
+    ///
+    /// # Returns
+    /// - The filtered sample.
+    pub fn process(&mut self, input: f32, settings: &FilterSettings, sample_rate: f32) -> f32 {
+        if !settings.enabled {
+            return input;
+        }
+        let cutoff = settings.cutoff.clamp(10.0, 0.49 * sample_rate); // Keep the prewarped cutoff finite
+        let g = (PI * cutoff / sample_rate).tan();
+        let k = settings.damping();
+        let a1 = 1.0 / (1.0 + g * (g + k));
+        let a2 = g * a1;
+        let a3 = g * a2;
+
+        let v3 = input - self.ic2eq;
+        let v1 = a1 * self.ic1eq + a2 * v3; // Band-pass output
+        let v2 = self.ic2eq + a2 * self.ic1eq + a3 * v3; // Low-pass output
+        self.ic1eq = 2.0 * v1 - self.ic1eq;
+        self.ic2eq = 2.0 * v2 - self.ic2eq;
+
+        match settings.mode {
+            FilterMode::LowPass => v2,
+            FilterMode::HighPass => input - k * v1 - v2,
+            FilterMode::BandPass => k * v1, // Scaled for unity gain at the center frequency
+            FilterMode::Notch => input - k * v1,
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use std::f64::consts::TAU;
+use serde::{Serialize, Deserialize};
+use crate::envelope::{Envelope, EnvelopeCurve, EnvelopeGenerator};
+
+/// Number of operators in an FM patch.
+pub const OPERATOR_COUNT: usize = 4;
+
+/// How the four operators are connected. Operators are numbered 1 to 4 and higher-numbered
+/// operators modulate lower-numbered ones; carriers are the operators that are heard.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum FmAlgorithm {
+    Stack,        // 4 → 3 → 2 → 1
+    Branch,       // (3 + 4) → 2 → 1
+    TwoStacks,    // 4 → 3 and 2 → 1, both heard
+    ThreeToOne,   // (2 + 3 + 4) → 1
+    OneToThree,   // 4 → 1, 2 and 3, all heard
+    StackAndSine, // 4 → 3 → 2 heard alongside a plain 1
+    Additive,     // No modulation, all four heard
+}
+
+impl FmAlgorithm {
+    /// Every algorithm, in display order.
+    pub const ALL: [FmAlgorithm; 7] = [
+        FmAlgorithm::Stack,
+        FmAlgorithm::Branch,
+        FmAlgorithm::TwoStacks,
+        FmAlgorithm::ThreeToOne,
+        FmAlgorithm::OneToThree,
+        FmAlgorithm::StackAndSine,
+        FmAlgorithm::Additive,
+    ];
+
+    /// Returns, for each operator, a bit mask of the operators that modulate it.
+    fn modulators(&self) -> [u8; OPERATOR_COUNT] {
+        match self {
+            FmAlgorithm::Stack => [0b0010, 0b0100, 0b1000, 0],
+            FmAlgorithm::Branch => [0b0010, 0b1100, 0, 0],
+            FmAlgorithm::TwoStacks => [0b0010, 0, 0b1000, 0],
+            FmAlgorithm::ThreeToOne => [0b1110, 0, 0, 0],
+            FmAlgorithm::OneToThree => [0b1000, 0b1000, 0b1000, 0],
+            FmAlgorithm::StackAndSine => [0, 0b0100, 0b1000, 0],
+            FmAlgorithm::Additive => [0, 0, 0, 0],
+        }
+    }
+
+    /// Returns a bit mask of the carrier operators.
+    fn carriers(&self) -> u8 {
+        match self {
+            FmAlgorithm::Stack | FmAlgorithm::Branch | FmAlgorithm::ThreeToOne => 0b0001,
+            FmAlgorithm::TwoStacks => 0b0101,
+            FmAlgorithm::OneToThree => 0b0111,
+            FmAlgorithm::StackAndSine => 0b0011,
+            FmAlgorithm::Additive => 0b1111,
+        }
+    }
+}
+
+/// A sine operator.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct FmOperator {
+    pub ratio: f32,         // Frequency as a multiple of the note frequency
+    pub level: f32,         // Output level; for modulators, 1.0 shifts the carrier phase by one cycle
+    pub envelope: Envelope, // Level envelope of the operator
+}
+
+impl FmOperator {
+    fn new(ratio: f32, level: f32, attack: f32, decay: f32, sustain: f32, release: f32) -> Self {
+        FmOperator {
+            ratio,
+            level,
+            envelope: Envelope { attack, decay, sustain, release, curve: EnvelopeCurve::Exponential },
+        }
+    }
+}
+
+/// Settings for the four-operator FM engine.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct FmPatch {
+    pub algorithm: FmAlgorithm,
+    pub operators: [FmOperator; OPERATOR_COUNT], // Operators 1 to 4
+    pub feedback: f32, // Self-modulation of operator 4, from 0.0 to 1.0
+}
+
+impl Default for FmPatch {
+    fn default() -> Self {
+        FmPatch::electric_piano()
+    }
+}
+
+impl FmPatch {
+    /// Tine-style electric piano: two stacks, one for the body and one for the bell-like attack.
+    pub fn electric_piano() -> Self {
+        FmPatch {
+            algorithm: FmAlgorithm::TwoStacks,
+            operators: [
+                FmOperator::new(1.0, 1.0, 0.002, 1.5, 0.3, 0.4),
+                FmOperator::new(1.0, 0.25, 0.002, 1.0, 0.1, 0.4),
+                FmOperator::new(1.0, 0.4, 0.002, 0.6, 0.0, 0.3),
+                FmOperator::new(14.0, 0.15, 0.001, 0.15, 0.0, 0.1),
+            ],
+            feedback: 0.0,
+        }
+    }
+
+    /// Struck bell with inharmonic partials and a long decay.
+    pub fn bell() -> Self {
+        FmPatch {
+            algorithm: FmAlgorithm::TwoStacks,
+            operators: [
+                FmOperator::new(1.0, 1.0, 0.001, 4.0, 0.0, 3.0),
+                FmOperator::new(3.5, 0.6, 0.001, 3.0, 0.0, 2.0),
+                FmOperator::new(2.0, 0.5, 0.001, 2.5, 0.0, 2.0),
+                FmOperator::new(5.19, 0.4, 0.001, 1.5, 0.0, 1.0),
+            ],
+            feedback: 0.0,
+        }
+    }
+
+    /// Punchy bass with a decaying modulation index and a little feedback grit.
+    pub fn bass() -> Self {
+        FmPatch {
+            algorithm: FmAlgorithm::Stack,
+            operators: [
+                FmOperator::new(0.5, 1.0, 0.002, 0.8, 0.6, 0.1),
+                FmOperator::new(0.5, 0.35, 0.002, 0.3, 0.2, 0.1),
+                FmOperator::new(1.0, 0.2, 0.002, 0.2, 0.1, 0.1),
+                FmOperator::new(1.0, 0.1, 0.002, 0.1, 0.0, 0.1),
+            ],
+            feedback: 0.4,
+        }
+    }
+
+    /// Returns how long the patch keeps sounding after the gate closes. Only carriers are
+    /// heard, so a modulator's longer release does not hold the note open.
+    pub fn release(&self) -> f32 {
+        let carriers = self.algorithm.carriers();
+        self.operators
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| carriers & (1 << index) != 0)
+            .map(|(_, op)| op.envelope.release)
+            .fold(0.0, f32::max)
+    }
+}
+
+/// Running state of one FM note.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FmVoice {
+    phases: [f64; OPERATOR_COUNT],                   // Normalized operator phases
+    envelopes: [EnvelopeGenerator; OPERATOR_COUNT],  // Operator envelope state for live notes
+    feedback: [f32; 2],                              // Last two outputs of operator 4
+}
+
+impl FmVoice {
+    /// Creates a silent voice.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clears the phases and envelopes.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Opens the gate of every operator envelope.
+    pub fn note_on(&mut self) {
+        for envelope in &mut self.envelopes {
+            envelope.note_on();
+        }
+    }
+
+    /// Starts the release of every operator envelope.
+    pub fn note_off(&mut self) {
+        for envelope in &mut self.envelopes {
+            envelope.note_off();
+        }
+    }
+
+    /// Renders the next sample using the voice's own operator envelopes.
+    ///
+    /// # Parameters
+    /// - `patch`: The FM patch to play.
+    /// - `frequency`: The note frequency in Hz.
+    /// - `sample_rate`: The sample rate in Hz.
+    ///
+    /// # Returns
+    /// - The generated sample.
+    pub fn next_sample(&mut self, patch: &FmPatch, frequency: f32, sample_rate: f32) -> f32 {
+        let mut levels = [0.0; OPERATOR_COUNT];
+        for (level, (envelope, operator)) in levels.iter_mut().zip(self.envelopes.iter_mut().zip(&patch.operators)) {
+            *level = envelope.next_level(&operator.envelope, sample_rate);
+        }
+        self.render(patch, frequency, sample_rate, levels)
+    }
+
+    /// Renders the next sample with externally supplied operator envelope levels, for notes
+    /// whose gate length is known in advance such as timeline clips.
+    ///
+    /// # Parameters
+    /// - `patch`: The FM patch to play.
+    /// - `frequency`: The note frequency in Hz.
+    /// - `sample_rate`: The sample rate in Hz.
+    /// - `levels`: The envelope level of each operator.
+    ///
+    /// # Returns
+    /// - The generated sample.
+    pub fn render(&mut self, patch: &FmPatch, frequency: f32, sample_rate: f32, levels: [f32; OPERATOR_COUNT]) -> f32 {
+        let modulators = patch.algorithm.modulators();
+        let carriers = patch.algorithm.carriers();
+        let mut outputs = [0.0f32; OPERATOR_COUNT];
+
+        // Higher operators only modulate lower ones, so render from operator 4 down to 1
+        for index in (0..OPERATOR_COUNT).rev() {
+            let mut modulation = 0.0;
+            for (source, output) in outputs.iter().enumerate() {
+                if modulators[index] & (1 << source) != 0 {
+                    modulation += output;
+                }
+            }
+            if index == OPERATOR_COUNT - 1 {
+                let previous = 0.5 * (self.feedback[0] + self.feedback[1]); // Averaging two outputs keeps feedback from oscillating
+                modulation += patch.feedback * 0.5 * previous;
+            }
+            let operator = &patch.operators[index];
+            outputs[index] = ((self.phases[index] + modulation as f64) * TAU).sin() as f32 * operator.level * levels[index];
+            self.phases[index] = (self.phases[index] + (frequency * operator.ratio) as f64 / sample_rate as f64).rem_euclid(1.0);
+        }
+        self.feedback = [outputs[OPERATOR_COUNT - 1], self.feedback[0]];
+
+        let carrier_count = carriers.count_ones() as f32;
+        let mixed: f32 = (0..OPERATOR_COUNT)
+            .filter(|index| carriers & (1 << index) != 0)
+            .map(|index| outputs[index])
+            .sum();
+        mixed / carrier_count // Keep the level independent of the number of carriers
+    }
+}
@@ -4,3 +4,17 @@ pub mod synthesizer; // Core synthesizer logic for waveform generation
 pub mod ui; // User interface logic for the application
 pub mod effects; // Declare the effects module
 pub mod timeline; // Timeline management for audio clips
+pub mod oscillator; // Band-limited oscillator waveforms
+pub mod envelope; // ADSR envelope generation
+pub mod voice; // Polyphonic voice allocation
+pub mod filter; // Resonant multimode filter
+pub mod modulation; // LFOs and the modulation matrix
+pub mod fm; // Four-operator FM synthesis
+pub mod wavetable; // Mip-mapped wavetables loaded from WAV files
+pub mod noise; // Seeded white, pink and brown noise
+pub mod sample; // WAV file playback for clips
+pub mod convolution; // Partitioned FFT convolution reverb
+pub mod dynamics; // Compressor, limiter and noise gate
+pub mod eq; // Parametric EQ built on biquad sections
+pub mod distortion; // Saturation, waveshaping and bitcrushing
+pub mod meter; // Peak, true-peak, RMS and loudness metering
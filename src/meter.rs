@@ -0,0 +1,337 @@
+use std::f64::consts::PI;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+use crate::eq::{Biquad, BiquadCoefficients};
+
+/// Fall rate of the peak and true-peak readings, in dB per second.
+const PEAK_FALL_DB_PER_SECOND: f32 = 20.0;
+
+/// Integration time of the RMS reading, in seconds.
+const RMS_TIME: f32 = 0.3;
+
+/// Oversampling factor of the true-peak interpolator, as recommended by ITU-R BS.1770.
+const TRUE_PEAK_FACTOR: usize = 4;
+
+/// Taps of each phase of the true-peak interpolator.
+const TRUE_PEAK_TAPS: usize = 12;
+
+/// Loudness is measured in steps of 100 ms; the momentary window is 4 steps and the short-term window 30.
+const MOMENTARY_STEPS: usize = 4;
+const SHORT_TERM_STEPS: usize = 30;
+
+/// Gating blocks quieter than this are left out of the integrated loudness, in LUFS.
+const ABSOLUTE_GATE: f32 = -70.0;
+
+/// The relative gate sits this far below the loudness of the blocks that pass the absolute gate, in LU.
+const RELATIVE_GATE: f32 = -10.0;
+
+/// Width and number of the histogram bins that collect gating blocks, from the absolute gate up.
+const BIN_WIDTH: f32 = 0.1;
+const BIN_COUNT: usize = 800;
+
+/// Latest meter values, written by the audio thread and read from any thread without locking.
+///
+/// Levels are stored as the bits of an `f32` in dB, so they can be swapped atomically.
+#[derive(Debug)]
+pub struct MeterValues {
+    peak: AtomicU32,
+    true_peak: AtomicU32,
+    rms: AtomicU32,
+    momentary: AtomicU32,
+    short_term: AtomicU32,
+    integrated: AtomicU32,
+    clipped: AtomicBool,
+}
+
+/// A snapshot of a meter. Levels are in dB, and `f32::NEG_INFINITY` for silence.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct MeterReading {
+    pub peak: f32,       // Sample peak in dBFS, falling back slowly
+    pub true_peak: f32,  // Peak between the samples in dBTP, falling back slowly
+    pub rms: f32,        // RMS level in dBFS
+    pub momentary: f32,  // EBU R128 loudness over the last 400 ms, in LUFS
+    pub short_term: f32, // EBU R128 loudness over the last 3 s, in LUFS
+    pub integrated: f32, // Gated EBU R128 loudness since the last reset, in LUFS
+    pub clipped: bool,   // The sample or true peak went over full scale since the clip indicator was cleared
+}
+
+impl Default for MeterValues {
+    fn default() -> Self {
+        let silence = || AtomicU32::new(f32::NEG_INFINITY.to_bits());
+        MeterValues {
+            peak: silence(),
+            true_peak: silence(),
+            rms: silence(),
+            momentary: silence(),
+            short_term: silence(),
+            integrated: silence(),
+            clipped: AtomicBool::new(false),
+        }
+    }
+}
+
+impl MeterValues {
+    /// Returns the latest values.
+    pub fn read(&self) -> MeterReading {
+        let load = |value: &AtomicU32| f32::from_bits(value.load(Ordering::Relaxed));
+        MeterReading {
+            peak: load(&self.peak),
+            true_peak: load(&self.true_peak),
+            rms: load(&self.rms),
+            momentary: load(&self.momentary),
+            short_term: load(&self.short_term),
+            integrated: load(&self.integrated),
+            clipped: self.clipped.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Turns the clip indicator off until the next clip.
+    pub fn clear_clip(&self) {
+        self.clipped.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Converts a linear level to dB, with silence at negative infinity.
+fn to_db(level: f32) -> f32 {
+    if level > 0.0 {
+        20.0 * level.log10()
+    } else {
+        f32::NEG_INFINITY
+    }
+}
+
+/// Converts a mean square of K-weighted samples to LUFS, as defined by ITU-R BS.1770.
+fn to_lufs(mean_square: f64) -> f32 {
+    if mean_square > 0.0 {
+        (-0.691 + 10.0 * mean_square.log10()) as f32
+    } else {
+        f32::NEG_INFINITY
+    }
+}
+
+/// Coefficients of the two K-weighting stages at a sample rate: a high shelf modelling the head,
+/// then a high-pass. Derived from the analog prototypes, so rates other than 48 kHz match too.
+fn k_weighting(sample_rate: f32) -> [BiquadCoefficients; 2] {
+    let (shelf_frequency, shelf_gain, shelf_q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+    let k = (PI * shelf_frequency / sample_rate as f64).tan();
+    let vh = 10f64.powf(shelf_gain / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / shelf_q + k * k;
+    let shelf = BiquadCoefficients {
+        b0: (vh + vb * k / shelf_q + k * k) / a0,
+        b1: 2.0 * (k * k - vh) / a0,
+        b2: (vh - vb * k / shelf_q + k * k) / a0,
+        a1: 2.0 * (k * k - 1.0) / a0,
+        a2: (1.0 - k / shelf_q + k * k) / a0,
+    };
+
+    let (pass_frequency, pass_q) = (38.13547087602444, 0.5003270373238773);
+    let k = (PI * pass_frequency / sample_rate as f64).tan();
+    let a0 = 1.0 + k / pass_q + k * k;
+    let high_pass = BiquadCoefficients {
+        b0: 1.0,
+        b1: -2.0,
+        b2: 1.0,
+        a1: 2.0 * (k * k - 1.0) / a0,
+        a2: (1.0 - k / pass_q + k * k) / a0,
+    };
+    [shelf, high_pass]
+}
+
+/// Windowed-sinc interpolation filter, split into one set of taps per oversampled phase.
+fn true_peak_phases() -> [[f32; TRUE_PEAK_TAPS]; TRUE_PEAK_FACTOR] {
+    let length = TRUE_PEAK_FACTOR * TRUE_PEAK_TAPS;
+    let center = (length - 1) as f64 / 2.0;
+    let mut phases = [[0.0; TRUE_PEAK_TAPS]; TRUE_PEAK_FACTOR];
+    for (phase, taps) in phases.iter_mut().enumerate() {
+        for (tap, coefficient) in taps.iter_mut().enumerate() {
+            let n = (phase + tap * TRUE_PEAK_FACTOR) as f64;
+            let x = (n - center) / TRUE_PEAK_FACTOR as f64;
+            let sinc = if x == 0.0 { 1.0 } else { (PI * x).sin() / (PI * x) };
+            let window = 0.42 - 0.5 * (2.0 * PI * n / (length - 1) as f64).cos() + 0.08 * (4.0 * PI * n / (length - 1) as f64).cos(); // Blackman
+            *coefficient = (sinc * window) as f32;
+        }
+        let sum: f32 = taps.iter().sum();
+        taps.iter_mut().for_each(|coefficient| *coefficient /= sum); // Unity gain for every phase
+    }
+    phases
+}
+
+/// Level meter with sample peak, true peak, RMS and EBU R128 loudness.
+///
+/// The audio thread feeds it with `process`; readers on other threads get a handle with
+/// `values` and never block the audio thread.
+#[derive(Clone, Debug)]
+pub struct Meter {
+    values: Arc<MeterValues>,
+    sample_rate: f32, // Rate the filters were built for; 0.0 until the first block
+    peak: f32,        // Linear sample peak, falling back
+    true_peak: f32,   // Linear true peak, falling back
+    mean_square: f32, // Running mean square for the RMS reading
+    phases: [[f32; TRUE_PEAK_TAPS]; TRUE_PEAK_FACTOR],
+    history: [[f32; TRUE_PEAK_TAPS]; 2], // Latest samples of each channel, newest first
+    k_weighting: [BiquadCoefficients; 2],
+    filters: [[Biquad; 2]; 2], // Both K-weighting stages, for each channel
+    step_length: usize,        // Frames in a 100 ms loudness step
+    step_frames: usize,        // Frames gathered in the current step
+    step_energy: f64,          // Sum of the K-weighted squares of the current step
+    steps: [f64; SHORT_TERM_STEPS], // Energy of the latest steps, as a ring
+    steps_filled: usize,
+    next_step: usize,
+    bins: Vec<(f64, u32)>, // Summed mean square and count of the gating blocks in each 0.1 LU bin
+}
+
+impl Default for Meter {
+    fn default() -> Self {
+        Meter::new()
+    }
+}
+
+impl Meter {
+    /// Creates a meter reading silence.
+    pub fn new() -> Self {
+        Meter {
+            values: Arc::new(MeterValues::default()),
+            sample_rate: 0.0,
+            peak: 0.0,
+            true_peak: 0.0,
+            mean_square: 0.0,
+            phases: true_peak_phases(),
+            history: [[0.0; TRUE_PEAK_TAPS]; 2],
+            k_weighting: [BiquadCoefficients::IDENTITY; 2],
+            filters: [[Biquad::default(); 2]; 2],
+            step_length: 0,
+            step_frames: 0,
+            step_energy: 0.0,
+            steps: [0.0; SHORT_TERM_STEPS],
+            steps_filled: 0,
+            next_step: 0,
+            bins: vec![(0.0, 0); BIN_COUNT], // Allocated here, so the audio thread never has to
+        }
+    }
+
+    /// Returns a handle to the published values, for reading on another thread.
+    pub fn values(&self) -> Arc<MeterValues> {
+        Arc::clone(&self.values)
+    }
+
+    /// Returns the latest published values.
+    pub fn reading(&self) -> MeterReading {
+        self.values.read()
+    }
+
+    /// Clears every level, the integrated loudness and the clip indicator.
+    pub fn reset(&mut self) {
+        self.sample_rate = 0.0; // Rebuilds the filters and loudness steps on the next block
+        (self.peak, self.true_peak, self.mean_square) = (0.0, 0.0, 0.0);
+        self.history = [[0.0; TRUE_PEAK_TAPS]; 2];
+        self.bins.iter_mut().for_each(|bin| *bin = (0.0, 0));
+        self.publish_levels();
+        for value in [&self.values.momentary, &self.values.short_term, &self.values.integrated] {
+            value.store(f32::NEG_INFINITY.to_bits(), Ordering::Relaxed);
+        }
+        self.values.clear_clip();
+    }
+
+    /// Measures a block of stereo audio and publishes the new values.
+    ///
+    /// # Parameters
+    /// - `left`, `right`: The block, of equal length.
+    /// - `sample_rate`: The sample rate in Hz.
+    pub fn process(&mut self, left: &[f32], right: &[f32], sample_rate: f32) {
+        if sample_rate != self.sample_rate {
+            self.prepare(sample_rate);
+        }
+        let fall = 10f32.powf(-PEAK_FALL_DB_PER_SECOND * left.len() as f32 / sample_rate / 20.0);
+        let (mut peak, mut true_peak) = (0.0f32, 0.0f32); // Peaks of this block
+        let rms_coefficient = 1.0 - (-1.0 / (RMS_TIME * sample_rate)).exp();
+
+        for (&left, &right) in left.iter().zip(right) {
+            for (channel, sample) in [left, right].into_iter().enumerate() {
+                peak = peak.max(sample.abs());
+                let history = &mut self.history[channel];
+                history.copy_within(..TRUE_PEAK_TAPS - 1, 1);
+                history[0] = sample;
+                for taps in &self.phases {
+                    let interpolated: f32 = taps.iter().zip(history.iter()).map(|(tap, sample)| tap * sample).sum();
+                    true_peak = true_peak.max(interpolated.abs());
+                }
+            }
+            self.mean_square += (0.5 * (left * left + right * right) - self.mean_square) * rms_coefficient;
+
+            let [shelf, high_pass] = &self.k_weighting;
+            for (filters, sample) in self.filters.iter_mut().zip([left, right]) {
+                let shelved = filters[0].process(sample, shelf);
+                let weighted = filters[1].process(shelved, high_pass) as f64;
+                self.step_energy += weighted * weighted; // Both channels weigh 1.0
+            }
+            self.step_frames += 1;
+            if self.step_frames == self.step_length {
+                self.finish_step();
+            }
+        }
+
+        let true_peak = true_peak.max(peak); // The phases fall between the samples, so an isolated sample counts on its own
+        (self.peak, self.true_peak) = ((self.peak * fall).max(peak), (self.true_peak * fall).max(true_peak));
+        self.publish_levels();
+        if peak.max(true_peak) > 1.0 {
+            self.values.clipped.store(true, Ordering::Relaxed); // Stays on until cleared
+        }
+    }
+
+    /// Builds the filters for a new sample rate and restarts the loudness windows.
+    fn prepare(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.k_weighting = k_weighting(sample_rate);
+        self.filters = [[Biquad::default(); 2]; 2];
+        self.step_length = ((sample_rate / 10.0).round() as usize).max(1);
+        (self.step_frames, self.step_energy) = (0, 0.0);
+        (self.steps_filled, self.next_step) = (0, 0);
+    }
+
+    /// Closes a 100 ms step: updates the momentary and short-term loudness, and adds the
+    /// 400 ms block that ends here to the integrated loudness.
+    fn finish_step(&mut self) {
+        self.steps[self.next_step] = self.step_energy;
+        self.next_step = (self.next_step + 1) % SHORT_TERM_STEPS;
+        self.steps_filled = (self.steps_filled + 1).min(SHORT_TERM_STEPS);
+        (self.step_frames, self.step_energy) = (0, 0.0);
+
+        let momentary = to_lufs(self.window_mean_square(MOMENTARY_STEPS));
+        let short_term = to_lufs(self.window_mean_square(SHORT_TERM_STEPS));
+        self.values.momentary.store(momentary.to_bits(), Ordering::Relaxed);
+        self.values.short_term.store(short_term.to_bits(), Ordering::Relaxed);
+
+        if self.steps_filled >= MOMENTARY_STEPS && momentary > ABSOLUTE_GATE {
+            let bin = (((momentary - ABSOLUTE_GATE) / BIN_WIDTH) as usize).min(BIN_COUNT - 1);
+            self.bins[bin].0 += self.window_mean_square(MOMENTARY_STEPS);
+            self.bins[bin].1 += 1;
+            self.values.integrated.store(self.integrated().to_bits(), Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the mean square over the latest steps, or over every step so far if there are fewer.
+    fn window_mean_square(&self, steps: usize) -> f64 {
+        let steps = steps.min(self.steps_filled);
+        let energy: f64 = (1..=steps).map(|back| self.steps[(self.next_step + SHORT_TERM_STEPS - back) % SHORT_TERM_STEPS]).sum();
+        energy / (steps * self.step_length).max(1) as f64
+    }
+
+    /// Gated loudness of the blocks collected so far: the blocks above the absolute gate set a
+    /// relative gate, and the blocks above both are averaged.
+    fn integrated(&self) -> f32 {
+        let mean = |bins: &[(f64, u32)]| {
+            let (energy, count) = bins.iter().fold((0.0, 0), |(energy, count), bin| (energy + bin.0, count + bin.1));
+            if count == 0 { 0.0 } else { energy / count as f64 }
+        };
+        let threshold = to_lufs(mean(&self.bins)) + RELATIVE_GATE;
+        let first = (((threshold - ABSOLUTE_GATE) / BIN_WIDTH).ceil().max(0.0) as usize).min(BIN_COUNT);
+        to_lufs(mean(&self.bins[first..]))
+    }
+
+    fn publish_levels(&self) {
+        self.values.peak.store(to_db(self.peak).to_bits(), Ordering::Relaxed);
+        self.values.true_peak.store(to_db(self.true_peak).to_bits(), Ordering::Relaxed);
+        self.values.rms.store(to_db(self.mean_square.sqrt()).to_bits(), Ordering::Relaxed);
+    }
+}
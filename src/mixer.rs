@@ -1,30 +1,708 @@
+use std::fmt;
+use serde::{Serialize, Deserialize};
+use crate::effects::{EffectChain, EffectContext, EffectKind, Reverb};
+use crate::meter::Meter;
+use crate::sample::SampleRegion;
 use crate::synthesizer::Track;
 
+/// What a mixer track plays.
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum TrackSource {
+    Synth, // The live voice and played notes of the synthesizer
+    #[default]
+    Clips, // The timeline clips assigned to the track
+    File(SampleRegion), // An audio file, played from the start of the timeline
+}
+
+/// How a pan position maps to left and right gains, named by the level of a centred track.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum PanLaw {
+    Linear,         // 0 dB in the centre; the far side fades linearly as the track moves over
+    #[default]
+    ConstantPower,  // -3 dB in the centre, so the loudness stays even across the field
+    Compromise,     // -4.5 dB in the centre, between constant power and -6 dB
+    LinearSum,      // -6 dB in the centre, so a centred track sums to unity in mono
+}
+
+impl PanLaw {
+    /// Every pan law, in display order.
+    pub const ALL: [PanLaw; 4] = [PanLaw::Linear, PanLaw::ConstantPower, PanLaw::Compromise, PanLaw::LinearSum];
+
+    /// Returns the display name of the pan law.
+    pub fn name(&self) -> &'static str {
+        match self {
+            PanLaw::Linear => "0 dB",
+            PanLaw::ConstantPower => "-3 dB",
+            PanLaw::Compromise => "-4.5 dB",
+            PanLaw::LinearSum => "-6 dB",
+        }
+    }
+
+    /// Returns the left and right gains for a pan position.
+    ///
+    /// # Parameters
+    /// - `pan`: The position, from -1.0 (hard left) to 1.0 (hard right).
+    pub fn gains(&self, pan: f32) -> (f32, f32) {
+        let position = (pan.clamp(-1.0, 1.0) + 1.0) / 2.0; // 0.0 hard left, 1.0 hard right
+        let linear = (1.0 - position, position);
+        let angle = position * std::f32::consts::FRAC_PI_2;
+        let power = (angle.cos(), angle.sin());
+        match self {
+            PanLaw::Linear => ((2.0 * linear.0).min(1.0), (2.0 * linear.1).min(1.0)),
+            PanLaw::ConstantPower => power,
+            PanLaw::Compromise => ((linear.0 * power.0).sqrt(), (linear.1 * power.1).sqrt()), // Geometric mean of -3 dB and -6 dB
+            PanLaw::LinearSum => linear,
+        }
+    }
+}
+
+/// How a track's pan control treats its source.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum PanMode {
+    #[default]
+    Pan,     // Places the source with the mixer's pan law; a stereo image narrows to mono towards either side
+    Balance, // Keeps a stereo source's image and turns down the opposite side
+}
+
+/// What happens to the other solos when a track is soloed.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum SoloMode {
+    #[default]
+    Additive,  // Solos add up, so several tracks can be heard together
+    Exclusive, // Soloing a track clears every other solo
+}
+
+/// What a bus is used for.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum BusKind {
+    #[default]
+    Group,  // Sub-mix of the tracks routed to it
+    Return, // Shared effect, fed by aux sends
+}
+
+/// A send from a track or bus to another bus.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AuxSend {
+    pub bus: String,     // ID of the receiving bus
+    pub level: f32,      // Send gain, from 0.0 to 1.0
+    pub pre_fader: bool, // Tap the signal before the volume fader, so fader moves leave the send alone
+    #[serde(skip)]
+    target: Option<usize>, // Index of the receiving bus, resolved by `Mixer::begin_block`
+}
+
+impl AuxSend {
+    /// Creates a send to the bus with the given ID.
+    pub fn new(bus: &str, level: f32, pre_fader: bool) -> Self {
+        AuxSend { bus: bus.to_string(), level, pre_fader, target: None }
+    }
+}
+
+/// A group or return bus: the stereo sum of what is routed or sent to it, through its own inserts.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Bus {
+    pub id: String,
+    pub kind: BusKind,
+    pub volume: f32,
+    pub muted: bool,
+    #[serde(default)]
+    pub solo: bool, // Heard alone, together with the tracks and buses feeding it
+    #[serde(default = "default_bus_solo_safe")]
+    pub solo_safe: bool, // Keep playing while others are soloed; on for new return buses
+    pub pan: f32, // Balance from -1.0 (left only) to 1.0 (right only)
+    pub inserts: EffectChain, // Effects on the bus, before its fader
+    #[serde(default)]
+    pub output: String, // ID of the bus this one feeds; empty for the master bus
+    #[serde(default)]
+    pub sends: Vec<AuxSend>,
+    #[serde(skip)]
+    input: [Vec<f32>; 2], // Left and right sum of the current block
+    #[serde(skip)]
+    destination: Option<usize>, // Index of the output bus, resolved by `Mixer::begin_block`
+}
+
+impl Bus {
+    /// Creates a bus feeding the master bus. Return buses start with a fully wet reverb.
+    pub fn new(id: &str, kind: BusKind) -> Self {
+        let mut inserts = EffectChain::new();
+        if kind == BusKind::Return {
+            inserts.add(EffectKind::Reverb(Reverb::new(0.7, 0.5, 1.0))); // The dry signal already reaches the master through the tracks
+        }
+        Bus {
+            id: id.to_string(),
+            kind,
+            volume: 1.0,
+            muted: false,
+            solo: false,
+            solo_safe: kind == BusKind::Return, // Returns carry the effects of whatever is soloed
+            pan: 0.0,
+            inserts,
+            output: String::new(),
+            sends: Vec::new(),
+            input: [Vec::new(), Vec::new()],
+            destination: None,
+        }
+    }
+
+    /// Adds a frame to the bus input, if the frame lies within the current block.
+    fn add_input(&mut self, frame: usize, (left, right): (f32, f32)) {
+        let [input_left, input_right] = &mut self.input;
+        if let (Some(input_left), Some(input_right)) = (input_left.get_mut(frame), input_right.get_mut(frame)) {
+            *input_left += left;
+            *input_right += right;
+        }
+    }
+}
+
+/// A track or bus, as the source of a connection.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Node {
+    Track(usize),
+    Bus(usize),
+}
+
+/// A routing change that the mixer cannot play.
+#[derive(Clone, PartialEq, Debug)]
+pub enum RoutingError {
+    UnknownBus(String),        // A track or bus feeds a bus that does not exist
+    DuplicateBus(String),      // Two buses share an ID, so connections to it are ambiguous
+    FeedbackLoop(Vec<String>), // These buses feed each other in a cycle
+}
+
+impl fmt::Display for RoutingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RoutingError::UnknownBus(id) => write!(f, "no bus is named \"{}\"", id),
+            RoutingError::DuplicateBus(id) => write!(f, "more than one bus is named \"{}\"", id),
+            RoutingError::FeedbackLoop(ids) => write!(f, "feedback loop through {}", ids.join(" -> ")),
+        }
+    }
+}
+
+impl std::error::Error for RoutingError {}
+
+/// Tracks and buses mixed into one stereo output.
+///
+/// Routing forms a graph: every track and bus feeds one bus, or the master bus when its
+/// `output` is empty, and may send to more buses. The master bus is the mixer output; its
+/// insert chain is `Synthesizer::master`.
+#[derive(Serialize, Deserialize, Default)]
 pub struct Mixer {
     pub tracks: Vec<Track>, // Store tracks for mixing
+    #[serde(default)]
+    pub pan_law: PanLaw, // Pan law shared by every track
+    #[serde(default)]
+    pub solo_mode: SoloMode, // Whether solos add up or replace each other
+    #[serde(default)]
+    pub buses: Vec<Bus>, // Group and return buses
+    #[serde(skip)]
+    bus_order: Vec<usize>, // Buses in processing order, each before the buses it feeds
+    #[serde(skip)]
+    pending_inputs: Vec<usize>, // Unprocessed buses feeding each bus, while sorting
+    #[serde(skip)]
+    sample_rate: f32, // Rate the inserts were prepared for, used for buses added later
+    #[serde(skip)]
+    keys: Vec<[Vec<f32>; 2]>, // Source of each track in the current block, for sidechains
+    #[serde(skip)]
+    keyed: bool, // Whether any insert follows a sidechain key, resolved by `begin_block`
+    #[serde(skip)]
+    tracks_heard: Vec<bool>, // Whether each track is audible in the current block
+    #[serde(skip)]
+    buses_heard: Vec<bool>, // Whether each bus is audible in the current block
 }
 
 impl Mixer {
     pub fn new() -> Self {
         Mixer {
             tracks: Vec::new(), // Initialize with no tracks
+            pan_law: PanLaw::default(),
+            solo_mode: SoloMode::default(),
+            buses: Vec::new(),
+            bus_order: Vec::new(),
+            pending_inputs: Vec::new(),
+            sample_rate: 0.0,
+            keys: Vec::new(),
+            keyed: false,
+            tracks_heard: Vec::new(),
+            buses_heard: Vec::new(),
+        }
+    }
+
+    /// Prepares the inserts of every track and bus for a sample rate.
+    pub fn prepare(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        for track in &mut self.tracks {
+            track.inserts.prepare(sample_rate);
+        }
+        for bus in &mut self.buses {
+            bus.inserts.prepare(sample_rate);
+        }
+    }
+
+    /// Returns the index of the bus with the given ID.
+    pub fn bus_index(&self, id: &str) -> Option<usize> {
+        find_bus(&self.buses, id)
+    }
+
+    /// Returns a track ID of the form "Track N" that no track uses yet, counting up from the
+    /// number of tracks so that removed tracks do not hand their ID to a new one.
+    pub fn new_track_id(&self) -> String {
+        unused_id("Track", self.tracks.len() + 1, |id| find_track(&self.tracks, id).is_some())
+    }
+
+    /// Returns a bus ID of the form "Bus N" that no bus uses yet.
+    pub fn new_bus_id(&self) -> String {
+        unused_id("Bus", self.buses.len() + 1, |id| self.bus_index(id).is_some())
+    }
+
+    /// Adds a bus, feeding the master bus unless its `output` says otherwise.
+    pub fn add_bus(&mut self, mut bus: Bus) -> Result<(), RoutingError> {
+        if self.bus_index(&bus.id).is_some() {
+            return Err(RoutingError::DuplicateBus(bus.id));
+        }
+        if self.sample_rate > 0.0 {
+            bus.inserts.prepare(self.sample_rate);
+        }
+        self.buses.push(bus);
+        let result = self.validate_routing();
+        if result.is_err() {
+            self.buses.pop();
+        }
+        result
+    }
+
+    /// Removes a bus. Whatever fed it goes to the master bus instead, and sends to it are removed.
+    pub fn remove_bus(&mut self, index: usize) {
+        if index >= self.buses.len() {
+            return;
+        }
+        let id = self.buses.remove(index).id;
+        let tracks = self.tracks.iter_mut().map(|track| (&mut track.output, &mut track.sends));
+        let buses = self.buses.iter_mut().map(|bus| (&mut bus.output, &mut bus.sends));
+        for (output, sends) in tracks.chain(buses) {
+            if *output == id {
+                output.clear();
+            }
+            sends.retain(|send| send.bus != id);
+        }
+    }
+
+    /// Routes a track or bus to the bus with the given ID, or to the master bus if `output` is empty.
+    ///
+    /// # Returns
+    /// - An error, with the routing left unchanged, if the bus does not exist or the change would create a feedback loop.
+    pub fn set_output(&mut self, node: Node, output: &str) -> Result<(), RoutingError> {
+        let previous = match node {
+            Node::Track(index) => self.tracks.get_mut(index).map(|track| std::mem::replace(&mut track.output, output.to_string())),
+            Node::Bus(index) => self.buses.get_mut(index).map(|bus| std::mem::replace(&mut bus.output, output.to_string())),
+        };
+        let Some(previous) = previous else {
+            return Ok(());
+        };
+        let result = self.validate_routing();
+        if result.is_err() {
+            match node {
+                Node::Track(index) => self.tracks[index].output = previous,
+                Node::Bus(index) => self.buses[index].output = previous,
+            }
+        }
+        result
+    }
+
+    /// Adds an aux send to a track or bus.
+    ///
+    /// # Returns
+    /// - An error, with the send left out, if the bus does not exist or the send would create a feedback loop.
+    pub fn add_send(&mut self, node: Node, send: AuxSend) -> Result<(), RoutingError> {
+        let sends = match node {
+            Node::Track(index) => self.tracks.get_mut(index).map(|track| &mut track.sends),
+            Node::Bus(index) => self.buses.get_mut(index).map(|bus| &mut bus.sends),
+        };
+        let Some(sends) = sends else {
+            return Ok(());
+        };
+        sends.push(send);
+        let result = self.validate_routing();
+        if result.is_err() {
+            match node {
+                Node::Track(index) => self.tracks[index].sends.pop(),
+                Node::Bus(index) => self.buses[index].sends.pop(),
+            };
+        }
+        result
+    }
+
+    /// Checks that every connection names an existing bus, that bus IDs are unique and that
+    /// no bus feeds back into itself, directly or through other buses.
+    pub fn validate_routing(&self) -> Result<(), RoutingError> {
+        for (index, bus) in self.buses.iter().enumerate() {
+            if self.buses[..index].iter().any(|other| other.id == bus.id) {
+                return Err(RoutingError::DuplicateBus(bus.id.clone()));
+            }
+        }
+        let tracks = self.tracks.iter().map(|track| (&track.output, &track.sends));
+        let buses = self.buses.iter().map(|bus| (&bus.output, &bus.sends));
+        for (output, sends) in tracks.chain(buses) {
+            let targets = std::iter::once(output).filter(|output| !output.is_empty()).chain(sends.iter().map(|send| &send.bus));
+            for target in targets {
+                if self.bus_index(target).is_none() {
+                    return Err(RoutingError::UnknownBus(target.clone()));
+                }
+            }
+        }
+
+        let mut visited = vec![false; self.buses.len()];
+        let mut path = Vec::new();
+        for start in 0..self.buses.len() {
+            if let Some(cycle) = self.find_loop(start, &mut visited, &mut path) {
+                return Err(RoutingError::FeedbackLoop(cycle.iter().map(|&index| self.buses[index].id.clone()).collect()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Depth-first search for a cycle through `index`; `path` holds the buses being followed.
+    fn find_loop(&self, index: usize, visited: &mut [bool], path: &mut Vec<usize>) -> Option<Vec<usize>> {
+        if let Some(position) = path.iter().position(|&on_path| on_path == index) {
+            let mut cycle = path[position..].to_vec();
+            cycle.push(index); // Name the first bus again to close the loop
+            return Some(cycle);
+        }
+        if visited[index] {
+            return None; // Already searched from here without finding a loop
+        }
+        visited[index] = true;
+        path.push(index);
+        let bus = &self.buses[index];
+        let targets = std::iter::once(&bus.output).chain(bus.sends.iter().map(|send| &send.bus));
+        for target in targets.filter_map(|id| self.bus_index(id)) {
+            if let Some(cycle) = self.find_loop(target, visited, path) {
+                return Some(cycle);
+            }
+        }
+        path.pop();
+        None
+    }
+
+    /// Mutes or unmutes a track together with the rest of its group.
+    pub fn set_muted(&mut self, index: usize, muted: bool) {
+        for track in self.group_members(index) {
+            track.muted = muted;
+        }
+    }
+
+    /// Solos or unsolos a track together with the rest of its group. In exclusive mode,
+    /// soloing also clears the solo of every track outside the group and of every bus.
+    pub fn set_solo(&mut self, index: usize, solo: bool) {
+        let Some(group) = self.tracks.get(index).map(|track| track.group.clone()) else {
+            return;
+        };
+        if solo && self.solo_mode == SoloMode::Exclusive {
+            for (other, track) in self.tracks.iter_mut().enumerate() {
+                if other != index && (group.is_empty() || track.group != group) {
+                    track.solo = false;
+                }
+            }
+            for bus in &mut self.buses {
+                bus.solo = false;
+            }
+        }
+        for track in self.group_members(index) {
+            track.solo = solo;
         }
     }
 
-    pub fn mix_tracks(&self, time: f32) -> f32 {
-        let mut mixed_sample = 0.0;
-        for track in &self.tracks {
-            if !track.muted {
-                // Generate a sine wave sample for the track and scale by volume
-                let raw_sample = track.volume * (2.0 * std::f32::consts::PI * time).sin();
-                mixed_sample += raw_sample; // Sum raw samples
+    /// Solos or unsolos a bus. In exclusive mode, soloing also clears every other solo.
+    pub fn set_bus_solo(&mut self, index: usize, solo: bool) {
+        if index >= self.buses.len() {
+            return;
+        }
+        if solo && self.solo_mode == SoloMode::Exclusive {
+            for track in &mut self.tracks {
+                track.solo = false;
+            }
+            for bus in &mut self.buses {
+                bus.solo = false;
             }
         }
-        mixed_sample // Return the mixed sample
+        self.buses[index].solo = solo;
+    }
+
+    /// Returns `true` if any track or bus is soloed, which silences everything that is not
+    /// soloed, solo-safe or on the path of a solo. The synthesizer also silences what it plays
+    /// outside the mixer.
+    pub fn any_solo(&self) -> bool {
+        self.tracks.iter().any(|track| track.solo) || self.buses.iter().any(|bus| bus.solo)
+    }
+
+    /// Returns `true` if a track is heard: it is not muted, and either nothing is soloed,
+    /// it is soloed or solo-safe itself, or it feeds a soloed bus.
+    pub fn is_audible(&self, index: usize) -> bool {
+        self.tracks.get(index).is_some_and(|track| self.track_audible(track, self.any_solo()))
+    }
+
+    /// Returns `true` if a bus is heard: it is not muted, and either nothing is soloed, it is
+    /// soloed or solo-safe itself, it feeds a soloed bus, or a soloed track or bus feeds it.
+    pub fn is_bus_audible(&self, index: usize) -> bool {
+        index < self.buses.len() && self.bus_audible(index, self.any_solo())
+    }
+
+    fn track_audible(&self, track: &Track, any_solo: bool) -> bool {
+        !track.muted && (!any_solo || track.solo || track.solo_safe || self.feeds_solo(&track.output))
+    }
+
+    fn bus_audible(&self, index: usize, any_solo: bool) -> bool {
+        let bus = &self.buses[index];
+        !bus.muted && (!any_solo || bus.solo || bus.solo_safe || self.feeds_solo(&bus.output) || self.fed_by_solo(index))
+    }
+
+    /// Returns the buses reached by following outputs from `output`, nearest first. At most
+    /// every bus is visited once, so a feedback loop cannot trap it.
+    fn output_path<'a>(&'a self, output: &str) -> impl Iterator<Item = usize> + 'a {
+        std::iter::successors(find_bus(&self.buses, output), |&index| find_bus(&self.buses, &self.buses[index].output))
+            .take(self.buses.len())
+    }
+
+    /// Returns `true` if an output leads, directly or through other buses, to a soloed bus.
+    fn feeds_solo(&self, output: &str) -> bool {
+        self.output_path(output).any(|index| self.buses[index].solo)
+    }
+
+    /// Returns `true` if a soloed track or bus reaches the bus at `index` through its outputs.
+    fn fed_by_solo(&self, index: usize) -> bool {
+        let tracks = self.tracks.iter().filter(|track| track.solo).map(|track| &track.output);
+        let buses = self.buses.iter().filter(|bus| bus.solo).map(|bus| &bus.output);
+        tracks.chain(buses).any(|output| self.output_path(output).any(|target| target == index))
     }
 
-    pub fn apply_mixing(&self, time: f32) -> f32 {
-        self.mix_tracks(time) // Use `mix_tracks`
+    /// Returns the track at `index` and the other tracks of its group.
+    fn group_members(&mut self, index: usize) -> impl Iterator<Item = &mut Track> {
+        let group = self.tracks.get(index).map(|track| track.group.clone()).unwrap_or_default();
+        self.tracks
+            .iter_mut()
+            .enumerate()
+            .filter(move |(other, track)| *other == index || (!group.is_empty() && track.group == group))
+            .map(|(_, track)| track)
+    }
+
+    /// Prepares the buses for a block: clears their inputs, decides what mute and solo let
+    /// through, looks up the buses named by each connection and the tracks keying each
+    /// sidechain, and sorts the buses so that each one
+    /// is processed before the buses it feeds.
+    /// Buses caught in a feedback loop are left out, so they stay silent instead of feeding back.
+    ///
+    /// # Parameters
+    /// - `length`: The number of frames in the block.
+    pub fn begin_block(&mut self, length: usize) {
+        for index in 0..self.buses.len() {
+            let destination = find_bus(&self.buses, &self.buses[index].output);
+            for send in 0..self.buses[index].sends.len() {
+                let target = find_bus(&self.buses, &self.buses[index].sends[send].bus);
+                self.buses[index].sends[send].target = target;
+            }
+            let bus = &mut self.buses[index];
+            bus.destination = destination;
+            for channel in &mut bus.input {
+                channel.clear();
+                channel.resize(length, 0.0); // Keeps its capacity, so only the first blocks allocate
+            }
+        }
+        for track in &mut self.tracks {
+            track.destination = find_bus(&self.buses, &track.output);
+            for send in &mut track.sends {
+                send.target = find_bus(&self.buses, &send.bus);
+            }
+            for channel in &mut track.block {
+                channel.clear();
+                channel.resize(length, 0.0);
+            }
+        }
+        let any_solo = self.any_solo();
+        let (mut tracks_heard, mut buses_heard) = (std::mem::take(&mut self.tracks_heard), std::mem::take(&mut self.buses_heard));
+        tracks_heard.clear();
+        tracks_heard.extend(self.tracks.iter().map(|track| self.track_audible(track, any_solo)));
+        buses_heard.clear();
+        buses_heard.extend((0..self.buses.len()).map(|index| self.bus_audible(index, any_solo)));
+        (self.tracks_heard, self.buses_heard) = (tracks_heard, buses_heard);
+
+        self.keyed = false;
+        for index in 0..self.tracks.len() {
+            let mut inserts = std::mem::replace(&mut self.tracks[index].inserts, EffectChain::new()); // Frees the tracks for the lookup
+            self.keyed |= inserts.resolve_sidechains(|id| find_track(&self.tracks, id));
+            self.tracks[index].inserts = inserts;
+        }
+        for bus in &mut self.buses {
+            self.keyed |= bus.inserts.resolve_sidechains(|id| find_track(&self.tracks, id));
+        }
+
+        // Kahn's algorithm, using `bus_order` as the queue
+        self.pending_inputs.clear();
+        self.pending_inputs.resize(self.buses.len(), 0);
+        for bus in &self.buses {
+            for target in bus.destination.into_iter().chain(bus.sends.iter().filter_map(|send| send.target)) {
+                self.pending_inputs[target] += 1;
+            }
+        }
+        self.bus_order.clear();
+        self.bus_order.extend((0..self.buses.len()).filter(|&index| self.pending_inputs[index] == 0));
+        let mut next = 0;
+        while let Some(&index) = self.bus_order.get(next) {
+            next += 1;
+            let bus = &self.buses[index];
+            for target in bus.destination.into_iter().chain(bus.sends.iter().filter_map(|send| send.target)) {
+                self.pending_inputs[target] -= 1;
+                if self.pending_inputs[target] == 0 {
+                    self.bus_order.push(target);
+                }
+            }
+        }
+    }
+
+    /// Stores one frame of each track's source in the block started with `begin_block`.
+    ///
+    /// Clip tracks take what was gathered with `add_clip_input` since the last
+    /// `clear_clip_inputs`.
+    ///
+    /// # Parameters
+    /// - `frame`: The position of the frame in the block.
+    /// - `time`: The timeline position in seconds, used by file tracks.
+    /// - `increment`: The time between frames in seconds.
+    /// - `synth`: The synthesizer's `(left, right)` frame, used by synth tracks.
+    pub fn add_sources(&mut self, frame: usize, time: f64, increment: f64, synth: (f32, f32)) {
+        for track in &mut self.tracks {
+            let (source_left, source_right) = match &track.source {
+                TrackSource::Synth => synth,
+                TrackSource::Clips => (track.clip_input, track.clip_input), // Clips are mono
+                TrackSource::File(region) => region.frame_at(time, increment),
+            };
+            let [block_left, block_right] = &mut track.block;
+            if let (Some(left), Some(right)) = (block_left.get_mut(frame), block_right.get_mut(frame)) {
+                (*left, *right) = (source_left, source_right);
+            }
+        }
+    }
+
+    /// Runs each track's block through its inserts, then mixes the audible tracks with gain
+    /// and pan. Sidechains are keyed by the source of their key track, before its inserts and
+    /// fader, so a muted track can still key others. Tracks routed to the master bus are added to the block; the rest, and every
+    /// aux send, go to the bus inputs for `process_buses`. Each track meter measures what the
+    /// track passes on after its fader. Track inserts are not delay-compensated, so latency
+    /// on a track delays it against the rest of the mix.
+    ///
+    /// # Parameters
+    /// - `left`, `right`: The master bus block.
+    /// - `context`: Sample rate and tempo for the inserts.
+    pub fn mix_tracks(&mut self, left: &mut [f32], right: &mut [f32], context: &EffectContext) {
+        if self.keyed {
+            self.keys.resize_with(self.tracks.len(), Default::default);
+            for (key, track) in self.keys.iter_mut().zip(&self.tracks) {
+                for (key, source) in key.iter_mut().zip(&track.block) {
+                    key.clear();
+                    key.extend_from_slice(source); // Keeps its capacity, so only the first blocks allocate
+                }
+            }
+        }
+        for (index, track) in self.tracks.iter_mut().enumerate() {
+            let [block_left, block_right] = &mut track.block;
+            let length = block_left.len().min(left.len());
+            track.inserts.process_keyed(&mut block_left[..length], &mut block_right[..length], &self.keys, context); // Muted tracks too, so their tails carry on
+            let audible = self.tracks_heard.get(index).copied().unwrap_or(false);
+            for frame in 0..length {
+                let pre_fader = track.pan_frame(track.block[0][frame], track.block[1][frame], self.pan_law);
+                let post_fader = if audible { (pre_fader.0 * track.volume, pre_fader.1 * track.volume) } else { (0.0, 0.0) };
+                (track.block[0][frame], track.block[1][frame]) = post_fader; // Kept for the meter
+                if !audible {
+                    continue;
+                }
+                feed_sends(&mut self.buses, &track.sends, frame, pre_fader, post_fader);
+                match track.destination {
+                    Some(bus) => self.buses[bus].add_input(frame, post_fader),
+                    None => {
+                        left[frame] += post_fader.0; // Sum the tracks at their volume
+                        right[frame] += post_fader.1;
+                    }
+                }
+            }
+            track.meter.process(&track.block[0][..length], &track.block[1][..length], context.sample_rate);
+        }
+    }
+
+    /// Mixes the tracks and then the buses into the master bus block.
+    pub fn apply_mixing(&mut self, left: &mut [f32], right: &mut [f32], context: &EffectContext) {
+        self.mix_tracks(left, right, context); // Use `mix_tracks`
+        self.process_buses(left, right, context);
+    }
+
+    /// Runs each bus through its inserts, balance and fader, in routing order, and adds the
+    /// buses that feed the master bus to the block. Bus inserts are not delay-compensated, so
+    /// latency on a bus delays it against the rest of the mix.
+    ///
+    /// # Parameters
+    /// - `left`, `right`: The master bus block, as mixed by `mix_tracks`.
+    /// - `context`: Sample rate and tempo for the inserts.
+    pub fn process_buses(&mut self, left: &mut [f32], right: &mut [f32], context: &EffectContext) {
+        for position in 0..self.bus_order.len() {
+            let index = self.bus_order[position];
+            let [mut input_left, mut input_right] = std::mem::take(&mut self.buses[index].input); // Frees the other buses for sends
+            let sends = std::mem::take(&mut self.buses[index].sends);
+            let length = input_left.len().min(left.len());
+            let bus = &mut self.buses[index];
+            bus.inserts.process_keyed(&mut input_left[..length], &mut input_right[..length], &self.keys, context);
+            let (volume, pan, destination) = (bus.volume, bus.pan, bus.destination);
+            if self.buses_heard.get(index).copied().unwrap_or(false) {
+                for frame in 0..length {
+                    let pre_fader = balance(input_left[frame], input_right[frame], pan);
+                    let post_fader = (pre_fader.0 * volume, pre_fader.1 * volume);
+                    feed_sends(&mut self.buses, &sends, frame, pre_fader, post_fader);
+                    match destination {
+                        Some(target) => self.buses[target].add_input(frame, post_fader),
+                        None => {
+                            left[frame] += post_fader.0;
+                            right[frame] += post_fader.1;
+                        }
+                    }
+                }
+            }
+            let bus = &mut self.buses[index];
+            (bus.input, bus.sends) = ([input_left, input_right], sends);
+        }
+    }
+
+    /// Clears the state of every track and bus insert, and the track meters.
+    pub fn reset(&mut self) {
+        for track in &mut self.tracks {
+            track.inserts.reset();
+            track.meter.reset();
+        }
+        for bus in &mut self.buses {
+            bus.inserts.reset();
+        }
+    }
+
+    /// Returns `true` if a track plays the synthesizer, which then reaches the output only
+    /// through the mixer.
+    pub fn uses_synth(&self) -> bool {
+        self.tracks.iter().any(|track| track.source == TrackSource::Synth)
+    }
+
+    /// Silences the clip input of every track, before the clips of the next frame are added.
+    pub fn clear_clip_inputs(&mut self) {
+        for track in &mut self.tracks {
+            track.clip_input = 0.0;
+        }
+    }
+
+    /// Adds a clip sample to the clip track with the given ID.
+    ///
+    /// # Returns
+    /// - `false` if no clip track has that ID, so the caller should play the sample directly.
+    pub fn add_clip_input(&mut self, track_id: &str, sample: f32) -> bool {
+        match self.tracks.iter_mut().find(|track| track.id == track_id && track.source == TrackSource::Clips) {
+            Some(track) => {
+                track.clip_input += sample;
+                true
+            }
+            None => false,
+        }
     }
 }
 
@@ -35,6 +713,84 @@ impl Track {
             id: id.to_string(),
             volume: 0.5, // Default volume
             muted: false, // Default muted state
+            pan: 0.0, // Centred
+            pan_mode: PanMode::Pan,
+            source: TrackSource::Clips, // Silent until clips are assigned
+            solo: false,
+            solo_safe: false,
+            group: String::new(), // Not grouped
+            output: String::new(), // The master bus
+            sends: Vec::new(),
+            inserts: EffectChain::new(), // No effects until some are added
+            clip_input: 0.0,
+            block: [Vec::new(), Vec::new()],
+            meter: Meter::new(),
+            destination: None,
+        }
+    }
+
+    /// Applies the track's pan or balance to a stereo frame.
+    ///
+    /// # Parameters
+    /// - `left`, `right`: The source frame; mono sources pass the same sample twice.
+    /// - `law`: The pan law used in `PanMode::Pan`.
+    ///
+    /// # Returns
+    /// - The positioned `(left, right)` samples.
+    pub fn pan_frame(&self, left: f32, right: f32, law: PanLaw) -> (f32, f32) {
+        let pan = self.pan.clamp(-1.0, 1.0);
+        match self.pan_mode {
+            PanMode::Pan => {
+                let (mid, side) = (0.5 * (left + right), 0.5 * (left - right));
+                let (gain_left, gain_right) = law.gains(pan);
+                let width = gain_left.min(gain_right); // Full width in the centre, none at either edge
+                (mid * gain_left + side * width, mid * gain_right - side * width)
+            }
+            PanMode::Balance => balance(left, right, pan),
+        }
+    }
+}
+
+/// Turns down the side of a stereo frame opposite the balance position.
+fn balance(left: f32, right: f32, pan: f32) -> (f32, f32) {
+    let pan = pan.clamp(-1.0, 1.0);
+    (left * (1.0 - pan).min(1.0), right * (1.0 + pan).min(1.0))
+}
+
+/// Returns the first "`prefix` N" from `first` upwards that is not `taken`.
+fn unused_id(prefix: &str, first: usize, taken: impl Fn(&str) -> bool) -> String {
+    let mut number = first;
+    loop {
+        let id = format!("{} {}", prefix, number);
+        if !taken(&id) {
+            return id;
+        }
+        number += 1;
+    }
+}
+
+/// Buses from projects saved before they could be soloed keep playing through solos.
+fn default_bus_solo_safe() -> bool {
+    true
+}
+
+fn find_track(tracks: &[Track], id: &str) -> Option<usize> {
+    tracks.iter().position(|track| track.id == id)
+}
+
+fn find_bus(buses: &[Bus], id: &str) -> Option<usize> {
+    if id.is_empty() {
+        return None; // The master bus
+    }
+    buses.iter().position(|bus| bus.id == id)
+}
+
+/// Adds a frame to the buses receiving the sends, tapped before or after the fader.
+fn feed_sends(buses: &mut [Bus], sends: &[AuxSend], frame: usize, pre_fader: (f32, f32), post_fader: (f32, f32)) {
+    for send in sends {
+        if let Some(target) = send.target {
+            let (left, right) = if send.pre_fader { pre_fader } else { post_fader };
+            buses[target].add_input(frame, (left * send.level, right * send.level));
         }
     }
 }
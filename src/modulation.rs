@@ -0,0 +1,197 @@
+use serde::{Serialize, Deserialize};
+use crate::oscillator::band_limited_sample;
+use crate::synthesizer::Waveform;
+use crate::noise::Rng;
+
+/// Shape of a low-frequency oscillator.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum LfoShape {
+    Waveform(Waveform), // One of the oscillator waveforms
+    SampleAndHold,      // A new random value at the start of every cycle
+}
+
+/// How fast an LFO runs.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum LfoRate {
+    Hertz(f32),  // Free-running rate in cycles per second
+    Synced(f32), // Cycle length in beats, following the project tempo
+}
+
+impl LfoRate {
+    /// Returns the rate in Hz for the given tempo in beats per minute.
+    pub fn frequency(&self, tempo: f32) -> f32 {
+        match *self {
+            LfoRate::Hertz(hz) => hz,
+            LfoRate::Synced(beats) => tempo / 60.0 / beats.max(1.0 / 64.0),
+        }
+    }
+}
+
+/// Low-frequency oscillator producing a bipolar control signal.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Lfo {
+    pub shape: LfoShape,
+    pub rate: LfoRate,
+    #[serde(skip)]
+    phase: f64, // Normalized phase in the range 0.0..1.0
+    #[serde(skip)]
+    held: f32, // Current sample-and-hold value
+    #[serde(skip)]
+    rng: Rng, // Random source for sample-and-hold
+}
+
+impl Lfo {
+    /// Creates an LFO starting at phase zero.
+    pub fn new(shape: LfoShape, rate: LfoRate) -> Self {
+        let mut lfo = Lfo { shape, rate, phase: 0.0, held: 0.0, rng: Rng::default() };
+        lfo.reset();
+        lfo
+    }
+
+    /// Restarts the LFO at phase zero.
+    pub fn reset(&mut self) {
+        self.phase = 0.0;
+        self.rng = Rng::default();
+        self.held = self.rng.next_bipolar();
+    }
+
+    /// Returns the LFO value for the current phase, in the range `-1.0..=1.0`.
+    pub fn value(&self) -> f32 {
+        match self.shape {
+            LfoShape::Waveform(waveform) => band_limited_sample(waveform, self.phase as f32, 0.0),
+            LfoShape::SampleAndHold => self.held,
+        }
+    }
+
+    /// Advances the LFO by one sample.
+    ///
+    /// # Parameters
+    /// - `sample_rate`: The sample rate in Hz.
+    /// - `tempo`: The project tempo in beats per minute, used by synced rates.
+    pub fn advance(&mut self, sample_rate: f32, tempo: f32) {
+        let next = self.phase + self.rate.frequency(tempo) as f64 / sample_rate as f64;
+        if next >= 1.0 {
+            self.held = self.rng.next_bipolar(); // Pick a new held value once per cycle
+        }
+        self.phase = next.rem_euclid(1.0);
+    }
+}
+
+/// Synthesizer parameter that can be modulated.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum ModTarget {
+    Frequency,       // Both channel frequencies, in octaves
+    FrequencyLeft,   // Left channel frequency, in octaves
+    FrequencyRight,  // Right channel frequency, in octaves
+    Amplitude,       // Output amplitude
+    WavetablePosition, // Frame position of the wavetable waveform
+    FilterCutoff,    // Filter cutoff, in octaves
+    FilterResonance, // Filter resonance
+    Delay,           // Delay wet/dry mix
+    Reverb,          // Reverb wet/dry mix
+    Chorus,          // Chorus wet/dry mix
+    Flanger,         // Flanger wet/dry mix
+    Phaser,          // Phaser wet/dry mix
+    Tremolo,         // Tremolo wet/dry mix
+    AutoPan,         // Auto-pan wet/dry mix
+}
+
+impl ModTarget {
+    /// Every modulation target, in display order.
+    pub const ALL: [ModTarget; 14] = [
+        ModTarget::Frequency,
+        ModTarget::FrequencyLeft,
+        ModTarget::FrequencyRight,
+        ModTarget::Amplitude,
+        ModTarget::WavetablePosition,
+        ModTarget::FilterCutoff,
+        ModTarget::FilterResonance,
+        ModTarget::Delay,
+        ModTarget::Reverb,
+        ModTarget::Chorus,
+        ModTarget::Flanger,
+        ModTarget::Phaser,
+        ModTarget::Tremolo,
+        ModTarget::AutoPan,
+    ];
+
+    /// Returns the name of the effect whose wet/dry mix the target modulates, in the
+    /// synthesizer's effect chain, or `None` for the synthesizer parameters.
+    pub fn effect_name(&self) -> Option<&'static str> {
+        match self {
+            ModTarget::Delay => Some("Delay"),
+            ModTarget::Reverb => Some("Reverb"),
+            ModTarget::Chorus => Some("Chorus"),
+            ModTarget::Flanger => Some("Flanger"),
+            ModTarget::Phaser => Some("Phaser"),
+            ModTarget::Tremolo => Some("Tremolo"),
+            ModTarget::AutoPan => Some("Auto-Pan"),
+            _ => None,
+        }
+    }
+
+    /// Applies a modulation amount to a parameter value.
+    ///
+    /// Frequency targets are modulated in octaves so vibrato sounds even across the range;
+    /// the others are offset linearly and kept within their valid range.
+    ///
+    /// # Parameters
+    /// - `value`: The unmodulated parameter value.
+    /// - `amount`: The LFO output multiplied by the route depth.
+    ///
+    /// # Returns
+    /// - The modulated parameter value.
+    pub fn apply(&self, value: f32, amount: f32) -> f32 {
+        match self {
+            ModTarget::Frequency | ModTarget::FrequencyLeft | ModTarget::FrequencyRight | ModTarget::FilterCutoff => {
+                value * 2f32.powf(amount)
+            }
+            _ => (value + amount).clamp(0.0, 1.0), // Amplitude, resonance, wavetable position and effect mixes
+        }
+    }
+}
+
+/// Connection from an LFO to a parameter.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ModRoute {
+    pub source: usize,     // Index of the LFO in the matrix
+    pub target: ModTarget, // Parameter to modulate
+    pub depth: f32,        // Scale applied to the LFO output
+}
+
+/// The set of LFOs and the routes connecting them to synthesizer parameters.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ModMatrix {
+    pub lfos: Vec<Lfo>,
+    pub routes: Vec<ModRoute>,
+}
+
+impl ModMatrix {
+    /// Advances every LFO by one sample.
+    pub fn advance(&mut self, sample_rate: f32, tempo: f32) {
+        for lfo in &mut self.lfos {
+            lfo.advance(sample_rate, tempo);
+        }
+    }
+
+    /// Removes an LFO together with the routes that use it.
+    pub fn remove_lfo(&mut self, index: usize) {
+        if index >= self.lfos.len() {
+            return;
+        }
+        self.lfos.remove(index);
+        self.routes.retain(|route| route.source != index);
+        for route in &mut self.routes {
+            if route.source > index {
+                route.source -= 1; // Keep the remaining routes pointing at the same LFOs
+            }
+        }
+    }
+
+    /// Restarts every LFO at phase zero.
+    pub fn reset(&mut self) {
+        for lfo in &mut self.lfos {
+            lfo.reset();
+        }
+    }
+}
@@ -0,0 +1,100 @@
+/// Small seedable pseudo-random number generator (xorshift32).
+///
+/// The same seed always produces the same sequence, so anything rendered from it is reproducible.
+#[derive(Clone, Copy, Debug)]
+pub struct Rng {
+    state: u32,
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Rng::new(1)
+    }
+}
+
+impl Rng {
+    /// Creates a generator from a seed. A seed of zero is replaced, since xorshift would stay at zero.
+    pub fn new(seed: u32) -> Self {
+        Rng { state: if seed == 0 { 0x9E37_79B9 } else { seed } }
+    }
+
+    /// Returns the next 32 random bits.
+    pub fn next_u32(&mut self) -> u32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        self.state
+    }
+
+    /// Returns a random value in the range `-1.0..=1.0`.
+    pub fn next_bipolar(&mut self) -> f32 {
+        self.next_u32() as f32 / u32::MAX as f32 * 2.0 - 1.0
+    }
+}
+
+/// Number of random rows summed by the Voss-McCartney pink noise algorithm.
+const PINK_ROWS: usize = 16;
+
+/// White, pink and brown noise from a seeded generator.
+#[derive(Clone, Copy, Debug)]
+pub struct NoiseGenerator {
+    seed: u32,                     // Seed restored by `reset`
+    rng: Rng,
+    pink_rows: [f32; PINK_ROWS],   // Random values updated at octave-spaced rates
+    pink_sum: f32,                 // Running sum of `pink_rows`
+    pink_counter: u32,             // Selects which row to update
+    brown: f32,                    // Integrator state for brown noise
+}
+
+impl Default for NoiseGenerator {
+    fn default() -> Self {
+        NoiseGenerator::new(1)
+    }
+}
+
+impl NoiseGenerator {
+    /// Creates a noise generator from a seed.
+    pub fn new(seed: u32) -> Self {
+        NoiseGenerator {
+            seed,
+            rng: Rng::new(seed),
+            pink_rows: [0.0; PINK_ROWS],
+            pink_sum: 0.0,
+            pink_counter: 0,
+            brown: 0.0,
+        }
+    }
+
+    /// Restarts the sequence from the seed.
+    pub fn reset(&mut self) {
+        *self = NoiseGenerator::new(self.seed);
+    }
+
+    /// Restarts the sequence from a new seed.
+    pub fn reseed(&mut self, seed: u32) {
+        *self = NoiseGenerator::new(seed);
+    }
+
+    /// Returns the next white noise sample, uniformly distributed in `-1.0..=1.0`.
+    pub fn white(&mut self) -> f32 {
+        self.rng.next_bipolar()
+    }
+
+    /// Returns the next pink noise sample (-3 dB per octave), using the Voss-McCartney algorithm.
+    pub fn pink(&mut self) -> f32 {
+        self.pink_counter = self.pink_counter.wrapping_add(1);
+        let row = (self.pink_counter.trailing_zeros() as usize).min(PINK_ROWS - 1); // Row n changes every 2^n samples
+        let value = self.rng.next_bipolar();
+        self.pink_sum += value - self.pink_rows[row];
+        self.pink_rows[row] = value;
+        let white = self.rng.next_bipolar(); // Fills in the top octave
+        ((self.pink_sum + white) / (PINK_ROWS as f32 + 1.0) * 3.0).clamp(-1.0, 1.0) // Scaled to use most of the range; rare peaks past it are clipped
+    }
+
+    /// Returns the next brown noise sample (-6 dB per octave), from leaky integration of white noise.
+    pub fn brown(&mut self) -> f32 {
+        let white = self.rng.next_bipolar();
+        self.brown = (self.brown + 0.02 * white) / 1.02; // The leak keeps the integrator from drifting away
+        (self.brown * 3.5).clamp(-1.0, 1.0)
+    }
+}
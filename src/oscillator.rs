@@ -0,0 +1,142 @@
+use std::f32::consts::PI;
+use crate::synthesizer::Waveform; // Waveform shapes rendered by the oscillator
+use crate::wavetable::Wavetable; // Tables read by `Waveform::Wavetable`
+use crate::noise::NoiseGenerator; // Seeded source for the noise waveforms
+
+/// Two-sample polynomial band-limited step (PolyBLEP) residual.
+///
+/// # Parameters
+/// - `t`: The normalized phase in the range `0.0..1.0`.
+/// - `dt`: The phase increment per sample (`frequency / sample_rate`).
+///
+/// # Returns
+/// - The correction to add around a discontinuity of height 2.
+fn poly_blep(t: f32, dt: f32) -> f32 {
+    if t < dt {
+        let t = t / dt;
+        t + t - t * t - 1.0 // Just after the discontinuity
+    } else if t > 1.0 - dt {
+        let t = (t - 1.0) / dt;
+        t * t + t + t + 1.0 // Just before the discontinuity
+    } else {
+        0.0
+    }
+}
+
+/// Integrated PolyBLEP (PolyBLAMP) residual, used to smooth slope changes.
+///
+/// # Parameters
+/// - `t`: The normalized phase in the range `0.0..1.0`.
+/// - `dt`: The phase increment per sample.
+///
+/// # Returns
+/// - The correction to add around a corner, scaled for a slope change of 2 per sample.
+fn poly_blamp(t: f32, dt: f32) -> f32 {
+    if t < dt {
+        let t = t / dt - 1.0;
+        -t * t * t / 3.0 // Just after the corner
+    } else if t > 1.0 - dt {
+        let t = (t - 1.0) / dt + 1.0;
+        t * t * t / 3.0 // Just before the corner
+    } else {
+        0.0
+    }
+}
+
+/// Generates an anti-aliased waveform sample for a normalized phase.
+///
+/// Square and sawtooth discontinuities are smoothed with PolyBLEP, and the corners
+/// of the triangle with PolyBLAMP, which keeps harmonics above Nyquist from folding
+/// back into the audible range. Wavetables need their table data, so without it they
+/// fall back to a sine; noise needs generator state and is silent here (see `Oscillator`).
+///
+/// # Parameters
+/// - `waveform`: The waveform shape to generate.
+/// - `phase`: The normalized phase in the range `0.0..1.0`.
+/// - `phase_increment`: The phase advance per sample (`frequency / sample_rate`).
+///
+/// # Returns
+/// - The generated sample in the range `-1.0..=1.0` (with slight overshoot near edges).
+pub fn band_limited_sample(waveform: Waveform, phase: f32, phase_increment: f32) -> f32 {
+    let dt = phase_increment.abs().min(0.5); // PolyBLEP needs at least two samples per period
+    match waveform {
+        Waveform::Sine | Waveform::Wavetable { .. } => (2.0 * PI * phase).sin(),
+        Waveform::Square => {
+            let naive = if phase < 0.5 { 1.0 } else { -1.0 };
+            naive + poly_blep(phase, dt) - poly_blep((phase + 0.5).fract(), dt) // Rising edge at 0, falling edge at 0.5
+        }
+        Waveform::Triangle => {
+            let naive = 4.0 * (phase - 0.5).abs() - 1.0;
+            naive + 4.0 * dt * (poly_blamp((phase + 0.5).fract(), dt) - poly_blamp(phase, dt)) // Corners at 0.5 and 0
+        }
+        Waveform::Sawtooth => {
+            let naive = 2.0 * phase - 1.0;
+            naive - poly_blep(phase, dt) // Falling edge at the wrap
+        }
+        Waveform::WhiteNoise | Waveform::PinkNoise | Waveform::BrownNoise => 0.0,
+    }
+}
+
+/// Oscillator that carries its own phase from one sample to the next.
+///
+/// Because the phase is accumulated rather than derived from absolute time, pitch stays
+/// accurate during long sessions and frequency changes take effect without phase jumps.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Oscillator {
+    phase: f64, // Normalized phase in the range 0.0..1.0, kept in f64 so rounding does not drift the pitch
+    noise: NoiseGenerator, // Source for the noise waveforms
+}
+
+impl Oscillator {
+    /// Creates a new oscillator starting at phase zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new oscillator whose noise waveforms use the given seed.
+    pub fn with_seed(seed: u32) -> Self {
+        Oscillator { phase: 0.0, noise: NoiseGenerator::new(seed) }
+    }
+
+    /// Generates the next sample and advances the phase by one sample period.
+    ///
+    /// # Parameters
+    /// - `waveform`: The waveform shape to generate.
+    /// - `frequency`: The oscillator frequency in Hz.
+    /// - `sample_rate`: The sample rate in Hz.
+    /// - `wavetables`: The loaded tables that `Waveform::Wavetable` refers to by index.
+    ///
+    /// # Returns
+    /// - The generated sample.
+    pub fn next_sample(&mut self, waveform: Waveform, frequency: f32, sample_rate: f32, wavetables: &[Wavetable]) -> f32 {
+        let phase_increment = frequency as f64 / sample_rate as f64;
+        let sample = match waveform {
+            Waveform::Wavetable { table, position } if table < wavetables.len() => {
+                wavetables[table].sample(self.phase as f32, position, phase_increment as f32)
+            }
+            Waveform::WhiteNoise => self.noise.white(),
+            Waveform::PinkNoise => self.noise.pink(),
+            Waveform::BrownNoise => self.noise.brown(),
+            _ => band_limited_sample(waveform, self.phase as f32, phase_increment as f32),
+        };
+        self.phase = (self.phase + phase_increment).rem_euclid(1.0); // Wrap to keep full precision
+        sample
+    }
+
+    /// Returns the current normalized phase.
+    pub fn phase(&self) -> f32 {
+        self.phase as f32
+    }
+
+    /// Restarts the oscillator at phase zero and replays the noise sequence from its seed.
+    pub fn reset(&mut self) {
+        self.phase = 0.0;
+        self.noise.reset();
+    }
+
+    /// Restarts the oscillator at phase zero with a new noise seed.
+    pub fn reseed(&mut self, seed: u32) {
+        self.phase = 0.0;
+        self.noise.reseed(seed);
+    }
+}
@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::f64::consts::PI;
+use serde::{Serialize, Deserialize};
+
+/// Zero crossings on each side of the anti-aliasing kernel used when reading faster than the file rate.
+const SINC_ZERO_CROSSINGS: f64 = 8.0;
+
+/// Largest step the anti-aliasing kernel is widened for; faster reads keep its width and cost.
+const MAX_FILTERED_STEP: f64 = 8.0;
+
+/// Audio file decoded into memory as floating-point samples.
+pub struct AudioBuffer {
+    pub sample_rate: u32,        // Sample rate of the file in Hz
+    pub channels: Vec<Vec<f32>>, // One buffer of samples per channel
+}
+
+impl std::fmt::Debug for AudioBuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AudioBuffer")
+            .field("sample_rate", &self.sample_rate)
+            .field("channels", &self.channels.len())
+            .field("frames", &self.frames())
+            .finish()
+    }
+}
+
+impl AudioBuffer {
+    /// Decodes a WAV file.
+    ///
+    /// # Parameters
+    /// - `filename`: The WAV file to read. Integer and float formats of any bit depth are supported.
+    pub fn from_wav(filename: &str) -> Result<Self, hound::Error> {
+        let mut reader = hound::WavReader::open(filename)?;
+        let spec = reader.spec();
+        let channel_count = spec.channels.max(1) as usize;
+        let interleaved: Vec<f32> = match spec.sample_format {
+            hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+            hound::SampleFormat::Int => {
+                let scale = (1i64 << (spec.bits_per_sample - 1)) as f32; // Full scale for the bit depth
+                reader.samples::<i32>().map(|s| s.map(|s| s as f32 / scale)).collect::<Result<_, _>>()?
+            }
+        };
+
+        let mut channels = vec![Vec::with_capacity(interleaved.len() / channel_count); channel_count];
+        for frame in interleaved.chunks_exact(channel_count) {
+            for (channel, &sample) in channels.iter_mut().zip(frame) {
+                channel.push(sample);
+            }
+        }
+        Ok(AudioBuffer { sample_rate: spec.sample_rate, channels })
+    }
+
+    /// Returns the number of sample frames.
+    pub fn frames(&self) -> usize {
+        self.channels.first().map_or(0, Vec::len)
+    }
+
+    /// Returns the length of the audio in seconds.
+    pub fn duration(&self) -> f32 {
+        self.frames() as f32 / self.sample_rate as f32
+    }
+
+    /// Reads one channel at a fractional frame position.
+    ///
+    /// Reading at up to one frame per output sample uses cubic (Catmull-Rom) interpolation,
+    /// which resamples without the dullness of linear interpolation. Faster reads would fold
+    /// everything above the output's Nyquist frequency back into the audible range, so they
+    /// use a windowed-sinc low-pass scaled to the step instead.
+    ///
+    /// # Parameters
+    /// - `channel`: The channel index. Out-of-range channels read the last channel.
+    /// - `position`: The position in frames. Positions outside the buffer read silence.
+    /// - `step`: How many frames the position advances per output sample.
+    pub fn read(&self, channel: usize, position: f64, step: f64) -> f32 {
+        let Some(data) = self.channels.get(channel).or(self.channels.last()) else {
+            return 0.0;
+        };
+        if position < 0.0 || position >= data.len() as f64 {
+            return 0.0;
+        }
+        if step > 1.0 {
+            return read_filtered(data, position, step.min(MAX_FILTERED_STEP));
+        }
+        let index = position as usize;
+        let t = (position - index as f64) as f32;
+        let at = |i: isize| -> f32 { data.get((index as isize + i).max(0) as usize).copied().unwrap_or(0.0) };
+        let (p0, p1, p2, p3) = (at(-1), at(0), at(1), at(2));
+        p1 + 0.5 * t * (p2 - p0 + t * (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3 + t * (3.0 * (p1 - p2) + p3 - p0)))
+    }
+
+    /// Reads all channels mixed down to mono at a fractional frame position, as `read` does.
+    pub fn read_mono(&self, position: f64, step: f64) -> f32 {
+        let count = self.channels.len().max(1);
+        (0..count).map(|channel| self.read(channel, position, step)).sum::<f32>() / count as f32
+    }
+}
+
+/// Reads a channel through a Hann-windowed sinc low-pass with its cutoff at the output's
+/// Nyquist frequency, for reads that step more than one frame per output sample.
+fn read_filtered(data: &[f32], position: f64, step: f64) -> f32 {
+    let half_width = SINC_ZERO_CROSSINGS * step; // Kernel reach in frames; the cutoff falls as the step grows
+    let first = (position - half_width).ceil().max(0.0) as usize;
+    let last = ((position + half_width).floor() as usize).min(data.len() - 1);
+    let (mut sum, mut weights) = (0.0, 0.0);
+    for (index, &sample) in data.iter().enumerate().take(last + 1).skip(first) {
+        let offset = index as f64 - position;
+        let x = PI * offset / step;
+        let sinc = if x.abs() < 1e-9 { 1.0 } else { x.sin() / x };
+        let weight = sinc * (0.5 + 0.5 * (PI * offset / half_width).cos());
+        sum += sample as f64 * weight;
+        weights += weight;
+    }
+    (sum / weights) as f32 // Normalizing keeps unity gain in the passband, also at the buffer edges
+}
+
+/// Decoded audio files shared between clips, keyed by file name, so each file is read only once.
+#[derive(Default)]
+pub struct SampleCache {
+    buffers: HashMap<String, Arc<AudioBuffer>>,
+}
+
+impl SampleCache {
+    /// Creates an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the decoded file, reading it from disk the first time it is requested.
+    pub fn load(&mut self, filename: &str) -> Result<Arc<AudioBuffer>, hound::Error> {
+        if let Some(buffer) = self.buffers.get(filename) {
+            return Ok(Arc::clone(buffer));
+        }
+        let buffer = Arc::new(AudioBuffer::from_wav(filename)?);
+        self.buffers.insert(filename.to_string(), Arc::clone(&buffer));
+        Ok(buffer)
+    }
+
+    /// Drops cached files that no clip refers to any more.
+    pub fn evict_unused(&mut self) {
+        self.buffers.retain(|_, buffer| Arc::strong_count(buffer) > 1);
+    }
+}
+
+/// Region of an audio file played by a clip.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SampleRegion {
+    pub filename: String, // WAV file to play
+    pub trim_start: f32,  // Start offset into the file, in seconds
+    pub trim_end: f32,    // Seconds cut from the end of the file
+    pub gain: f32,        // Linear playback gain
+    #[serde(skip)]
+    pub buffer: Option<Arc<AudioBuffer>>, // Decoded audio, filled in from the `SampleCache`
+}
+
+impl PartialEq for SampleRegion {
+    /// Regions are equal when their settings match; the decoded buffer is not compared.
+    fn eq(&self, other: &Self) -> bool {
+        self.filename == other.filename
+            && self.trim_start == other.trim_start
+            && self.trim_end == other.trim_end
+            && self.gain == other.gain
+    }
+}
+
+impl SampleRegion {
+    /// Creates a region covering the whole file at unity gain.
+    pub fn new(filename: &str, buffer: Arc<AudioBuffer>) -> Self {
+        SampleRegion {
+            filename: filename.to_string(),
+            trim_start: 0.0,
+            trim_end: 0.0,
+            gain: 1.0,
+            buffer: Some(buffer),
+        }
+    }
+
+    /// Returns the playable length after trimming, in seconds.
+    pub fn duration(&self) -> f32 {
+        self.buffer.as_ref().map_or(0.0, |buffer| (buffer.duration() - self.trim_start - self.trim_end).max(0.0))
+    }
+
+    /// Reads the region, mixed down to mono, at a time relative to the start of the region.
+    ///
+    /// # Parameters
+    /// - `time`: Seconds since the region started playing.
+    /// - `increment`: How far `time` advances per output sample, in seconds. Reading faster
+    ///   than the file's own rate filters out what would alias.
+    ///
+    /// # Returns
+    /// - The sample with gain applied, or silence outside the trimmed region or before loading.
+    pub fn sample_at(&self, time: f64, increment: f64) -> f32 {
+        self.position(time, increment).map_or(0.0, |(buffer, position, step)| buffer.read_mono(position, step) * self.gain)
+    }
+
+    /// Reads the region in stereo at a time relative to the start of the region. Mono files
+    /// play on both channels.
+    ///
+    /// # Parameters
+    /// - `time`: Seconds since the region started playing.
+    /// - `increment`: How far `time` advances per output sample, in seconds.
+    ///
+    /// # Returns
+    /// - The `(left, right)` samples with gain applied, or silence outside the trimmed region.
+    pub fn frame_at(&self, time: f64, increment: f64) -> (f32, f32) {
+        self.position(time, increment).map_or((0.0, 0.0), |(buffer, position, step)| {
+            (buffer.read(0, position, step) * self.gain, buffer.read(1, position, step) * self.gain)
+        })
+    }
+
+    /// Returns the buffer, frame position and frame step for a time, if the region is loaded and playing.
+    fn position(&self, time: f64, increment: f64) -> Option<(&AudioBuffer, f64, f64)> {
+        let buffer = self.buffer.as_ref()?;
+        if time < 0.0 || time >= self.duration() as f64 {
+            return None;
+        }
+        // Positions are computed in the file's own rate, which resamples to the engine rate
+        let rate = buffer.sample_rate as f64;
+        Some((buffer, (self.trim_start as f64 + time) * rate, increment * rate))
+    }
+}
@@ -1,9 +1,18 @@
-use std::f32::consts::PI;
 use hound;
 use serde::{Serialize, Deserialize};
-use crate::mixer::Mixer; // Import Mixer for track mixing
-use rayon::prelude::*;   // Import Rayon for parallel processing
-use crate::effects::Effects; // Use a relative path to the effects module
+use crate::mixer::{AuxSend, Mixer, PanMode, TrackSource}; // Import Mixer for track mixing
+use crate::effects::{EffectChain, EffectContext, EffectKind};
+use crate::dynamics::Limiter; // Keeps the master output below full scale
+use crate::oscillator::Oscillator; // Phase-accumulating band-limited oscillator
+use crate::envelope::{Envelope, EnvelopeGenerator}; // ADSR envelopes for the live voice and clips
+use crate::voice::VoiceAllocator; // Polyphonic note voices
+use crate::filter::{Filter, FilterSettings}; // Resonant filter after the oscillators
+use crate::modulation::{ModMatrix, ModTarget}; // LFO modulation of synth parameters
+use crate::fm::{FmPatch, FmVoice, OPERATOR_COUNT}; // FM synthesis engine
+use crate::wavetable::{Wavetable, DEFAULT_FRAME_SIZE}; // User-loadable wavetables
+use crate::sample::{SampleCache, SampleRegion}; // Audio file playback
+use crate::convolution::Convolution; // Impulse response reverb
+use crate::meter::Meter; // Output and track levels
 
 #[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
 pub enum Waveform {
@@ -11,6 +20,30 @@ pub enum Waveform {
     Square,    // Square wave
     Triangle,  // Triangle wave
     Sawtooth,  // Sawtooth wave
+    Wavetable { table: usize, position: f32 }, // Loaded wavetable by index, morphing across frames by position (0.0 to 1.0)
+    WhiteNoise, // Flat-spectrum noise
+    PinkNoise,  // Noise falling 3 dB per octave
+    BrownNoise, // Noise falling 6 dB per octave
+}
+
+/// Sound generator used by clips and by the polyphonic voices.
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum SoundSource {
+    #[default]
+    Oscillator, // The band-limited `Waveform` oscillator
+    Fm(FmPatch), // The four-operator FM engine
+    Sample(SampleRegion), // A region of an audio file
+}
+
+impl SoundSource {
+    /// Returns how long the source keeps sounding after its gate closes.
+    pub fn release(&self) -> f32 {
+        match self {
+            SoundSource::Oscillator => 0.0,
+            SoundSource::Fm(patch) => patch.release(),
+            SoundSource::Sample(_) => 0.0,
+        }
+    }
 }
 
 pub struct Synthesizer {
@@ -18,52 +51,216 @@ pub struct Synthesizer {
     pub frequency_right: f32, // Frequency for the right channel
     pub amplitude: f32,       // Amplitude of the waveform
     pub waveform: Waveform,   // Current waveform type
-    pub tracks: Vec<Track>,   // List of audio tracks
-    pub effects: Effects, // Audio effects (e.g., delay, reverb)
+    pub effects: EffectChain, // Ordered audio effects (e.g., delay, reverb)
+    pub master: EffectChain,  // Effects on the final output, after `effects`
     pub timeline: Timeline,   // Timeline for managing audio clips
     pub mixer: Mixer,         // Mixer for combining tracks
+    pub sample_rate: f32,     // Engine sample rate in Hz
+    pub envelope: Envelope,   // ADSR settings for the live voice
+    voice_envelope: EnvelopeGenerator, // Envelope state for the live voice
+    pub voices: VoiceAllocator, // Polyphonic voices started with `note_on`
+    pub voice_source: SoundSource, // Sound generator for the polyphonic voices
+    pub filter: FilterSettings, // Filter applied after the oscillators
+    left_filter: Filter,        // Filter state for the left channel
+    right_filter: Filter,       // Filter state for the right channel
+    left_oscillator: Oscillator,  // Phase state for the left channel
+    right_oscillator: Oscillator, // Phase state for the right channel
+    pub modulation: ModMatrix,    // LFOs and their routings to parameters
+    pub tempo: f32,               // Project tempo in beats per minute
+    pub wavetables: Vec<Wavetable>, // Tables available to `Waveform::Wavetable`
+    pub samples: SampleCache,       // Decoded audio files shared by sample clips
+    pub master_meter: Meter,        // Level of the final output, after the master chain
+}
+
+/// Values of every modulatable parameter, saved before modulation and restored after rendering.
+#[derive(Clone, Copy)]
+struct ModulatedParameters {
+    frequency_left: f32,
+    frequency_right: f32,
+    amplitude: f32,
+    waveform: Waveform,
+    filter: FilterSettings,
 }
 
 impl Synthesizer {
     #[allow(dead_code)] // Suppress warning for unused function
     pub fn new(frequency: f32, amplitude: f32, waveform: Waveform) -> Self {
-        Self {
+        let mut voice_envelope = EnvelopeGenerator::new();
+        voice_envelope.note_on(); // The live voice starts sounding, as before envelopes existed
+        let mut synth = Self {
             frequency_left: frequency,
             frequency_right: frequency,
             amplitude,
             waveform,
-            tracks: Vec::new(), // Initialize with no tracks
-            effects: Effects { delay: 0.0, reverb: 0.0 }, // Default effects
+            effects: EffectChain::default(), // Default effects
+            master: default_master(),
             timeline: Timeline { clips: Vec::new() }, // Empty timeline
             mixer: Mixer::new(), // Initialize mixer
-        }
+            sample_rate: 44100.0, // Default engine sample rate
+            envelope: Envelope::default(),
+            voice_envelope,
+            voices: VoiceAllocator::new(8), // Eight-voice polyphony by default
+            voice_source: SoundSource::Oscillator,
+            filter: FilterSettings::default(),
+            left_filter: Filter::new(),
+            right_filter: Filter::new(),
+            left_oscillator: Oscillator::with_seed(1),
+            right_oscillator: Oscillator::with_seed(2), // A different seed keeps stereo noise uncorrelated
+            modulation: ModMatrix::default(),
+            tempo: default_tempo(),
+            wavetables: Vec::new(), // No tables until one is loaded
+            samples: SampleCache::new(),
+            master_meter: Meter::new(),
+        };
+        synth.prepare_effects();
+        synth
     }
 
-    /// Generates a single audio sample for the given time and channel (left or right).
-    /// 
-    /// # Parameters
-    /// - `time`: The time at which the sample is generated.
-    /// - `is_left`: Whether the sample is for the left channel.
-    /// 
+    /// Sets the engine sample rate and prepares every effect chain for it. Preparing
+    /// allocates, so call this before playback starts rather than from the audio thread.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.prepare_effects();
+    }
+
+    /// Prepares the effect, master, track and bus chains for the current sample rate.
+    fn prepare_effects(&mut self) {
+        self.effects.prepare(self.sample_rate);
+        self.master.prepare(self.sample_rate);
+        self.mixer.prepare(self.sample_rate);
+    }
+
+    /// Generates the next stereo sample and advances both channel oscillators.
+    ///
     /// # Returns
-    /// - The generated audio sample.
-    pub fn generate_sample(&self, time: f32, is_left: bool) -> f32 {
-        let frequency = if is_left {
-            self.frequency_left // Use left channel frequency
-        } else {
-            self.frequency_right // Use right channel frequency
+    /// - The generated `(left, right)` audio samples.
+    pub fn generate_sample(&mut self) -> (f32, f32) {
+        let (mut left, mut right) = ([0.0], [0.0]);
+        self.render_block(&mut left, &mut right);
+        (left[0], right[0])
+    }
+
+    /// Generates a block of stereo samples, running the effect chain once over the whole block.
+    ///
+    /// # Parameters
+    /// - `left`, `right`: The buffers to fill, of equal length.
+    pub fn render_block(&mut self, left: &mut [f32], right: &mut [f32]) {
+        for (left, right) in left.iter_mut().zip(right.iter_mut()) {
+            (*left, *right) = self.with_modulation(Self::render_sample);
+        }
+        self.process_effects(left, right);
+    }
+
+    /// Runs a block through the effect chain and then the master chain. Effect parameters are
+    /// modulated once per block, using the LFO values reached at the end of it.
+    fn process_effects(&mut self, left: &mut [f32], right: &mut [f32]) {
+        let context = EffectContext { sample_rate: self.sample_rate, tempo: self.tempo };
+        let targets = ModTarget::ALL.map(|target| target.effect_name());
+        let saved = targets.map(|name| name.and_then(|name| self.effects.find_mix(name).map(|mix| *mix)));
+        for route in &self.modulation.routes {
+            let Some(name) = route.target.effect_name() else {
+                continue;
+            };
+            if let (Some(lfo), Some(mix)) = (self.modulation.lfos.get(route.source), self.effects.find_mix(name)) {
+                *mix = route.target.apply(*mix, lfo.value() * route.depth);
+            }
+        }
+
+        self.effects.process(left, right, &context);
+        self.master.process(left, right, &context);
+
+        for (name, value) in targets.into_iter().zip(saved) {
+            if let (Some(mix), Some(value)) = (name.and_then(|name| self.effects.find_mix(name)), value) {
+                *mix = value; // Restore the unmodulated setting
+            }
+        }
+    }
+
+    fn render_sample(&mut self) -> (f32, f32) {
+        let gain = self.voice_envelope.next_level(&self.envelope, self.sample_rate) * self.amplitude; // Shape the voice with its envelope
+        let notes = self.voices.next_sample(&self.voice_source, self.waveform, &self.envelope, self.sample_rate, &self.wavetables) * self.amplitude; // Played notes, centered
+        let left = self.left_oscillator.next_sample(self.waveform, self.frequency_left, self.sample_rate, &self.wavetables) * gain + notes;
+        let right = self.right_oscillator.next_sample(self.waveform, self.frequency_right, self.sample_rate, &self.wavetables) * gain + notes;
+        let left = self.left_filter.process(left, &self.filter, self.sample_rate);
+        let right = self.right_filter.process(right, &self.filter, self.sample_rate);
+
+        (left, right) // Effects are applied per block by `process_effects`
+    }
+
+    /// Returns oscillators, filters, LFOs, clips and the live voice envelope to their initial state.
+    fn reset_render_state(&mut self) {
+        self.left_oscillator.reset();
+        self.right_oscillator.reset();
+        self.left_filter.reset();
+        self.right_filter.reset();
+        self.modulation.reset();
+        self.effects.reset();
+        self.master.reset();
+        self.mixer.reset();
+        self.master_meter.reset(); // So the integrated loudness covers one export
+        for (index, clip) in self.timeline.clips.iter_mut().enumerate() {
+            clip.oscillator.reseed(index as u32 + 1); // As if each clip were about to begin
+            clip.filter_state.reset();
+            clip.fm_voice.reset();
+        }
+        if self.voice_envelope.is_gate_open() {
+            self.voice_envelope.reset();
+            self.voice_envelope.note_on(); // Replay the attack from silence
+        }
+    }
+
+    /// Advances the LFOs, applies the modulation matrix, runs `render`, then restores the
+    /// unmodulated parameter values so UI edits and modulation never fight each other.
+    fn with_modulation<R>(&mut self, render: impl FnOnce(&mut Self) -> R) -> R {
+        self.modulation.advance(self.sample_rate, self.tempo);
+        if self.modulation.routes.is_empty() {
+            return render(self);
+        }
+
+        let saved = ModulatedParameters {
+            frequency_left: self.frequency_left,
+            frequency_right: self.frequency_right,
+            amplitude: self.amplitude,
+            waveform: self.waveform,
+            filter: self.filter,
         };
-        let phase = 2.0 * PI * frequency * time; // Calculate phase
-        let raw_sample = match self.waveform {
-            Waveform::Sine => phase.sin(), // Generate sine wave
-            Waveform::Square => {
-                if phase.sin() >= 0.0 { 1.0 } else { -1.0 } // Generate square wave
+        for index in 0..self.modulation.routes.len() {
+            let route = self.modulation.routes[index];
+            if let Some(lfo) = self.modulation.lfos.get(route.source) {
+                let amount = lfo.value() * route.depth;
+                self.modulate(route.target, amount);
             }
-            Waveform::Triangle => 2.0 * (2.0 * frequency * time - (2.0 * frequency * time).floor() - 0.5).abs() - 1.0, // Generate triangle wave
-            Waveform::Sawtooth => 2.0 * (frequency * time - (frequency * time).floor()) - 1.0, // Generate sawtooth wave
-        } * self.amplitude; // Scale by amplitude
+        }
+
+        let output = render(self);
+
+        self.frequency_left = saved.frequency_left;
+        self.frequency_right = saved.frequency_right;
+        self.amplitude = saved.amplitude;
+        self.waveform = saved.waveform;
+        self.filter = saved.filter;
+        output
+    }
 
-        self.effects.apply(raw_sample) // Apply effects to the generated sample
+    /// Applies a modulation amount to one parameter.
+    fn modulate(&mut self, target: ModTarget, amount: f32) {
+        match target {
+            ModTarget::Frequency => {
+                self.frequency_left = target.apply(self.frequency_left, amount);
+                self.frequency_right = target.apply(self.frequency_right, amount);
+            }
+            ModTarget::FrequencyLeft => self.frequency_left = target.apply(self.frequency_left, amount),
+            ModTarget::FrequencyRight => self.frequency_right = target.apply(self.frequency_right, amount),
+            ModTarget::Amplitude => self.amplitude = target.apply(self.amplitude, amount),
+            ModTarget::WavetablePosition => {
+                if let Waveform::Wavetable { position, .. } = &mut self.waveform {
+                    *position = target.apply(*position, amount);
+                }
+            }
+            ModTarget::FilterCutoff => self.filter.cutoff = target.apply(self.filter.cutoff, amount),
+            ModTarget::FilterResonance => self.filter.resonance = target.apply(self.filter.resonance, amount),
+            ModTarget::Delay | ModTarget::Reverb | ModTarget::Chorus | ModTarget::Flanger | ModTarget::Phaser | ModTarget::Tremolo | ModTarget::AutoPan => {} // Effect mixes are modulated per block by `process_effects`
+        }
     }
 
     pub fn set_amplitude(&mut self, amplitude: f32) {
@@ -74,31 +271,149 @@ impl Synthesizer {
         self.waveform = waveform;
     }
 
+    /// Opens the live voice's gate, starting the envelope attack.
+    pub fn gate_on(&mut self) {
+        self.voice_envelope.note_on();
+    }
+
+    /// Closes the live voice's gate, starting the envelope release.
+    pub fn gate_off(&mut self) {
+        self.voice_envelope.note_off();
+    }
+
+    /// Returns `true` while the live voice's gate is open.
+    pub fn is_gate_open(&self) -> bool {
+        self.voice_envelope.is_gate_open()
+    }
+
+    /// Starts a polyphonic note using the current waveform, amplitude and envelope.
+    ///
+    /// # Parameters
+    /// - `note`: The MIDI note number (60 = middle C).
+    /// - `velocity`: The note velocity, from 0.0 to 1.0.
+    pub fn note_on(&mut self, note: u8, velocity: f32) {
+        self.voices.note_on(note, velocity);
+    }
+
+    /// Releases a polyphonic note.
+    pub fn note_off(&mut self, note: u8) {
+        self.voices.note_off(note);
+    }
+
+    /// Loads a wavetable from a WAV file and makes it available to `Waveform::Wavetable`.
+    ///
+    /// # Returns
+    /// - The index of the new table.
+    pub fn load_wavetable(&mut self, filename: &str) -> Result<usize, hound::Error> {
+        self.wavetables.push(Wavetable::from_wav(filename, DEFAULT_FRAME_SIZE)?);
+        Ok(self.wavetables.len() - 1)
+    }
+
+    /// Adds a clip that plays a WAV file, lasting as long as the file.
+    ///
+    /// # Parameters
+    /// - `id`: The clip ID.
+    /// - `start_time`: Where the clip starts on the timeline, in seconds.
+    /// - `filename`: The WAV file to play. Files already in use are not read again.
+    pub fn add_sample_clip(&mut self, id: &str, start_time: f32, filename: &str) -> Result<(), hound::Error> {
+        let region = SampleRegion::new(filename, self.samples.load(filename)?);
+        self.timeline.clips.push(Clip {
+            id: id.to_string(),
+            start_time,
+            duration: region.duration(),
+            frequency: 0.0, // Unused by sample clips
+            amplitude: 1.0,
+            waveform: Waveform::Sine,
+            source: SoundSource::Sample(region),
+            envelope: Envelope::default(), // Short fades keep the clip edges click-free
+            filter: FilterSettings::default(),
+            oscillator: Oscillator::new(),
+            filter_state: Filter::new(),
+            fm_voice: FmVoice::new(),
+            track: String::new(),
+        });
+        Ok(())
+    }
+
+    /// Adds a convolution reverb to the end of the effect chain.
+    ///
+    /// # Parameters
+    /// - `filename`: The impulse response WAV file.
+    pub fn add_convolution(&mut self, filename: &str) -> Result<(), hound::Error> {
+        let convolution = Convolution::new(filename, self.samples.load(filename)?);
+        self.effects.add(EffectKind::Convolution(convolution)); // Adding prepares it, off the audio thread
+        Ok(())
+    }
+
+    /// Fills in the decoded audio of every sample clip, file track and impulse response from the cache,
+    /// reading files as needed.
+    fn load_audio_files(&mut self) {
+        for clip in &mut self.timeline.clips {
+            if let SoundSource::Sample(region) = &mut clip.source {
+                match self.samples.load(&region.filename) {
+                    Ok(buffer) => region.buffer = Some(buffer),
+                    Err(e) => eprintln!("Failed to load sample {}: {}", region.filename, e), // The clip stays silent
+                }
+            }
+        }
+        for track in &mut self.mixer.tracks {
+            if let TrackSource::File(region) = &mut track.source {
+                match self.samples.load(&region.filename) {
+                    Ok(buffer) => region.buffer = Some(buffer),
+                    Err(e) => eprintln!("Failed to load track file {}: {}", region.filename, e), // The track stays silent
+                }
+            }
+        }
+        let chains = [&mut self.effects, &mut self.master]
+            .into_iter()
+            .chain(self.mixer.tracks.iter_mut().map(|track| &mut track.inserts))
+            .chain(self.mixer.buses.iter_mut().map(|bus| &mut bus.inserts));
+        for slot in chains.flat_map(|chain| chain.slots.iter_mut()) {
+            if let EffectKind::Convolution(convolution) = &mut slot.effect {
+                match self.samples.load(&convolution.filename) {
+                    Ok(buffer) => convolution.set_impulse(buffer),
+                    Err(e) => eprintln!("Failed to load impulse response {}: {}", convolution.filename, e), // The effect passes audio through
+                }
+            }
+        }
+        self.samples.evict_unused(); // Forget files used only by the previous project
+    }
+
     pub fn set_binaural_frequencies(&mut self, left: f32, right: f32) {
         self.frequency_left = left;
         self.frequency_right = right;
     }
 
     pub fn add_track(&mut self, id: &str) {
-        self.tracks.push(Track {
-            id: id.to_string(),
-            volume: 0.5,
-            muted: false,
-        });
+        let mut track = Track::new(id);
+        track.inserts.prepare(self.sample_rate);
+        self.mixer.tracks.push(track);
+    }
+
+    /// Adds a mixer track that plays a WAV file from the start of the timeline.
+    ///
+    /// # Parameters
+    /// - `id`: The track ID.
+    /// - `filename`: The WAV file to play. Files already in use are not read again.
+    pub fn add_file_track(&mut self, id: &str, filename: &str) -> Result<(), hound::Error> {
+        let mut track = Track::new(id);
+        track.source = TrackSource::File(SampleRegion::new(filename, self.samples.load(filename)?));
+        track.inserts.prepare(self.sample_rate);
+        self.mixer.tracks.push(track);
+        Ok(())
     }
 
     pub fn set_effect(&mut self, effect: &str, value: f32) {
-        match effect {
-            "delay" => self.effects.delay = value,
-            "reverb" => self.effects.reverb = value, // Add handling for reverb
-            _ => eprintln!("Unknown effect: {}", effect), // Log unknown effects
+        match self.effects.find_mix(effect) {
+            Some(mix) => *mix = value, // Wet/dry mix of the first matching effect in the chain
+            None => eprintln!("Unknown effect: {}", effect), // Log effects missing from the chain
         }
     }
 
-    pub fn export_to_wav(&self, duration: f32, filename: &str) -> Result<(), hound::Error> {
+    pub fn export_to_wav(&mut self, duration: f32, filename: &str) -> Result<(), hound::Error> {
         let spec = hound::WavSpec {
             channels: 2,
-            sample_rate: 44100,
+            sample_rate: self.sample_rate as u32, // Render at the engine sample rate
             bits_per_sample: 16,
             sample_format: hound::SampleFormat::Int,
         };
@@ -106,77 +421,223 @@ impl Synthesizer {
         let sample_rate = spec.sample_rate as f32;
         let max_amplitude = i16::MAX as f32;
 
-        // Generate samples in parallel
-        let samples: Vec<(i16, i16)> = (0..(duration * sample_rate) as usize)
-            .into_par_iter() // Parallel iterator
-            .map(|i| {
-                let time = i as f32 / sample_rate;
-                let left_sample = (self.generate_sample(time, true) * max_amplitude) as i16;
-                let right_sample = (self.generate_sample(time, false) * max_amplitude) as i16;
-                (left_sample, right_sample)
-            })
-            .collect();
+        self.reset_render_state(); // Start from a known state so exports are reproducible
 
-        // Write samples to the WAV file
-        for (left_sample, right_sample) in samples {
-            writer.write_sample(left_sample)?;
-            writer.write_sample(right_sample)?;
+        // Oscillators and effects carry state between samples, so render blocks sequentially
+        let total = (duration * sample_rate) as usize;
+        let (mut left, mut right) = ([0.0; EXPORT_BLOCK_SIZE], [0.0; EXPORT_BLOCK_SIZE]);
+        for start in (0..total).step_by(EXPORT_BLOCK_SIZE) {
+            let length = EXPORT_BLOCK_SIZE.min(total - start);
+            let time = start as f64 / sample_rate as f64; // Timeline position of the block
+            self.render_mixed_block(time, &mut left[..length], &mut right[..length]); // The same mix as live playback
+            for (left, right) in left[..length].iter().zip(&right[..length]) {
+                writer.write_sample((left * max_amplitude) as i16)?;
+                writer.write_sample((right * max_amplitude) as i16)?;
+            }
         }
 
         writer.finalize()?;
         Ok(())
     }
 
-    pub fn generate_timeline_sample(&self, time: f32) -> f32 {
+    pub fn generate_timeline_sample(&mut self, time: f32) -> f32 {
         let mut sample = 0.0;
-        for clip in &self.timeline.clips {
-            if time >= clip.start_time && time < clip.start_time + clip.duration {
-                let phase = 2.0 * PI * clip.frequency * (time - clip.start_time);
-                let raw_sample = match clip.waveform {
-                    Waveform::Sine => phase.sin(),
-                    Waveform::Square => if phase.sin() >= 0.0 { 1.0 } else { -1.0 },
-                    Waveform::Triangle => 2.0 * (2.0 * clip.frequency * (time - clip.start_time) - (2.0 * clip.frequency * (time - clip.start_time)).floor() - 0.5).abs() - 1.0,
-                    Waveform::Sawtooth => 2.0 * (clip.frequency * (time - clip.start_time) - (clip.frequency * (time - clip.start_time)).floor()) - 1.0,
-                } * clip.amplitude;
-
-                sample += raw_sample; // Sum raw samples without applying effects here
-            }
+        for (index, clip) in self.timeline.clips.iter_mut().enumerate() {
+            sample += clip.render(index, time as f64, self.sample_rate, &self.wavetables); // Sum raw samples without applying effects here
         }
-        self.effects.apply(sample) // Apply effects to the combined sample
+        sample // Effects are applied by `apply_effects`
     }
 
     pub fn save_project(&self, filename: &str) -> Result<(), std::io::Error> {
-        let json = serde_json::to_string(&self.timeline)?;
+        let project = ProjectRef {
+            timeline: &self.timeline,
+            modulation: &self.modulation,
+            effects: &self.effects,
+            master: &self.master,
+            mixer: &self.mixer,
+            tempo: self.tempo,
+            wavetables: self.wavetables.iter().map(|table| table.name.as_str()).collect(),
+        };
+        let json = serde_json::to_string(&project)?;
         std::fs::write(filename, json)?;
         Ok(())
     }
 
     pub fn load_project(&mut self, filename: &str) -> Result<(), std::io::Error> {
         let json = std::fs::read_to_string(filename)?;
-        self.timeline = serde_json::from_str(&json)?;
+        let project: Project = serde_json::from_str(&json)?;
+        project.mixer.validate_routing().map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?; // Refuse loops rather than play them
+        self.timeline = project.timeline;
+        self.modulation = project.modulation;
+        self.effects = project.effects;
+        self.master = project.master;
+        self.mixer = project.mixer;
+        self.tempo = project.tempo;
+        self.wavetables = project
+            .wavetables
+            .iter()
+            .map(|filename| {
+                Wavetable::from_wav(filename, DEFAULT_FRAME_SIZE).unwrap_or_else(|e| {
+                    eprintln!("Failed to load wavetable {}: {}", filename, e); // Keep indices stable with a sine stand-in
+                    let sine: Vec<f32> = (0..DEFAULT_FRAME_SIZE)
+                        .map(|i| (2.0 * std::f32::consts::PI * i as f32 / DEFAULT_FRAME_SIZE as f32).sin())
+                        .collect();
+                    Wavetable::from_frames(filename, &[sine])
+                })
+            })
+            .collect();
+        self.load_audio_files();
+        self.prepare_effects(); // Loaded chains start without buffers, and impulse responses set their latency
         Ok(())
     }
 
-    pub fn apply_effects(&mut self, time: f32) -> f32 {
-        self.effects.apply(self.generate_timeline_sample(time)) // Simplify effect application
+    /// Runs the timeline at `time` through the effect and master chains.
+    ///
+    /// # Returns
+    /// - The processed `(left, right)` samples.
+    pub fn apply_effects(&mut self, time: f32) -> (f32, f32) {
+        let sample = self.generate_timeline_sample(time);
+        let (mut left, mut right) = ([sample], [sample]); // Clips are mono and start in the center
+        self.process_effects(&mut left, &mut right);
+        (left[0], right[0])
     }
 
     pub fn update_effect(&mut self, effect: &str, value: f32) {
         self.set_effect(effect, value);
     }
 
-    pub fn generate_mixed_sample(&self, time: f32) -> f32 {
-        let base_sample = self.generate_timeline_sample(time); // Generate timeline sample
-        let mixed_sample = self.mixer.apply_mixing(time); // Apply mixing
-        base_sample + mixed_sample // Combine base and mixed samples
+    /// Generates the next stereo frame of playback: the live voice and played notes, the
+    /// timeline and the mixer tracks, through the effect and master chains.
+    ///
+    /// # Parameters
+    /// - `time`: The timeline position in seconds.
+    ///
+    /// # Returns
+    /// - The `(left, right)` output samples.
+    pub fn generate_mixed_sample(&mut self, time: f32) -> (f32, f32) {
+        let (mut left, mut right) = ([0.0], [0.0]);
+        self.render_mixed_block(time as f64, &mut left, &mut right);
+        (left[0], right[0])
+    }
+
+    /// Generates a block of playback, running the effect chains once over the whole block.
+    ///
+    /// # Parameters
+    /// - `start_time`: The timeline position of the first frame, in seconds.
+    /// - `left`, `right`: The buffers to fill, of equal length.
+    pub fn render_mixed_block(&mut self, start_time: f64, left: &mut [f32], right: &mut [f32]) {
+        let sample_rate = self.sample_rate as f64;
+        self.mixer.begin_block(left.len());
+        let direct_audible = !self.mixer.any_solo(); // Only mixer tracks and buses can be soloed
+        for (index, (left, right)) in left.iter_mut().zip(right.iter_mut()).enumerate() {
+            let time = start_time + index as f64 / sample_rate; // Per-frame offsets keep f64 precision
+            (*left, *right) = self.with_modulation(|synth| synth.render_mixed_frame(index, time, direct_audible));
+        }
+        let context = EffectContext { sample_rate: self.sample_rate, tempo: self.tempo };
+        self.mixer.apply_mixing(left, right, &context);
+        self.process_effects(left, right);
+        self.master_meter.process(left, right, self.sample_rate);
     }
+
+    /// Renders the live voice and the clips, and mixes them through the tracks that play them.
+    /// The voice and any clips without a track go straight to the output, unless
+    /// `direct_audible` is `false` because something in the mixer is soloed.
+    fn render_mixed_frame(&mut self, frame: usize, time: f64, direct_audible: bool) -> (f32, f32) {
+        let voice = self.render_sample(); // Live voice with its binaural frequencies
+        self.mixer.clear_clip_inputs();
+        let mut direct = 0.0; // Clips not assigned to a clip track
+        for (index, clip) in self.timeline.clips.iter_mut().enumerate() {
+            let sample = clip.render(index, time, self.sample_rate, &self.wavetables);
+            if !self.mixer.add_clip_input(&clip.track, sample) {
+                direct += sample;
+            }
+        }
+        self.mixer.add_sources(frame, time, 1.0 / self.sample_rate as f64, voice); // The tracks are mixed once the block is complete
+        if !direct_audible {
+            return (0.0, 0.0); // Rendered all the same, so envelopes and clips stay in step
+        }
+        let (voice_left, voice_right) = if self.mixer.uses_synth() { (0.0, 0.0) } else { voice };
+        (voice_left + direct, voice_right + direct)
+    }
+}
+
+/// Number of samples rendered at a time by `export_to_wav`.
+const EXPORT_BLOCK_SIZE: usize = 256;
+
+fn default_tempo() -> f32 {
+    120.0
+}
+
+/// A limiter just below full scale, so loud mixes are not clipped by the output conversion.
+fn default_master() -> EffectChain {
+    let mut master = EffectChain::new();
+    master.add(EffectKind::Limiter(Limiter::default()));
+    master
 }
 
-#[derive(Clone)]
+/// Borrowed view of the project state written by `save_project`.
+#[derive(Serialize)]
+struct ProjectRef<'a> {
+    #[serde(flatten)]
+    timeline: &'a Timeline, // Flattened so older timeline-only project files still load
+    modulation: &'a ModMatrix,
+    effects: &'a EffectChain,
+    master: &'a EffectChain,
+    mixer: &'a Mixer,
+    tempo: f32,
+    wavetables: Vec<&'a str>, // Wavetable files, reloaded by `load_project`
+}
+
+/// Project state read by `load_project`. Fields added after the first release have defaults.
+#[derive(Deserialize)]
+struct Project {
+    #[serde(flatten)]
+    timeline: Timeline,
+    #[serde(default)]
+    modulation: ModMatrix,
+    #[serde(default)]
+    effects: EffectChain,
+    #[serde(default = "default_master")]
+    master: EffectChain,
+    #[serde(default)]
+    mixer: Mixer,
+    #[serde(default = "default_tempo")]
+    tempo: f32,
+    #[serde(default)]
+    wavetables: Vec<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Track {
     pub id: String,
     pub volume: f32,
     pub muted: bool,
+    #[serde(default)]
+    pub pan: f32, // Position from -1.0 (hard left) to 1.0 (hard right)
+    #[serde(default)]
+    pub pan_mode: PanMode, // Pan a mono fold-down or balance a stereo source
+    #[serde(default)]
+    pub source: TrackSource, // What the track plays
+    #[serde(default)]
+    pub solo: bool, // Silence the tracks that are not soloed
+    #[serde(default)]
+    pub solo_safe: bool, // Keep playing while other tracks are soloed
+    #[serde(default)]
+    pub group: String, // Mute/solo group; tracks sharing a group mute and solo together
+    #[serde(default)]
+    pub output: String, // ID of the bus the track feeds; empty for the master bus
+    #[serde(default)]
+    pub sends: Vec<AuxSend>, // Aux sends to return buses
+    #[serde(default = "EffectChain::new")]
+    pub inserts: EffectChain, // Effects on this track only, before its pan and fader
+    #[serde(skip)]
+    pub(crate) clip_input: f32, // Sum of the track's clips for the current frame
+    #[serde(skip)]
+    pub(crate) block: [Vec<f32>; 2], // Left and right source of the current block, run through `inserts`
+    #[serde(skip)]
+    pub meter: Meter, // Post-fader level of the track
+    #[serde(skip)]
+    pub(crate) destination: Option<usize>, // Index of the output bus, resolved by `Mixer::begin_block`
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -187,6 +648,20 @@ pub struct Clip {
     pub frequency: f32,
     pub amplitude: f32,
     pub waveform: Waveform,
+    #[serde(default)]
+    pub source: SoundSource, // Oscillator (using `waveform` and `frequency`), FM engine or audio file
+    #[serde(default)]
+    pub envelope: Envelope, // ADSR applied over the clip; the release extends past `duration`
+    #[serde(default)]
+    pub filter: FilterSettings, // Filter applied to the clip's oscillator
+    #[serde(default)]
+    pub track: String, // ID of the mixer track that plays the clip; clips without one play directly
+    #[serde(skip)]
+    pub(crate) oscillator: Oscillator, // Phase state while the clip is playing
+    #[serde(skip)]
+    pub(crate) filter_state: Filter, // Filter state while the clip is playing
+    #[serde(skip)]
+    pub(crate) fm_voice: FmVoice, // FM operator state while the clip is playing
 }
 
 #[derive(Serialize, Deserialize)]
@@ -198,4 +673,55 @@ impl Timeline {
     pub fn remove_clip(&mut self, clip_id: &str) {
         self.clips.retain(|clip| clip.id != clip_id);
     }
+}
+
+impl Clip {
+    /// Trims a sample clip's region and fits the clip's duration to what is left of the file.
+    /// Other clips are left unchanged.
+    ///
+    /// # Parameters
+    /// - `trim_start`: Seconds skipped at the start of the file.
+    /// - `trim_end`: Seconds cut from the end of the file.
+    pub fn trim(&mut self, trim_start: f32, trim_end: f32) {
+        if let SoundSource::Sample(region) = &mut self.source {
+            region.trim_start = trim_start.max(0.0);
+            region.trim_end = trim_end.max(0.0);
+            self.duration = region.duration();
+        }
+    }
+
+    /// Renders the clip at a timeline position, through its envelope and filter.
+    ///
+    /// # Parameters
+    /// - `index`: The clip's position in the timeline, used to seed its noise.
+    /// - `time`: The timeline position in seconds.
+    /// - `sample_rate`: The engine sample rate in Hz.
+    /// - `wavetables`: The tables available to `Waveform::Wavetable`.
+    ///
+    /// # Returns
+    /// - The clip's sample, or silence outside the clip and its release.
+    fn render(&mut self, index: usize, time: f64, sample_rate: f32, wavetables: &[Wavetable]) -> f32 {
+        let elapsed = time - self.start_time as f64; // In f64 so audio files stay sample-accurate deep into the timeline
+        let envelope = Envelope { release: self.envelope.release.max(self.source.release()), ..self.envelope };
+        if elapsed >= 0.0 && elapsed < (self.duration + envelope.release) as f64 { // Let the release ring past the clip end
+            let gain = envelope.level_at(elapsed as f32, self.duration) * self.amplitude;
+            let raw_sample = match &self.source {
+                SoundSource::Oscillator => self.oscillator.next_sample(self.waveform, self.frequency, sample_rate, wavetables),
+                SoundSource::Fm(patch) => {
+                    let mut levels = [0.0; OPERATOR_COUNT];
+                    for (level, operator) in levels.iter_mut().zip(&patch.operators) {
+                        *level = operator.envelope.level_at(elapsed as f32, self.duration);
+                    }
+                    self.fm_voice.render(patch, self.frequency, sample_rate, levels)
+                }
+                SoundSource::Sample(region) => region.sample_at(elapsed, 1.0 / sample_rate as f64),
+            } * gain;
+            self.filter_state.process(raw_sample, &self.filter, sample_rate)
+        } else {
+            self.oscillator.reseed(index as u32 + 1); // Restart the phase and noise the next time the clip begins
+            self.filter_state.reset();
+            self.fm_voice.reset();
+            0.0
+        }
+    }
 }
\ No newline at end of file
@@ -1,6 +1,17 @@
 use eframe::egui::{self, ProgressBar};
 use std::sync::{Arc, Mutex};
-use crate::synthesizer::{Synthesizer, Waveform};
+use crate::synthesizer::{SoundSource, Synthesizer, Waveform};
+use crate::fm::{FmAlgorithm, FmPatch};
+use crate::envelope::EnvelopeCurve;
+use crate::voice::VoiceStealing;
+use crate::filter::FilterMode;
+use crate::effects::{AutoPan, Chorus, Delay, DelayTime, EffectChain, EffectKind, Flanger, Phaser, Reverb, Tremolo, MAX_PHASER_STAGES};
+use crate::dynamics::{Compressor, Gate, Limiter, MAX_LOOKAHEAD_MS};
+use crate::eq::{BandType, Equalizer};
+use crate::distortion::{Bitcrusher, Oversampling, Saturation, SaturationMode, Waveshaper};
+use crate::modulation::{Lfo, LfoRate, LfoShape, ModRoute, ModTarget};
+use crate::mixer::{AuxSend, Bus, BusKind, Node, PanLaw, PanMode, SoloMode, TrackSource};
+use crate::meter::MeterValues;
 use std::thread;
 use crate::audio::play_audio;
 
@@ -16,11 +27,15 @@ pub fn run_ui(synth: Arc<Mutex<Synthesizer>>) -> Result<(), eframe::Error> {
         }
     });
 
+    let master_meter = synth.lock().unwrap().master_meter.values(); // Taken once under the lock; reads through it never lock
     eframe::run_native(
         "Wave Crafter", // Application title
         options,
         Box::new(|_cc| Box::new(WaveCrafterApp {
             synth,
+            master_meter,
+            export_loudness: Arc::new(Mutex::new(String::new())),
+            sample_path: "sample.wav".to_string(), // Audio file used by file tracks and sample clips
             loading: false, // Initial state for loading
             progress: 0.0,  // Initial progress value
         })),
@@ -30,6 +45,9 @@ pub fn run_ui(synth: Arc<Mutex<Synthesizer>>) -> Result<(), eframe::Error> {
 #[allow(dead_code)]
 struct WaveCrafterApp {
     synth: Arc<Mutex<Synthesizer>>, // Shared synthesizer instance
+    master_meter: Arc<MeterValues>, // Output levels published by the audio thread
+    export_loudness: Arc<Mutex<String>>, // Loudness of the last audio export, set by the export thread
+    sample_path: String,            // Audio file to load for new file tracks and sample clips
     loading: bool,                 // Loading state
     progress: f32,                 // Progress value for loading
 }
@@ -61,7 +79,13 @@ impl WaveCrafterApp {
     fn show_main_ui(&mut self, ctx: &egui::Context) {
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.heading("🎵 WaveCraft - Digital Audio Workstation"); // Main application heading
+            show_meter(ui, "Master", &self.master_meter);
+            ctx.request_repaint_after(std::time::Duration::from_millis(50)); // Keep the meters moving
             ui.separator(); // Add a separator line
+            ui.horizontal(|ui| {
+                ui.label("Audio File:");
+                ui.text_edit_singleline(&mut self.sample_path); // Path read by "Add File Track" and "Add Sample Clip"
+            });
 
             // Lock the synthesizer for thread-safe access
             let mut synth = self.synth.lock().unwrap();
@@ -70,6 +94,10 @@ impl WaveCrafterApp {
             self.show_frequency_slider(ui, &mut synth);
             self.show_amplitude_slider(ui, &mut synth);
             self.show_waveform_selector(ui, &mut synth);
+            self.show_envelope_ui(ui, &mut synth);
+            self.show_keyboard(ui, &mut synth);
+            self.show_filter_ui(ui, &mut synth);
+            self.show_modulation_ui(ui, &mut synth);
             self.show_track_management(ui, &mut synth);
             self.show_timeline_visualization(ui, &mut synth);
             self.show_effects_ui(ui, &mut synth);
@@ -117,37 +145,359 @@ impl WaveCrafterApp {
             if ui.selectable_label(synth.waveform == Waveform::Sawtooth, "📐 Sawtooth").clicked() {
                 synth.set_waveform(Waveform::Sawtooth); // Set waveform to sawtooth
             }
+            for (waveform, name) in [(Waveform::WhiteNoise, "White"), (Waveform::PinkNoise, "Pink"), (Waveform::BrownNoise, "Brown")] {
+                if ui.selectable_label(synth.waveform == waveform, name).clicked() {
+                    synth.set_waveform(waveform); // Set waveform to noise
+                }
+            }
+            let is_wavetable = matches!(synth.waveform, Waveform::Wavetable { .. });
+            if !synth.wavetables.is_empty() && ui.selectable_label(is_wavetable, "〰 Wavetable").clicked() {
+                synth.set_waveform(Waveform::Wavetable { table: 0, position: 0.0 }); // Set waveform to the first wavetable
+            }
+            if ui.button("Load Wavetable").clicked() {
+                match synth.load_wavetable("wavetable.wav") {
+                    Ok(table) => synth.set_waveform(Waveform::Wavetable { table, position: 0.0 }), // Switch to the new table
+                    Err(e) => eprintln!("Failed to load wavetable: {}", e), // Log errors during wavetable loading
+                }
+            }
+        });
+        let table_count = synth.wavetables.len();
+        if let Waveform::Wavetable { table, position } = &mut synth.waveform {
+            ui.horizontal(|ui| {
+                if table_count > 1 {
+                    ui.add(egui::Slider::new(table, 0..=table_count - 1).text("Table")); // Choose among loaded tables
+                }
+                ui.add(egui::Slider::new(position, 0.0..=1.0).text("Position")); // Morph across the frames
+            });
+        }
+    }
+
+    fn show_envelope_ui(&self, ui: &mut egui::Ui, synth: &mut Synthesizer) {
+        ui.horizontal(|ui| {
+            ui.label("Envelope:"); // Label for the live voice envelope
+            ui.add(egui::Slider::new(&mut synth.envelope.attack, 0.0..=2.0).text("A")); // Attack time in seconds
+            ui.add(egui::Slider::new(&mut synth.envelope.decay, 0.0..=2.0).text("D")); // Decay time in seconds
+            ui.add(egui::Slider::new(&mut synth.envelope.sustain, 0.0..=1.0).text("S")); // Sustain level
+            ui.add(egui::Slider::new(&mut synth.envelope.release, 0.0..=5.0).text("R")); // Release time in seconds
+        });
+        ui.horizontal(|ui| {
+            let curve = &mut synth.envelope.curve;
+            ui.selectable_value(curve, EnvelopeCurve::Linear, "Linear"); // Straight-line segments
+            ui.selectable_value(curve, EnvelopeCurve::Exponential, "Exponential"); // Curved segments
+            let label = if synth.is_gate_open() { "⏹ Release" } else { "▶ Trigger" };
+            if ui.button(label).clicked() {
+                if synth.is_gate_open() {
+                    synth.gate_off(); // Start the release
+                } else {
+                    synth.gate_on(); // Start the attack
+                }
+            }
+        });
+    }
+
+    fn show_keyboard(&self, ui: &mut egui::Ui, synth: &mut Synthesizer) {
+        const NOTE_NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+        ui.horizontal(|ui| {
+            ui.label("Keyboard:"); // Click keys to hold or release notes, so chords can be built up
+            for (offset, name) in NOTE_NAMES.iter().enumerate() {
+                let note = 60 + offset as u8; // One octave from middle C
+                let held = synth.voices.is_note_held(note);
+                if ui.selectable_label(held, *name).clicked() {
+                    if held {
+                        synth.note_off(note);
+                    } else {
+                        synth.note_on(note, 0.8);
+                    }
+                }
+            }
+            if ui.button("All Off").clicked() {
+                synth.voices.all_notes_off(); // Release every held note
+            }
+        });
+        ui.horizontal(|ui| {
+            let mut polyphony = synth.voices.polyphony();
+            if ui.add(egui::Slider::new(&mut polyphony, 1..=32).text("Voices")).changed() {
+                synth.voices.set_polyphony(polyphony); // Update polyphony limit
+            }
+            ui.label("Steal:"); // Voice stealing mode
+            ui.selectable_value(&mut synth.voices.stealing, VoiceStealing::Oldest, "Oldest");
+            ui.selectable_value(&mut synth.voices.stealing, VoiceStealing::Quietest, "Quietest");
+            ui.selectable_value(&mut synth.voices.stealing, VoiceStealing::SameNote, "Same Note");
+        });
+        ui.horizontal(|ui| {
+            ui.label("Voice:"); // Sound generator for played notes
+            show_source_selector(ui, "voice_source", &mut synth.voice_source);
+        });
+        if let SoundSource::Fm(patch) = &mut synth.voice_source {
+            show_fm_editor(ui, patch);
+        }
+    }
+
+    fn show_filter_ui(&self, ui: &mut egui::Ui, synth: &mut Synthesizer) {
+        ui.horizontal(|ui| {
+            let filter = &mut synth.filter;
+            ui.checkbox(&mut filter.enabled, "Filter"); // Toggle the filter
+            ui.selectable_value(&mut filter.mode, FilterMode::LowPass, "LP");
+            ui.selectable_value(&mut filter.mode, FilterMode::HighPass, "HP");
+            ui.selectable_value(&mut filter.mode, FilterMode::BandPass, "BP");
+            ui.selectable_value(&mut filter.mode, FilterMode::Notch, "Notch");
+            ui.add(egui::Slider::new(&mut filter.cutoff, 20.0..=20000.0).logarithmic(true).text("Cutoff")); // Cutoff in Hz
+            ui.add(egui::Slider::new(&mut filter.resonance, 0.0..=1.0).text("Resonance"));
+        });
+    }
+
+    fn show_modulation_ui(&self, ui: &mut egui::Ui, synth: &mut Synthesizer) {
+        ui.heading("Modulation"); // Heading for LFOs and routings
+        ui.add(egui::Slider::new(&mut synth.tempo, 40.0..=240.0).text("Tempo (BPM)")); // Used by synced LFOs
+
+        let mut lfo_to_remove = None;
+        for (index, lfo) in synth.modulation.lfos.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(format!("LFO {}", index + 1));
+                egui::ComboBox::from_id_source(("lfo_shape", index))
+                    .selected_text(format!("{:?}", lfo.shape))
+                    .show_ui(ui, |ui| {
+                        for waveform in [Waveform::Sine, Waveform::Square, Waveform::Triangle, Waveform::Sawtooth] {
+                            ui.selectable_value(&mut lfo.shape, LfoShape::Waveform(waveform), format!("{:?}", waveform));
+                        }
+                        ui.selectable_value(&mut lfo.shape, LfoShape::SampleAndHold, "Sample & Hold");
+                    });
+                show_rate_control(ui, &mut lfo.rate);
+                if ui.button("Remove").clicked() {
+                    lfo_to_remove = Some(index); // Mark LFO for removal
+                }
+            });
+        }
+        if let Some(index) = lfo_to_remove {
+            synth.modulation.remove_lfo(index);
+        }
+
+        let lfo_count = synth.modulation.lfos.len();
+        let mut route_to_remove = None;
+        for (index, route) in synth.modulation.routes.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_source(("route_source", index))
+                    .selected_text(format!("LFO {}", route.source + 1))
+                    .show_ui(ui, |ui| {
+                        for source in 0..lfo_count {
+                            ui.selectable_value(&mut route.source, source, format!("LFO {}", source + 1));
+                        }
+                    });
+                ui.label("→");
+                egui::ComboBox::from_id_source(("route_target", index))
+                    .selected_text(format!("{:?}", route.target))
+                    .show_ui(ui, |ui| {
+                        for target in ModTarget::ALL {
+                            ui.selectable_value(&mut route.target, target, format!("{:?}", target));
+                        }
+                    });
+                ui.add(egui::Slider::new(&mut route.depth, -2.0..=2.0).text("Depth"));
+                if ui.button("Remove").clicked() {
+                    route_to_remove = Some(index); // Mark route for removal
+                }
+            });
+        }
+        if let Some(index) = route_to_remove {
+            synth.modulation.routes.remove(index);
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("Add LFO").clicked() {
+                synth.modulation.lfos.push(Lfo::new(LfoShape::Waveform(Waveform::Sine), LfoRate::Hertz(1.0)));
+            }
+            if lfo_count > 0 && ui.button("Add Route").clicked() {
+                synth.modulation.routes.push(ModRoute { source: 0, target: ModTarget::FilterCutoff, depth: 0.5 });
+            }
         });
     }
 
     fn show_track_management(&self, ui: &mut egui::Ui, synth: &mut Synthesizer) {
         ui.heading("Tracks"); // Heading for track management
-        if ui.button("Add Track").clicked() {
-            synth.add_track("New Track"); // Add a new track
-        }
+        ui.horizontal(|ui| {
+            let id = synth.mixer.new_track_id(); // Clips find their track by ID, so keep IDs unique
+            if ui.button("Add Track").clicked() {
+                synth.add_track(&id); // Add a new track
+            }
+            if ui.button("Add File Track").clicked() {
+                if let Err(e) = synth.add_file_track(&id, &self.sample_path) {
+                    eprintln!("Failed to load track file: {}", e); // Log errors reading the file
+                }
+            }
+        });
 
-        for track in &mut synth.tracks {
+        egui::ComboBox::from_label("Pan Law")
+            .selected_text(synth.mixer.pan_law.name())
+            .show_ui(ui, |ui| {
+                for law in PanLaw::ALL {
+                    ui.selectable_value(&mut synth.mixer.pan_law, law, law.name());
+                }
+            });
+        egui::ComboBox::from_label("Solo Mode")
+            .selected_text(format!("{:?}", synth.mixer.solo_mode))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut synth.mixer.solo_mode, SoloMode::Additive, "Additive");
+                ui.selectable_value(&mut synth.mixer.solo_mode, SoloMode::Exclusive, "Exclusive");
+            });
+
+        let audible: Vec<bool> = (0..synth.mixer.tracks.len()).map(|index| synth.mixer.is_audible(index)).collect();
+        let bus_ids: Vec<String> = synth.mixer.buses.iter().map(|bus| bus.id.clone()).collect();
+        let track_ids: Vec<String> = synth.mixer.tracks.iter().map(|track| track.id.clone()).collect(); // Sidechain keys
+        let mut mute_change = None; // (track, muted), applied to the whole group after the loop
+        let mut solo_change = None; // (track, solo)
+        let mut routing_changes = Vec::new(); // (node, new output, bus to send to), validated after the loop
+        let sample_rate = synth.sample_rate;
+        for (index, track) in synth.mixer.tracks.iter_mut().enumerate() {
             ui.horizontal(|ui| {
-                ui.label(&track.id); // Display track ID
+                let label = egui::RichText::new(&track.id);
+                ui.label(if audible[index] { label } else { label.weak() }); // Dim tracks silenced by mute or solo
+                match &track.source {
+                    TrackSource::File(region) => {
+                        ui.label(&region.filename);
+                    }
+                    TrackSource::Synth | TrackSource::Clips => {
+                        egui::ComboBox::from_id_source(("track_source", &track.id))
+                            .selected_text(if track.source == TrackSource::Synth { "Synth" } else { "Clips" })
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut track.source, TrackSource::Synth, "Synth");
+                                ui.selectable_value(&mut track.source, TrackSource::Clips, "Clips");
+                            });
+                    }
+                }
                 let mut volume = track.volume;
                 if ui.add(egui::Slider::new(&mut volume, 0.0..=1.0)).changed() {
                     track.volume = volume; // Update track volume
                 }
-                if ui.button("Mute").clicked() {
-                    track.muted = !track.muted; // Toggle mute state
+                ui.add(egui::Slider::new(&mut track.pan, -1.0..=1.0).text("Pan"));
+                if ui.button("Center").clicked() {
+                    track.pan = 0.0;
                 }
+                let mut balance = track.pan_mode == PanMode::Balance;
+                if ui.checkbox(&mut balance, "Balance").on_hover_text("Keep the stereo image and turn down the opposite side").changed() {
+                    track.pan_mode = if balance { PanMode::Balance } else { PanMode::Pan };
+                }
+                if ui.selectable_label(track.muted, "Mute").clicked() {
+                    mute_change = Some((index, !track.muted)); // Toggle mute state
+                }
+                if ui.selectable_label(track.solo, "Solo").clicked() {
+                    solo_change = Some((index, !track.solo));
+                }
+                ui.checkbox(&mut track.solo_safe, "Solo Safe").on_hover_text("Keep playing while other tracks are soloed");
+                ui.add(egui::TextEdit::singleline(&mut track.group).hint_text("Group").desired_width(60.0)); // Mute/solo group
+            });
+            let (output, send) = show_routing(ui, &track.id, &track.output, &mut track.sends, &bus_ids);
+            routing_changes.push((Node::Track(index), output, send));
+            show_meter(ui, "", &track.meter.values());
+            egui::CollapsingHeader::new("Inserts").id_source(("track_inserts", &track.id)).show(ui, |ui| {
+                ui.horizontal_wrapped(|ui| show_add_effect_buttons(ui, &mut track.inserts));
+                show_effect_chain(ui, &format!("track {}", track.id), &mut track.inserts, &track_ids, sample_rate);
             });
         }
+        if let Some((index, muted)) = mute_change {
+            synth.mixer.set_muted(index, muted);
+        }
+        if let Some((index, solo)) = solo_change {
+            synth.mixer.set_solo(index, solo);
+        }
+
+        ui.heading("Buses"); // Group and return buses
+        ui.horizontal(|ui| {
+            let id = synth.mixer.new_bus_id(); // Routing finds buses by ID
+            let mut new_bus = None;
+            if ui.button("Add Group Bus").clicked() {
+                new_bus = Some(Bus::new(&id, BusKind::Group));
+            }
+            if ui.button("Add Return Bus").clicked() {
+                new_bus = Some(Bus::new(&id, BusKind::Return));
+            }
+            if let Some(Err(e)) = new_bus.map(|bus| synth.mixer.add_bus(bus)) {
+                eprintln!("Failed to add bus: {}", e);
+            }
+        });
+        let mut bus_to_remove = None;
+        let mut bus_solo_change = None; // (bus, solo)
+        let buses_audible: Vec<bool> = (0..synth.mixer.buses.len()).map(|index| synth.mixer.is_bus_audible(index)).collect();
+        for (index, bus) in synth.mixer.buses.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                let label = egui::RichText::new(format!("{} ({:?})", bus.id, bus.kind));
+                ui.label(if buses_audible[index] { label } else { label.weak() });
+                ui.add(egui::Slider::new(&mut bus.volume, 0.0..=1.0));
+                ui.add(egui::Slider::new(&mut bus.pan, -1.0..=1.0).text("Balance"));
+                if ui.selectable_label(bus.muted, "Mute").clicked() {
+                    bus.muted = !bus.muted;
+                }
+                if ui.selectable_label(bus.solo, "Solo").clicked() {
+                    bus_solo_change = Some((index, !bus.solo));
+                }
+                ui.checkbox(&mut bus.solo_safe, "Solo Safe").on_hover_text("Keep playing while tracks or other buses are soloed");
+                if ui.button("Remove").clicked() {
+                    bus_to_remove = Some(index);
+                }
+            });
+            let others: Vec<String> = bus_ids.iter().filter(|id| **id != bus.id).cloned().collect(); // A bus cannot feed itself
+            let (output, send) = show_routing(ui, &bus.id, &bus.output, &mut bus.sends, &others);
+            routing_changes.push((Node::Bus(index), output, send));
+            egui::CollapsingHeader::new("Inserts").id_source(("bus_inserts", &bus.id)).show(ui, |ui| {
+                ui.horizontal_wrapped(|ui| show_add_effect_buttons(ui, &mut bus.inserts));
+                show_effect_chain(ui, &format!("bus {}", bus.id), &mut bus.inserts, &track_ids, sample_rate);
+            });
+        }
+        for (node, output, send) in routing_changes {
+            let output = output.map(|output| synth.mixer.set_output(node, &output));
+            let send = send.map(|bus| synth.mixer.add_send(node, AuxSend::new(&bus, 0.5, false)));
+            for result in [output, send].into_iter().flatten() {
+                if let Err(e) = result {
+                    eprintln!("Routing rejected: {}", e); // The mixer keeps its previous routing
+                }
+            }
+        }
+        if let Some((index, solo)) = bus_solo_change {
+            synth.mixer.set_bus_solo(index, solo);
+        }
+        if let Some(index) = bus_to_remove {
+            synth.mixer.remove_bus(index);
+        }
     }
 
     fn show_timeline_visualization(&self, ui: &mut egui::Ui, synth: &mut Synthesizer) {
         ui.heading("Timeline"); // Heading for timeline
+        if ui.button("Add Sample Clip").clicked() {
+            let id = format!("Sample {}", synth.timeline.clips.len() + 1);
+            if let Err(e) = synth.add_sample_clip(&id, 0.0, &self.sample_path) {
+                eprintln!("Failed to load sample: {}", e); // Log errors reading the file
+            }
+        }
+        let clip_tracks: Vec<String> = synth
+            .mixer
+            .tracks
+            .iter()
+            .filter(|track| track.source == TrackSource::Clips)
+            .map(|track| track.id.clone())
+            .collect();
         let mut clips_to_remove = Vec::new(); // Collect clips to remove
         for clip in &mut synth.timeline.clips {
             ui.horizontal(|ui| {
                 ui.label(&clip.id); // Display clip ID
                 ui.add(egui::Slider::new(&mut clip.start_time, 0.0..=60.0).text("Start Time")); // Adjust start time
                 ui.add(egui::Slider::new(&mut clip.duration, 0.1..=10.0).text("Duration")); // Adjust duration
+                show_source_selector(ui, ("clip_source", &clip.id), &mut clip.source); // Oscillator or FM
+                egui::ComboBox::from_id_source(("clip_track", &clip.id))
+                    .selected_text(if clip.track.is_empty() { "No Track" } else { clip.track.as_str() })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut clip.track, String::new(), "No Track"); // Play directly
+                        for id in &clip_tracks {
+                            ui.selectable_value(&mut clip.track, id.clone(), id);
+                        }
+                    });
+                if let SoundSource::Sample(region) = &mut clip.source {
+                    let length = region.buffer.as_ref().map_or(0.0, |buffer| buffer.duration());
+                    let (mut trim_start, mut trim_end) = (region.trim_start, region.trim_end);
+                    ui.add(egui::Slider::new(&mut region.gain, 0.0..=2.0).text("Gain"));
+                    let start_changed = ui.add(egui::Slider::new(&mut trim_start, 0.0..=length).text("Trim Start")).changed();
+                    let end_changed = ui.add(egui::Slider::new(&mut trim_end, 0.0..=length).text("Trim End")).changed();
+                    if start_changed || end_changed {
+                        clip.trim(trim_start, trim_end); // Keeps the clip as long as the trimmed region
+                    }
+                }
                 if ui.button("Remove").clicked() {
                     clips_to_remove.push(clip.id.clone()); // Mark clip for removal
                 }
@@ -161,12 +511,18 @@ impl WaveCrafterApp {
     fn show_effects_ui(&self, ui: &mut egui::Ui, synth: &mut Synthesizer) {
         ui.heading("Effects"); // Heading for effects
         ui.horizontal(|ui| {
-            ui.label("Delay:"); // Label for delay effect
-            let mut delay = synth.effects.delay;
-            if ui.add(egui::Slider::new(&mut delay, 0.0..=100.0)).changed() {
-                synth.update_effect("delay", delay); // Update delay effect
+            show_add_effect_buttons(ui, &mut synth.effects);
+            if ui.button("Add Convolution").clicked() {
+                if let Err(e) = synth.add_convolution("impulse.wav") {
+                    eprintln!("Failed to load impulse response: {}", e); // Log errors reading the file
+                }
             }
         });
+        show_effect_chain(ui, "effects", &mut synth.effects, &[], synth.sample_rate);
+
+        ui.heading("Master"); // Effects on the final output
+        ui.horizontal(|ui| show_add_effect_buttons(ui, &mut synth.master));
+        show_effect_chain(ui, "master", &mut synth.master, &[], synth.sample_rate);
     }
 
     fn show_project_management(&mut self, ui: &mut egui::Ui) {
@@ -180,15 +536,25 @@ impl WaveCrafterApp {
         }
 
         // Export Audio
-        if ui.button("💾 Export Audio").clicked() {
-            self.loading = true; // Set loading state
-            let synth_clone = Arc::clone(&self.synth);
-            thread::spawn(move || {
-                if let Err(e) = synth_clone.lock().unwrap().export_to_wav(5.0, "output.wav") {
-                    eprintln!("Failed to export audio: {}", e); // Log errors during audio export
-                }
-            });
-        }
+        ui.horizontal(|ui| {
+            if ui.button("💾 Export Audio").clicked() {
+                self.loading = true; // Set loading state
+                let synth_clone = Arc::clone(&self.synth);
+                let export_loudness = Arc::clone(&self.export_loudness);
+                thread::spawn(move || {
+                    let mut synth = synth_clone.lock().unwrap();
+                    match synth.export_to_wav(5.0, "output.wav") {
+                        Ok(()) => {
+                            let reading = synth.master_meter.reading(); // Measured over the export alone
+                            *export_loudness.lock().unwrap() =
+                                format!("{} LUFS integrated, {} dBTP true peak", level_text(reading.integrated), level_text(reading.true_peak));
+                        }
+                        Err(e) => eprintln!("Failed to export audio: {}", e), // Log errors during audio export
+                    }
+                });
+            }
+            ui.label(self.export_loudness.lock().unwrap().as_str()); // Empty until the first export
+        });
 
         // Load Project
         if ui.button("Load Project").clicked() {
@@ -202,3 +568,450 @@ impl WaveCrafterApp {
         }
     }
 }
+
+/// Combo box choosing between the oscillator and the FM presets.
+fn show_source_selector(ui: &mut egui::Ui, id: impl std::hash::Hash, source: &mut SoundSource) {
+    let presets = [
+        ("Oscillator", SoundSource::Oscillator),
+        ("FM E-Piano", SoundSource::Fm(FmPatch::electric_piano())),
+        ("FM Bell", SoundSource::Fm(FmPatch::bell())),
+        ("FM Bass", SoundSource::Fm(FmPatch::bass())),
+    ];
+    let selected = match source {
+        SoundSource::Oscillator => "Oscillator",
+        SoundSource::Fm(_) => "FM",
+        SoundSource::Sample(_) => "Sample",
+    };
+    egui::ComboBox::from_id_source(id).selected_text(selected).show_ui(ui, |ui| {
+        for (name, preset) in presets {
+            if ui.selectable_label(*source == preset, name).clicked() {
+                *source = preset; // Load the preset
+            }
+        }
+    });
+}
+
+/// Buttons that append a new effect to a chain.
+fn show_add_effect_buttons(ui: &mut egui::Ui, chain: &mut EffectChain) {
+    if ui.button("Add Delay").clicked() {
+        chain.add(EffectKind::Delay(Delay::default()));
+    }
+    if ui.button("Add Reverb").clicked() {
+        chain.add(EffectKind::Reverb(Reverb::default()));
+    }
+    if ui.button("Add Compressor").clicked() {
+        chain.add(EffectKind::Compressor(Compressor::default()));
+    }
+    if ui.button("Add Limiter").clicked() {
+        chain.add(EffectKind::Limiter(Limiter::default()));
+    }
+    if ui.button("Add Gate").clicked() {
+        chain.add(EffectKind::Gate(Gate::default()));
+    }
+    if ui.button("Add EQ").clicked() {
+        chain.add(EffectKind::Equalizer(Equalizer::default()));
+    }
+    if ui.button("Add Chorus").clicked() {
+        chain.add(EffectKind::Chorus(Chorus::default()));
+    }
+    if ui.button("Add Flanger").clicked() {
+        chain.add(EffectKind::Flanger(Flanger::default()));
+    }
+    if ui.button("Add Phaser").clicked() {
+        chain.add(EffectKind::Phaser(Phaser::default()));
+    }
+    if ui.button("Add Tremolo").clicked() {
+        chain.add(EffectKind::Tremolo(Tremolo::default()));
+    }
+    if ui.button("Add Auto-Pan").clicked() {
+        chain.add(EffectKind::AutoPan(AutoPan::default()));
+    }
+    if ui.button("Add Saturation").clicked() {
+        chain.add(EffectKind::Saturation(Saturation::default()));
+    }
+    if ui.button("Add Waveshaper").clicked() {
+        chain.add(EffectKind::Waveshaper(Waveshaper::default()));
+    }
+    if ui.button("Add Bitcrusher").clicked() {
+        chain.add(EffectKind::Bitcrusher(Bitcrusher::default()));
+    }
+}
+
+/// Slots of an effect chain, with bypass, slot mix, reordering and removal. Mixer inserts
+/// pass the track IDs that can key a sidechain; other chains pass none.
+fn show_effect_chain(ui: &mut egui::Ui, id: &str, chain: &mut EffectChain, key_tracks: &[String], sample_rate: f32) {
+    let slot_count = chain.slots.len();
+    let mut slot_to_move = None; // (from, to) for a reordered slot
+    let mut slot_to_remove = None;
+    let mut slot_to_prepare = None; // Slot whose latency setting changed
+    for (index, slot) in chain.slots.iter_mut().enumerate() {
+        ui.push_id((id, index), |ui| {
+            ui.horizontal(|ui| {
+                ui.label(format!("{}. {}", index + 1, slot.effect.name()));
+                ui.checkbox(&mut slot.bypass, "Bypass");
+                ui.add(egui::Slider::new(&mut slot.mix, 0.0..=1.0).text("Slot Mix")); // Wet/dry of the whole slot
+                if !key_tracks.is_empty() && slot.effect.accepts_sidechain() {
+                    let selected = if slot.sidechain.is_empty() { "Own Input" } else { slot.sidechain.as_str() };
+                    egui::ComboBox::from_id_source(("sidechain", id, index)).selected_text(selected.to_string()).show_ui(ui, |ui| {
+                        ui.selectable_value(&mut slot.sidechain, String::new(), "Own Input");
+                        for track in key_tracks {
+                            ui.selectable_value(&mut slot.sidechain, track.clone(), track);
+                        }
+                    });
+                }
+                if ui.add_enabled(index > 0, egui::Button::new("⬆")).clicked() {
+                    slot_to_move = Some((index, index - 1));
+                }
+                if ui.add_enabled(index + 1 < slot_count, egui::Button::new("⬇")).clicked() {
+                    slot_to_move = Some((index, index + 1));
+                }
+                if ui.button("Remove").clicked() {
+                    slot_to_remove = Some(index); // Mark slot for removal
+                }
+            });
+            ui.horizontal(|ui| {
+                if show_effect_controls(ui, (id, index), &mut slot.effect, sample_rate) {
+                    slot_to_prepare = Some(index);
+                }
+            });
+        });
+    }
+    if let Some(index) = slot_to_prepare {
+        chain.prepare_slot(index); // Resizes the buffers here rather than in the audio callback
+    }
+    if let Some((from, to)) = slot_to_move {
+        chain.move_slot(from, to);
+    }
+    if let Some(index) = slot_to_remove {
+        chain.remove(index);
+    }
+}
+
+/// Output selector and aux sends of a track or bus. Send levels and taps are edited in place;
+/// a new output or send could create a feedback loop, so it is returned for the mixer to check.
+///
+/// # Returns
+/// - The newly selected output, and the bus picked for a new send.
+fn show_routing(ui: &mut egui::Ui, id: &str, output: &str, sends: &mut Vec<AuxSend>, buses: &[String]) -> (Option<String>, Option<String>) {
+    let (mut new_output, mut new_send) = (None, None);
+    let mut send_to_remove = None;
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_id_source(("output", id))
+            .selected_text(if output.is_empty() { "Master" } else { output })
+            .show_ui(ui, |ui| {
+                if ui.selectable_label(output.is_empty(), "Master").clicked() {
+                    new_output = Some(String::new());
+                }
+                for bus in buses {
+                    if ui.selectable_label(output == bus, bus).clicked() {
+                        new_output = Some(bus.clone());
+                    }
+                }
+            });
+        for (index, send) in sends.iter_mut().enumerate() {
+            ui.push_id(index, |ui| {
+                ui.add(egui::Slider::new(&mut send.level, 0.0..=1.0).text(&send.bus));
+                ui.checkbox(&mut send.pre_fader, "Pre").on_hover_text("Send before the volume fader");
+                if ui.button("✖").clicked() {
+                    send_to_remove = Some(index);
+                }
+            });
+        }
+        egui::ComboBox::from_id_source(("add_send", id))
+            .selected_text("Add Send")
+            .show_ui(ui, |ui| {
+                for bus in buses {
+                    if ui.selectable_label(false, bus).clicked() {
+                        new_send = Some(bus.clone());
+                    }
+                }
+            });
+    });
+    if let Some(index) = send_to_remove {
+        sends.remove(index); // Removing a connection cannot create a loop
+    }
+    (new_output, new_send)
+}
+
+/// Formats a level in dB, showing silence as minus infinity.
+fn level_text(db: f32) -> String {
+    if db.is_finite() {
+        format!("{:.1}", db)
+    } else {
+        "-∞".to_string()
+    }
+}
+
+/// Meter bar from -60 to 0 dBFS: the RMS level inside the peak level, a tick at the true
+/// peak, a clip indicator that clears when clicked, and the loudness readings.
+fn show_meter(ui: &mut egui::Ui, label: &str, values: &MeterValues) {
+    const FLOOR: f32 = -60.0; // Bottom of the bar, in dBFS
+    let reading = values.read();
+    ui.horizontal(|ui| {
+        if !label.is_empty() {
+            ui.label(label);
+        }
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(200.0, 12.0), egui::Sense::hover());
+        let fill = |db: f32| {
+            let fraction = ((db - FLOOR) / -FLOOR).clamp(0.0, 1.0); // Silence is negative infinity, so it stays empty
+            egui::Rect::from_min_max(rect.min, egui::pos2(rect.left() + rect.width() * fraction, rect.bottom()))
+        };
+        let peak_color = match reading.peak {
+            db if db > -6.0 => egui::Color32::from_rgb(220, 60, 50),
+            db if db > -18.0 => egui::Color32::from_rgb(220, 200, 60),
+            _ => egui::Color32::from_rgb(70, 190, 90),
+        };
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, egui::Color32::from_gray(30));
+        painter.rect_filled(fill(reading.peak), 0.0, peak_color);
+        painter.rect_filled(fill(reading.rms).shrink2(egui::vec2(0.0, 3.0)), 0.0, egui::Color32::from_rgb(40, 110, 60));
+        if reading.true_peak.is_finite() {
+            let x = fill(reading.true_peak).right();
+            painter.line_segment([egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())], egui::Stroke::new(1.5, egui::Color32::WHITE));
+        }
+
+        let clip_color = if reading.clipped { egui::Color32::from_rgb(220, 40, 40) } else { egui::Color32::from_gray(50) };
+        if ui.add(egui::Button::new("CLIP").fill(clip_color)).on_hover_text("True peak over 0 dBTP; click to clear").clicked() {
+            values.clear_clip();
+        }
+        ui.label(format!(
+            "TP {} dBTP  M {}  S {}  I {} LUFS",
+            level_text(reading.true_peak),
+            level_text(reading.momentary),
+            level_text(reading.short_term),
+            level_text(reading.integrated),
+        ));
+    });
+}
+
+/// Sync checkbox and rate slider for an LFO rate, in Hz or beats per cycle.
+fn show_rate_control(ui: &mut egui::Ui, rate: &mut LfoRate) {
+    let mut synced = matches!(rate, LfoRate::Synced(_));
+    if ui.checkbox(&mut synced, "Sync").changed() {
+        *rate = if synced { LfoRate::Synced(1.0) } else { LfoRate::Hertz(1.0) }; // Switch rate mode
+    }
+    match rate {
+        LfoRate::Hertz(hz) => ui.add(egui::Slider::new(hz, 0.01..=20.0).logarithmic(true).text("Hz")),
+        LfoRate::Synced(beats) => ui.add(egui::Slider::new(beats, 0.25..=16.0).logarithmic(true).text("Beats")),
+    };
+}
+
+/// Selector for the oversampling of a nonlinear stage, in the slot given by `id`.
+fn show_oversampling_control(ui: &mut egui::Ui, id: (&str, usize), oversampling: &mut Oversampling) {
+    egui::ComboBox::from_id_source(("oversampling", id))
+        .selected_text(format!("{:?}", oversampling))
+        .show_ui(ui, |ui| {
+            for option in Oversampling::ALL {
+                ui.selectable_value(oversampling, option, format!("{:?}", option));
+            }
+        });
+}
+
+/// Draws a waveshaper curve and lets the user drag its points up and down.
+fn show_waveshaper_curve(ui: &mut egui::Ui, waveshaper: &mut Waveshaper) {
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(160.0, 160.0), egui::Sense::click_and_drag());
+    let to_screen = |x: f32, y: f32| egui::pos2(rect.center().x + x * rect.width() / 2.0, rect.center().y - y.clamp(-1.0, 1.0) * rect.height() / 2.0);
+
+    let count = waveshaper.curve.len();
+    if let (Some(pointer), true) = (response.interact_pointer_pos(), count > 1) {
+        let x = ((pointer.x - rect.left()) / rect.width()).clamp(0.0, 1.0);
+        let index = (x * (count - 1) as f32).round() as usize; // Nearest point
+        waveshaper.curve[index] = ((rect.center().y - pointer.y) / (rect.height() / 2.0)).clamp(-1.0, 1.0);
+    }
+
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, egui::Color32::from_gray(24));
+    painter.line_segment([to_screen(-1.0, -1.0), to_screen(1.0, 1.0)], egui::Stroke::new(1.0, egui::Color32::from_gray(70))); // Unshaped
+    let points: Vec<egui::Pos2> = (0..=100)
+        .map(|i| {
+            let x = i as f32 / 50.0 - 1.0;
+            to_screen(x, waveshaper.shape(x))
+        })
+        .collect();
+    painter.add(egui::Shape::line(points, egui::Stroke::new(1.5, egui::Color32::LIGHT_BLUE)));
+    for (index, &y) in waveshaper.curve.iter().enumerate() {
+        let x = index as f32 / (count - 1).max(1) as f32 * 2.0 - 1.0;
+        painter.circle_filled(to_screen(x, y), 3.0, egui::Color32::WHITE);
+    }
+}
+
+/// Draws the combined response of an equalizer from 20 Hz to 20 kHz, over a ±24 dB range.
+fn show_eq_curve(ui: &mut egui::Ui, equalizer: &Equalizer, sample_rate: f32) {
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(400.0, 120.0), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, egui::Color32::from_gray(24));
+    painter.line_segment([rect.left_center(), rect.right_center()], egui::Stroke::new(1.0, egui::Color32::from_gray(70))); // 0 dB
+
+    let points: Vec<egui::Pos2> = (0..=200)
+        .map(|i| {
+            let position = i as f32 / 200.0;
+            let frequency = 20.0 * 1000f32.powf(position); // Logarithmic axis
+            let db = 20.0 * equalizer.magnitude_response(frequency, sample_rate).max(1e-6).log10();
+            let y = rect.center().y - (db.clamp(-24.0, 24.0) / 24.0) * rect.height() / 2.0;
+            egui::pos2(rect.left() + position * rect.width(), y)
+        })
+        .collect();
+    painter.add(egui::Shape::line(points, egui::Stroke::new(1.5, egui::Color32::LIGHT_BLUE)));
+}
+
+/// Controls for the parameters of one effect. `id` is the chain ID and slot index, which keeps
+/// the combo boxes of every slot apart.
+///
+/// # Returns
+/// - `true` if a setting that changes the latency of the effect was edited.
+fn show_effect_controls(ui: &mut egui::Ui, id: (&str, usize), effect: &mut EffectKind, sample_rate: f32) -> bool {
+    let mut latency_changed = false;
+    match effect {
+        EffectKind::Delay(delay) => {
+            ui.add(egui::Slider::new(&mut delay.mix, 0.0..=1.0).text("Mix"));
+            let mut synced = matches!(delay.time, DelayTime::Synced(_));
+            if ui.checkbox(&mut synced, "Sync").changed() {
+                delay.time = if synced { DelayTime::Synced(0.5) } else { DelayTime::Milliseconds(250.0) }; // Switch time mode
+            }
+            match &mut delay.time {
+                DelayTime::Milliseconds(ms) => ui.add(egui::Slider::new(ms, 1.0..=2000.0).logarithmic(true).text("ms")),
+                DelayTime::Synced(beats) => ui.add(egui::Slider::new(beats, 0.125..=4.0).logarithmic(true).text("Beats")),
+            };
+            ui.add(egui::Slider::new(&mut delay.feedback, 0.0..=0.95).text("Feedback"));
+            ui.checkbox(&mut delay.ping_pong, "Ping-Pong"); // Bounce echoes between channels
+        }
+        EffectKind::Reverb(reverb) => {
+            ui.add(egui::Slider::new(&mut reverb.mix, 0.0..=1.0).text("Mix"));
+            ui.add(egui::Slider::new(&mut reverb.room_size, 0.0..=1.0).text("Room Size"));
+            ui.add(egui::Slider::new(&mut reverb.damping, 0.0..=1.0).text("Damping"));
+            ui.add(egui::Slider::new(&mut reverb.pre_delay, 0.0..=200.0).text("Pre-Delay ms"));
+            ui.add(egui::Slider::new(&mut reverb.width, 0.0..=1.0).text("Width"));
+        }
+        EffectKind::Convolution(convolution) => {
+            ui.label(&convolution.filename); // Impulse response file
+            ui.add(egui::Slider::new(&mut convolution.mix, 0.0..=1.0).text("Mix"));
+        }
+        EffectKind::Compressor(compressor) => {
+            ui.add(egui::Slider::new(&mut compressor.threshold, -60.0..=0.0).text("Threshold dB"));
+            ui.add(egui::Slider::new(&mut compressor.ratio, 1.0..=20.0).logarithmic(true).text("Ratio"));
+            ui.add(egui::Slider::new(&mut compressor.attack, 0.1..=200.0).logarithmic(true).text("Attack ms"));
+            ui.add(egui::Slider::new(&mut compressor.release, 5.0..=2000.0).logarithmic(true).text("Release ms"));
+            ui.add(egui::Slider::new(&mut compressor.knee, 0.0..=24.0).text("Knee dB"));
+            ui.add(egui::Slider::new(&mut compressor.makeup, 0.0..=24.0).text("Makeup dB"));
+            ui.label(format!("GR {:.1} dB", compressor.gain_reduction())); // Current gain reduction
+        }
+        EffectKind::Limiter(limiter) => {
+            ui.add(egui::Slider::new(&mut limiter.ceiling, -24.0..=0.0).text("Ceiling dB"));
+            ui.add(egui::Slider::new(&mut limiter.makeup, 0.0..=24.0).text("Gain dB"));
+            latency_changed = ui.add(egui::Slider::new(&mut limiter.lookahead, 0.5..=MAX_LOOKAHEAD_MS).text("Lookahead ms")).changed();
+            ui.add(egui::Slider::new(&mut limiter.release, 5.0..=2000.0).logarithmic(true).text("Release ms"));
+            ui.label(format!("GR {:.1} dB", limiter.gain_reduction()));
+        }
+        EffectKind::Gate(gate) => {
+            ui.add(egui::Slider::new(&mut gate.threshold, -90.0..=0.0).text("Threshold dB"));
+            ui.add(egui::Slider::new(&mut gate.range, 0.0..=90.0).text("Range dB"));
+            ui.add(egui::Slider::new(&mut gate.attack, 0.1..=50.0).logarithmic(true).text("Attack ms"));
+            ui.add(egui::Slider::new(&mut gate.hold, 0.0..=500.0).text("Hold ms"));
+            ui.add(egui::Slider::new(&mut gate.release, 5.0..=2000.0).logarithmic(true).text("Release ms"));
+        }
+        EffectKind::Chorus(chorus) => {
+            ui.add(egui::Slider::new(&mut chorus.mix, 0.0..=1.0).text("Mix"));
+            show_rate_control(ui, &mut chorus.rate);
+            ui.add(egui::Slider::new(&mut chorus.depth, 0.0..=1.0).text("Depth"));
+            ui.add(egui::Slider::new(&mut chorus.feedback, 0.0..=0.9).text("Feedback"));
+        }
+        EffectKind::Flanger(flanger) => {
+            ui.add(egui::Slider::new(&mut flanger.mix, 0.0..=1.0).text("Mix"));
+            show_rate_control(ui, &mut flanger.rate);
+            ui.add(egui::Slider::new(&mut flanger.depth, 0.0..=1.0).text("Depth"));
+            ui.add(egui::Slider::new(&mut flanger.feedback, -0.95..=0.95).text("Feedback"));
+        }
+        EffectKind::Phaser(phaser) => {
+            ui.add(egui::Slider::new(&mut phaser.mix, 0.0..=1.0).text("Mix"));
+            show_rate_control(ui, &mut phaser.rate);
+            ui.add(egui::Slider::new(&mut phaser.depth, 0.0..=1.0).text("Depth"));
+            ui.add(egui::Slider::new(&mut phaser.feedback, -0.9..=0.9).text("Feedback"));
+            ui.add(egui::Slider::new(&mut phaser.stages, 2..=MAX_PHASER_STAGES).step_by(2.0).text("Stages"));
+        }
+        EffectKind::Tremolo(tremolo) => {
+            ui.add(egui::Slider::new(&mut tremolo.mix, 0.0..=1.0).text("Mix"));
+            show_rate_control(ui, &mut tremolo.rate);
+            ui.add(egui::Slider::new(&mut tremolo.depth, 0.0..=1.0).text("Depth"));
+        }
+        EffectKind::AutoPan(auto_pan) => {
+            ui.add(egui::Slider::new(&mut auto_pan.mix, 0.0..=1.0).text("Mix"));
+            show_rate_control(ui, &mut auto_pan.rate);
+            ui.add(egui::Slider::new(&mut auto_pan.depth, 0.0..=1.0).text("Depth"));
+        }
+        EffectKind::Saturation(saturation) => {
+            egui::ComboBox::from_id_source(("saturation_mode", id))
+                .selected_text(format!("{:?}", saturation.mode))
+                .show_ui(ui, |ui| {
+                    for mode in SaturationMode::ALL {
+                        ui.selectable_value(&mut saturation.mode, mode, format!("{:?}", mode));
+                    }
+                });
+            ui.add(egui::Slider::new(&mut saturation.drive, 0.0..=48.0).text("Drive dB"));
+            ui.add(egui::Slider::new(&mut saturation.output, -24.0..=12.0).text("Output dB"));
+            show_oversampling_control(ui, id, &mut saturation.oversampling);
+        }
+        EffectKind::Waveshaper(waveshaper) => {
+            show_waveshaper_curve(ui, waveshaper);
+            ui.vertical(|ui| {
+                ui.add(egui::Slider::new(&mut waveshaper.drive, 0.0..=48.0).text("Drive dB"));
+                ui.add(egui::Slider::new(&mut waveshaper.output, -24.0..=12.0).text("Output dB"));
+                show_oversampling_control(ui, id, &mut waveshaper.oversampling);
+                if ui.button("Reset Curve").clicked() {
+                    waveshaper.curve = Waveshaper::default().curve; // Back to a straight line
+                }
+            });
+        }
+        EffectKind::Bitcrusher(bitcrusher) => {
+            ui.add(egui::Slider::new(&mut bitcrusher.bits, 1.0..=16.0).text("Bits"));
+            ui.add(egui::Slider::new(&mut bitcrusher.rate, 200.0..=48000.0).logarithmic(true).text("Rate Hz"));
+        }
+        EffectKind::Equalizer(equalizer) => {
+            ui.vertical(|ui| {
+                show_eq_curve(ui, equalizer, sample_rate);
+                for (index, band) in equalizer.bands.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut band.enabled, "");
+                        egui::ComboBox::from_id_source(("eq_band_type", id, index))
+                            .selected_text(format!("{:?}", band.band_type))
+                            .show_ui(ui, |ui| {
+                                for band_type in BandType::ALL {
+                                    ui.selectable_value(&mut band.band_type, band_type, format!("{:?}", band_type));
+                                }
+                            });
+                        ui.add(egui::Slider::new(&mut band.frequency, 20.0..=20000.0).logarithmic(true).text("Hz"));
+                        let has_gain = !matches!(band.band_type, BandType::HighPass | BandType::LowPass);
+                        ui.add_enabled(has_gain, egui::Slider::new(&mut band.gain, -24.0..=24.0).text("dB"));
+                        ui.add(egui::Slider::new(&mut band.q, 0.1..=18.0).logarithmic(true).text("Q"));
+                    });
+                }
+            });
+        }
+    }
+    latency_changed
+}
+
+/// Controls for the algorithm, feedback and operators of an FM patch.
+fn show_fm_editor(ui: &mut egui::Ui, patch: &mut FmPatch) {
+    ui.horizontal(|ui| {
+        ui.label("Algorithm:");
+        egui::ComboBox::from_id_source("fm_algorithm")
+            .selected_text(format!("{:?}", patch.algorithm))
+            .show_ui(ui, |ui| {
+                for algorithm in FmAlgorithm::ALL {
+                    ui.selectable_value(&mut patch.algorithm, algorithm, format!("{:?}", algorithm));
+                }
+            });
+        ui.add(egui::Slider::new(&mut patch.feedback, 0.0..=1.0).text("Feedback")); // Operator 4 self-modulation
+    });
+    for (index, operator) in patch.operators.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            ui.label(format!("Op {}", index + 1));
+            ui.add(egui::Slider::new(&mut operator.ratio, 0.5..=16.0).text("Ratio"));
+            ui.add(egui::Slider::new(&mut operator.level, 0.0..=1.0).text("Level"));
+            ui.add(egui::Slider::new(&mut operator.envelope.attack, 0.0..=2.0).text("A"));
+            ui.add(egui::Slider::new(&mut operator.envelope.decay, 0.0..=5.0).text("D"));
+            ui.add(egui::Slider::new(&mut operator.envelope.sustain, 0.0..=1.0).text("S"));
+            ui.add(egui::Slider::new(&mut operator.envelope.release, 0.0..=5.0).text("R"));
+        });
+    }
+}
@@ -0,0 +1,237 @@
+use serde::{Serialize, Deserialize};
+use crate::envelope::{Envelope, EnvelopeGenerator};
+use crate::oscillator::Oscillator;
+use crate::fm::FmVoice;
+use crate::synthesizer::{SoundSource, Waveform};
+use crate::wavetable::Wavetable;
+
+/// Strategy for choosing which voice to take over when every voice is busy.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum VoiceStealing {
+    Oldest,   // Steal the voice that was started first
+    Quietest, // Steal the voice with the lowest envelope level
+    SameNote, // Retrigger a voice already playing the note, otherwise steal the oldest
+}
+
+/// Note at which audio files play back at their original pitch (middle C).
+const SAMPLE_ROOT_NOTE: u8 = 60;
+
+/// Time a stolen voice takes to fade out, in seconds, so stealing does not click.
+const STEAL_FADE: f32 = 0.005;
+
+/// Converts a MIDI note number to a frequency in Hz (A4 = note 69 = 440 Hz).
+pub fn note_to_frequency(note: u8) -> f32 {
+    440.0 * 2f32.powf((note as f32 - 69.0) / 12.0)
+}
+
+/// A single sounding note.
+#[derive(Clone, Copy, Debug)]
+pub struct Voice {
+    pub note: u8,        // MIDI note number
+    pub velocity: f32,   // Note velocity, from 0.0 to 1.0
+    frequency: f32,      // Pitch of the note in Hz
+    oscillator: Oscillator,
+    fm: FmVoice,
+    envelope: EnvelopeGenerator,
+    sample_time: f64,    // Playback position in seconds when the source is an audio file
+    started: u64,        // Note-on order, used to find the oldest voice
+    fade: f32,           // Gain of a stolen voice on its way out; 1.0 otherwise
+}
+
+impl Voice {
+    fn new(note: u8, velocity: f32, started: u64) -> Self {
+        let mut voice = Voice {
+            note,
+            velocity,
+            frequency: note_to_frequency(note),
+            oscillator: Oscillator::with_seed(started as u32), // Each note gets its own noise sequence
+            fm: FmVoice::new(),
+            envelope: EnvelopeGenerator::new(),
+            sample_time: 0.0,
+            started,
+            fade: 1.0,
+        };
+        voice.envelope.note_on();
+        voice.fm.note_on();
+        voice
+    }
+
+    /// Returns `true` while the note is held.
+    pub fn is_held(&self) -> bool {
+        self.envelope.is_gate_open()
+    }
+
+    /// Returns `true` until the release has finished.
+    pub fn is_active(&self) -> bool {
+        self.envelope.is_active()
+    }
+
+    /// Renders the next sample of the note, shaped by its envelope and velocity.
+    fn next_sample(&mut self, source: &SoundSource, waveform: Waveform, envelope: &Envelope, sample_rate: f32, wavetables: &[Wavetable]) -> f32 {
+        let level = self.envelope.next_level(envelope, sample_rate) * self.velocity;
+        let raw_sample = match source {
+            SoundSource::Oscillator => self.oscillator.next_sample(waveform, self.frequency, sample_rate, wavetables),
+            SoundSource::Fm(patch) => self.fm.next_sample(patch, self.frequency, sample_rate),
+            SoundSource::Sample(region) => {
+                let increment = (self.frequency / note_to_frequency(SAMPLE_ROOT_NOTE)) as f64 / sample_rate as f64; // Pitched relative to the root
+                let sample = region.sample_at(self.sample_time, increment);
+                self.sample_time += increment;
+                sample
+            }
+        };
+        raw_sample * level
+    }
+}
+
+/// Pool of voices with note-on/note-off handling and voice stealing.
+pub struct VoiceAllocator {
+    voices: Vec<Voice>,           // Voices that have been allocated so far
+    stolen: Vec<Voice>,           // Voices taken over by newer notes, fading out
+    polyphony: usize,             // Maximum number of simultaneous voices
+    pub stealing: VoiceStealing,  // How to free a voice when the pool is full
+    note_counter: u64,            // Increments on every note-on
+}
+
+impl VoiceAllocator {
+    /// Creates an empty pool.
+    ///
+    /// # Parameters
+    /// - `polyphony`: The maximum number of simultaneous voices (at least one).
+    pub fn new(polyphony: usize) -> Self {
+        VoiceAllocator {
+            voices: Vec::with_capacity(polyphony.max(1)),
+            stolen: Vec::with_capacity(polyphony.max(1)),
+            polyphony: polyphony.max(1),
+            stealing: VoiceStealing::Oldest,
+            note_counter: 0,
+        }
+    }
+
+    /// Returns the polyphony limit.
+    pub fn polyphony(&self) -> usize {
+        self.polyphony
+    }
+
+    /// Changes the polyphony limit. If the pool shrinks, finished voices are dropped first,
+    /// then sounding voices fade out in the order the stealing policy would take them.
+    pub fn set_polyphony(&mut self, polyphony: usize) {
+        self.polyphony = polyphony.max(1);
+        self.voices.retain(|voice| voice.is_active());
+        while self.voices.len() > self.polyphony {
+            let Some(index) = self.steal_index() else {
+                break;
+            };
+            let stolen = self.voices.remove(index);
+            self.stolen.push(stolen); // Keeps sounding until its fade ends
+        }
+    }
+
+    /// Iterates over the voices of the pool that are still sounding. Stolen voices fading out
+    /// are not included.
+    pub fn active_voices(&self) -> impl Iterator<Item = &Voice> {
+        self.voices.iter().filter(|voice| voice.is_active())
+    }
+
+    /// Returns `true` if a voice is holding the given note.
+    pub fn is_note_held(&self, note: u8) -> bool {
+        self.voices.iter().any(|voice| voice.note == note && voice.is_held())
+    }
+
+    /// Starts a note, stealing a voice if the pool is full.
+    ///
+    /// # Parameters
+    /// - `note`: The MIDI note number.
+    /// - `velocity`: The note velocity, from 0.0 to 1.0.
+    pub fn note_on(&mut self, note: u8, velocity: f32) {
+        self.note_counter += 1;
+        let velocity = velocity.clamp(0.0, 1.0);
+
+        if self.stealing == VoiceStealing::SameNote {
+            if let Some(voice) = self.voices.iter_mut().find(|voice| voice.note == note && voice.is_active()) {
+                voice.velocity = velocity;
+                voice.started = self.note_counter;
+                voice.envelope.note_on(); // Retrigger from the current level without resetting the phase
+                voice.fm.note_on();
+                voice.sample_time = 0.0; // Audio files play again from the start
+                return;
+            }
+        }
+
+        let new_voice = Voice::new(note, velocity, self.note_counter);
+        if let Some(free) = self.voices.iter_mut().find(|voice| !voice.is_active()) {
+            *free = new_voice; // Reuse a finished voice
+        } else if self.voices.len() < self.polyphony {
+            self.voices.push(new_voice);
+        } else if let Some(index) = self.steal_index() {
+            let stolen = std::mem::replace(&mut self.voices[index], new_voice);
+            self.stolen.push(stolen); // Keeps sounding until its fade ends
+        }
+    }
+
+    /// Releases every voice holding the given note.
+    pub fn note_off(&mut self, note: u8) {
+        for voice in self.voices.iter_mut().filter(|voice| voice.note == note) {
+            voice.envelope.note_off();
+            voice.fm.note_off();
+        }
+    }
+
+    /// Releases every held voice.
+    pub fn all_notes_off(&mut self) {
+        for voice in &mut self.voices {
+            voice.envelope.note_off();
+            voice.fm.note_off();
+        }
+    }
+
+    /// Picks the voice to take over when the pool is full.
+    fn steal_index(&self) -> Option<usize> {
+        let oldest = || {
+            self.voices.iter().enumerate().min_by_key(|(_, voice)| voice.started).map(|(index, _)| index)
+        };
+        match self.stealing {
+            VoiceStealing::Oldest | VoiceStealing::SameNote => oldest(),
+            VoiceStealing::Quietest => self
+                .voices
+                .iter()
+                .enumerate()
+                .min_by(|(_, a), (_, b)| {
+                    (a.envelope.level() * a.velocity).total_cmp(&(b.envelope.level() * b.velocity))
+                })
+                .map(|(index, _)| index),
+        }
+    }
+
+    /// Renders and sums the next sample of every active voice.
+    ///
+    /// # Parameters
+    /// - `source`: The sound generator shared by all voices.
+    /// - `waveform`: The waveform used by the oscillator source.
+    /// - `envelope`: The amplitude envelope shared by all voices.
+    /// - `sample_rate`: The sample rate in Hz.
+    /// - `wavetables`: The loaded tables used by `Waveform::Wavetable`.
+    ///
+    /// # Returns
+    /// - The summed voice output, before the synthesizer amplitude is applied.
+    pub fn next_sample(
+        &mut self,
+        source: &SoundSource,
+        waveform: Waveform,
+        envelope: &Envelope,
+        sample_rate: f32,
+        wavetables: &[Wavetable],
+    ) -> f32 {
+        let envelope = Envelope { release: envelope.release.max(source.release()), ..*envelope }; // Let FM tails ring out
+        let mut sample = 0.0;
+        for voice in self.voices.iter_mut().filter(|voice| voice.is_active()) {
+            sample += voice.next_sample(source, waveform, &envelope, sample_rate, wavetables);
+        }
+        let fade_step = 1.0 / (STEAL_FADE * sample_rate);
+        for voice in &mut self.stolen {
+            sample += voice.next_sample(source, waveform, &envelope, sample_rate, wavetables) * voice.fade;
+            voice.fade -= fade_step;
+        }
+        self.stolen.retain(|voice| voice.fade > 0.0);
+        sample
+    }
+}
@@ -0,0 +1,132 @@
+use rustfft::{FftPlanner, num_complex::Complex};
+
+/// Number of samples in every rendered table.
+const TABLE_SIZE: usize = 2048;
+
+/// Number of band-limited copies of each frame. Level `n` keeps `TABLE_SIZE / 2 >> n` harmonics.
+const MIP_LEVELS: usize = 11;
+
+/// Frame length used by common wavetable packs when a WAV file holds several frames.
+pub const DEFAULT_FRAME_SIZE: usize = 2048;
+
+/// Multi-frame wavetable with mip-mapped, band-limited copies of each frame.
+///
+/// Each frame is stored once per mip level with the harmonics that would alias at higher
+/// pitches removed, and playback picks the level that fits below Nyquist for the note.
+pub struct Wavetable {
+    pub name: String,           // File the table was loaded from, used to reload it with the project
+    frames: Vec<Vec<Vec<f32>>>, // Indexed by frame, then mip level, then sample
+}
+
+impl Wavetable {
+    /// Builds a wavetable from single-cycle frames.
+    ///
+    /// # Parameters
+    /// - `name`: A name for the table, usually the file it came from.
+    /// - `frames`: One or more single-cycle waveforms. They may have any length.
+    pub fn from_frames(name: &str, frames: &[Vec<f32>]) -> Self {
+        let mut planner = FftPlanner::new();
+        let frames = frames
+            .iter()
+            .filter(|frame| frame.len() >= 2)
+            .map(|frame| build_mip_levels(frame, &mut planner))
+            .collect();
+        Wavetable { name: name.to_string(), frames }
+    }
+
+    /// Loads a wavetable from a WAV file.
+    ///
+    /// A file shorter than `frame_size` is treated as a single cycle; longer files are split
+    /// into consecutive frames of `frame_size` samples. Multi-channel files use the first channel.
+    ///
+    /// # Parameters
+    /// - `filename`: The WAV file to read.
+    /// - `frame_size`: The number of samples per frame.
+    pub fn from_wav(filename: &str, frame_size: usize) -> Result<Self, hound::Error> {
+        let mut reader = hound::WavReader::open(filename)?;
+        let spec = reader.spec();
+        let channels = spec.channels.max(1) as usize;
+        let samples: Vec<f32> = match spec.sample_format {
+            hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+            hound::SampleFormat::Int => {
+                let scale = (1i64 << (spec.bits_per_sample - 1)) as f32; // Full scale for the bit depth
+                reader.samples::<i32>().map(|s| s.map(|s| s as f32 / scale)).collect::<Result<_, _>>()?
+            }
+        };
+        let mono: Vec<f32> = samples.iter().step_by(channels).copied().collect();
+        if mono.len() < 2 {
+            return Err(hound::Error::FormatError("wavetable file has no audio"));
+        }
+
+        let frames: Vec<Vec<f32>> = if mono.len() < frame_size {
+            vec![mono] // A single cycle of any length
+        } else {
+            mono.chunks_exact(frame_size).map(|frame| frame.to_vec()).collect()
+        };
+        Ok(Self::from_frames(filename, &frames))
+    }
+
+    /// Returns the number of frames in the table.
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Reads the table at a phase, morphing between frames and choosing the mip level for the pitch.
+    ///
+    /// # Parameters
+    /// - `phase`: The normalized phase in the range `0.0..1.0`.
+    /// - `position`: The position across the frames, from 0.0 (first) to 1.0 (last).
+    /// - `phase_increment`: The phase advance per sample (`frequency / sample_rate`).
+    ///
+    /// # Returns
+    /// - The interpolated sample.
+    pub fn sample(&self, phase: f32, position: f32, phase_increment: f32) -> f32 {
+        if self.frames.is_empty() {
+            return 0.0;
+        }
+        // Highest harmonic that stays below Nyquist at this pitch
+        let max_harmonic = 0.5 / phase_increment.abs().max(1e-9);
+        let level = ((TABLE_SIZE / 2) as f32 / max_harmonic).log2().ceil().clamp(0.0, (MIP_LEVELS - 1) as f32) as usize;
+
+        let frame_position = position.clamp(0.0, 1.0) * (self.frames.len() - 1) as f32;
+        let first = frame_position.floor() as usize;
+        let second = (first + 1).min(self.frames.len() - 1);
+        let morph = frame_position - first as f32;
+
+        let a = read_interpolated(&self.frames[first][level], phase);
+        let b = read_interpolated(&self.frames[second][level], phase);
+        a + (b - a) * morph
+    }
+}
+
+/// Reads a table with linear interpolation.
+fn read_interpolated(table: &[f32], phase: f32) -> f32 {
+    let position = phase.rem_euclid(1.0) * table.len() as f32;
+    let index = position as usize % table.len();
+    let next = (index + 1) % table.len();
+    let fraction = position - position.floor();
+    table[index] + (table[next] - table[index]) * fraction
+}
+
+/// Resynthesizes a single-cycle frame at `TABLE_SIZE` samples for every mip level.
+fn build_mip_levels(frame: &[f32], planner: &mut FftPlanner<f32>) -> Vec<Vec<f32>> {
+    let length = frame.len();
+    let mut spectrum: Vec<Complex<f32>> = frame.iter().map(|&s| Complex { re: s, im: 0.0 }).collect();
+    planner.plan_fft_forward(length).process(&mut spectrum);
+    let inverse = planner.plan_fft_inverse(TABLE_SIZE);
+    let available = (length / 2).saturating_sub(1).max(1); // Harmonics present in the source frame
+
+    (0..MIP_LEVELS)
+        .map(|level| {
+            let harmonics = ((TABLE_SIZE / 2) >> level).clamp(1, available);
+            let mut table = vec![Complex { re: 0.0, im: 0.0 }; TABLE_SIZE];
+            for harmonic in 1..=harmonics {
+                // DC is dropped; normalize by the source length so amplitudes carry over
+                table[harmonic] = spectrum[harmonic] / length as f32;
+                table[TABLE_SIZE - harmonic] = spectrum[length - harmonic] / length as f32;
+            }
+            inverse.process(&mut table);
+            table.iter().map(|c| c.re).collect()
+        })
+        .collect()
+}
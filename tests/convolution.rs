@@ -0,0 +1,153 @@
+use std::sync::Arc;
+use wave_crafter::convolution::{Convolution, PARTITION_SIZE};
+use wave_crafter::effects::{Effect, EffectChain, EffectContext, EffectKind, EffectSlot};
+use wave_crafter::noise::Rng;
+use wave_crafter::sample::AudioBuffer;
+use wave_crafter::synthesizer::{Synthesizer, Waveform};
+
+const SAMPLE_RATE: f32 = 44100.0;
+
+/// Decaying stereo noise, like a small room, with a different response on each side.
+fn impulse_response(length: usize) -> AudioBuffer {
+    let mut rng = Rng::new(7);
+    let channels = (0..2)
+        .map(|_| (0..length).map(|i| rng.next_bipolar() * (-(i as f32) / 300.0).exp()).collect())
+        .collect();
+    AudioBuffer { sample_rate: SAMPLE_RATE as u32, channels }
+}
+
+fn direct_convolution(input: &[f32], impulse: &[f32], scale: f32) -> Vec<f32> {
+    (0..input.len())
+        .map(|n| (0..impulse.len().min(n + 1)).map(|k| input[n - k] * impulse[k]).sum::<f32>() * scale)
+        .collect()
+}
+
+/// Runs `process` over the input in blocks of varying length, as an audio callback would.
+fn process_in_blocks(input: &[f32], mut process: impl FnMut(&mut [f32], &mut [f32])) -> (Vec<f32>, Vec<f32>) {
+    let (mut left, mut right) = (input.to_vec(), input.to_vec());
+    let block_sizes = [1, 17, 300, 64, 511, 256, 3];
+    let mut start = 0;
+    for size in block_sizes.iter().cycle() {
+        if start >= input.len() {
+            break;
+        }
+        let end = (start + size).min(input.len());
+        process(&mut left[start..end], &mut right[start..end]);
+        start = end;
+    }
+    (left, right)
+}
+
+fn test_input(length: usize) -> Vec<f32> {
+    let mut rng = Rng::new(3);
+    (0..length).map(|_| rng.next_bipolar()).collect()
+}
+
+#[test]
+fn matches_direct_convolution() {
+    let impulse = impulse_response(1000); // Spans several partitions, the last one partial
+    let energy = impulse.channels.iter().flatten().map(|s| s * s).sum::<f32>() / 2.0;
+    let scale = 1.0 / energy.sqrt();
+    let input = test_input(6000);
+    let expected: Vec<Vec<f32>> = impulse.channels.iter().map(|ir| direct_convolution(&input, ir, scale)).collect();
+
+    let mut convolution = Convolution::new("room.wav", Arc::new(impulse));
+    convolution.mix = 1.0;
+    convolution.prepare(SAMPLE_RATE);
+    assert_eq!(convolution.latency(), PARTITION_SIZE);
+    let context = EffectContext { sample_rate: SAMPLE_RATE, tempo: 120.0 };
+    let (left, right) = process_in_blocks(&input, |left, right| convolution.process(left, right, &context));
+
+    for (channel, output) in [left, right].iter().enumerate() {
+        assert!(output[..PARTITION_SIZE].iter().all(|&s| s == 0.0), "output should start after the latency");
+        for n in PARTITION_SIZE..input.len() {
+            let wanted = expected[channel][n - PARTITION_SIZE];
+            assert!((output[n] - wanted).abs() < 1e-3, "channel {} sample {}: {} vs {}", channel, n, output[n], wanted);
+        }
+    }
+}
+
+#[test]
+fn slot_mix_lines_up_dry_and_wet() {
+    let impulse = impulse_response(600);
+    let energy = impulse.channels.iter().flatten().map(|s| s * s).sum::<f32>() / 2.0;
+    let wet = direct_convolution(&[1.0], &impulse.channels[0], 1.0 / energy.sqrt());
+    let mut input = vec![0.0; 2000];
+    input[0] = 1.0;
+
+    let mut convolution = Convolution::new("room.wav", Arc::new(impulse));
+    convolution.mix = 1.0;
+    let mut chain = EffectChain::new();
+    chain.prepare(SAMPLE_RATE);
+    chain.add(EffectKind::Convolution(convolution));
+    chain.slots[0].mix = 0.5;
+    let context = EffectContext { sample_rate: SAMPLE_RATE, tempo: 120.0 };
+    let (left, _) = process_in_blocks(&input, |left, right| chain.process(left, right, &context));
+
+    // The dry impulse is delayed by the latency so it lands on the first sample of the wet signal
+    let expected_first = 0.5 + 0.5 * wet[0];
+    assert!((left[PARTITION_SIZE] - expected_first).abs() < 1e-4, "{} vs {}", left[PARTITION_SIZE], expected_first);
+    assert!(left[..PARTITION_SIZE].iter().all(|&s| s == 0.0));
+    assert_eq!(chain.latency(), PARTITION_SIZE);
+}
+
+#[test]
+fn unprepared_effect_passes_audio_through() {
+    let mut convolution = Convolution::new("room.wav", Arc::new(impulse_response(600)));
+    let context = EffectContext { sample_rate: SAMPLE_RATE, tempo: 120.0 };
+    let input = test_input(1000);
+    let (left, right) = process_in_blocks(&input, |left, right| convolution.process(left, right, &context));
+    assert_eq!(left, input, "processing does not allocate the engine itself");
+    assert_eq!(right, input);
+}
+
+/// Plays an impulse through a chain and returns the left output.
+fn impulse_through(chain: &mut EffectChain) -> Vec<f32> {
+    let mut input = vec![0.0; 1000];
+    input[0] = 1.0;
+    let context = EffectContext { sample_rate: SAMPLE_RATE, tempo: 120.0 };
+    process_in_blocks(&input, |left, right| chain.process(left, right, &context)).0
+}
+
+#[test]
+fn impulse_responses_reload_on_every_chain() {
+    let dir = tempfile::tempdir().unwrap();
+    let impulse_path = dir.path().join("room.wav");
+    let spec = hound::WavSpec { channels: 2, sample_rate: SAMPLE_RATE as u32, bits_per_sample: 32, sample_format: hound::SampleFormat::Float };
+    let mut writer = hound::WavWriter::create(&impulse_path, spec).unwrap();
+    let impulse = impulse_response(600);
+    for frame in 0..600 {
+        writer.write_sample(impulse.channels[0][frame]).unwrap();
+        writer.write_sample(impulse.channels[1][frame]).unwrap();
+    }
+    writer.finalize().unwrap();
+
+    let mut synth = Synthesizer::new(440.0, 0.0, Waveform::Sine);
+    synth.set_sample_rate(SAMPLE_RATE);
+    synth.master.slots.clear(); // Only the convolution, so the chains can be compared directly
+    synth.add_track("Keys");
+    let buffer = Arc::new(AudioBuffer::from_wav(impulse_path.to_str().unwrap()).unwrap());
+    for chain in [&mut synth.master, &mut synth.mixer.tracks[0].inserts] {
+        let mut convolution = Convolution::new(impulse_path.to_str().unwrap(), buffer.clone());
+        convolution.mix = 1.0;
+        chain.add(EffectKind::Convolution(convolution));
+    }
+    let expected = impulse_through(&mut synth.master.clone());
+    assert!(expected[PARTITION_SIZE..].iter().any(|&s| s.abs() > 1e-3), "the room is heard after the latency");
+
+    let project_path = dir.path().join("project.json");
+    synth.save_project(project_path.to_str().unwrap()).unwrap();
+    let mut loaded = Synthesizer::new(440.0, 0.0, Waveform::Sine);
+    loaded.set_sample_rate(SAMPLE_RATE);
+    loaded.load_project(project_path.to_str().unwrap()).unwrap();
+
+    assert!(matches!(loaded.master.slots[..], [EffectSlot { effect: EffectKind::Convolution(_), .. }]));
+    let mut track = loaded.mixer.tracks[0].inserts.clone();
+    for (name, chain) in [("master", &mut loaded.master), ("track insert", &mut track)] {
+        assert_eq!(chain.latency(), PARTITION_SIZE, "the {} convolution reports its latency", name);
+        let output = impulse_through(chain);
+        for (sample, wanted) in output.iter().zip(&expected) {
+            assert!((sample - wanted).abs() < 1e-4, "the {} convolution plays the reloaded room", name);
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use wave_crafter::distortion::{Bitcrusher, Oversampling, Saturation, SaturationMode, Waveshaper};
+use wave_crafter::effects::{Effect, EffectContext};
+
+const SAMPLE_RATE: f32 = 48000.0;
+
+fn context() -> EffectContext {
+    EffectContext { sample_rate: SAMPLE_RATE, tempo: 120.0 }
+}
+
+fn sine(frequency: f32, amplitude: f32, length: usize) -> Vec<f32> {
+    (0..length).map(|i| amplitude * (2.0 * std::f32::consts::PI * frequency * i as f32 / SAMPLE_RATE).sin()).collect()
+}
+
+/// Returns the amplitude of one frequency in a signal, with the Goertzel algorithm.
+fn amplitude_at(samples: &[f32], frequency: f32) -> f32 {
+    let coefficient = 2.0 * (2.0 * std::f64::consts::PI * frequency as f64 / SAMPLE_RATE as f64).cos();
+    let (mut previous, mut before) = (0.0f64, 0.0f64);
+    for &sample in samples {
+        (previous, before) = (sample as f64 + coefficient * previous - before, previous);
+    }
+    let power = previous * previous + before * before - coefficient * previous * before;
+    (2.0 * power.sqrt() / samples.len() as f64) as f32
+}
+
+fn process(effect: &mut impl Effect, input: &[f32]) -> Vec<f32> {
+    let (mut left, mut right) = (input.to_vec(), input.to_vec());
+    for (left, right) in left.chunks_mut(256).zip(right.chunks_mut(256)) {
+        effect.process(left, right, &context());
+    }
+    assert_eq!(left, right, "both channels get the same treatment");
+    left
+}
+
+#[test]
+fn saturation_curves_have_expected_shapes() {
+    for mode in SaturationMode::ALL {
+        assert_eq!(mode.shape(0.0), 0.0, "{:?} should pass silence", mode);
+        assert!((mode.shape(0.3) + mode.shape(-0.3)).abs() < 1e-6, "{:?} should be symmetric", mode);
+    }
+    assert!((SaturationMode::Tanh.shape(0.5) - 0.5f32.tanh()).abs() < 1e-6);
+    assert_eq!(SaturationMode::HardClip.shape(3.0), 1.0);
+    assert!((SaturationMode::SoftClip.shape(1.0) - 1.0).abs() < 1e-6);
+    assert_eq!(SaturationMode::SoftClip.shape(5.0), SaturationMode::SoftClip.shape(1.0), "flat past full level");
+    assert!((SaturationMode::Foldback.shape(1.5) - 0.5).abs() < 1e-6, "folds back down past full level");
+    assert!((SaturationMode::Foldback.shape(2.5) + 0.5).abs() < 1e-6);
+}
+
+#[test]
+fn waveshaper_interpolates_its_curve() {
+    let waveshaper = Waveshaper::new(vec![-1.0, 0.0, 0.5]);
+    assert_eq!(waveshaper.shape(-1.0), -1.0);
+    assert_eq!(waveshaper.shape(0.0), 0.0);
+    assert!((waveshaper.shape(0.5) - 0.25).abs() < 1e-6);
+    assert!((waveshaper.shape(-0.5) + 0.5).abs() < 1e-6);
+    assert_eq!(waveshaper.shape(4.0), 0.5, "inputs beyond the curve hold the end value");
+
+    let mut straight = Waveshaper::default();
+    straight.oversampling = Oversampling::Off;
+    let input = sine(440.0, 0.8, 1000);
+    let output = process(&mut straight, &input);
+    assert!(output.iter().zip(&input).all(|(a, b)| (a - b).abs() < 1e-6), "the default curve is a straight line");
+}
+
+#[test]
+fn oversampling_delays_by_reported_latency() {
+    for oversampling in [Oversampling::X2, Oversampling::X4] {
+        let mut saturation = Saturation::new(SaturationMode::Tanh, 0.0);
+        saturation.oversampling = oversampling;
+        let input = sine(200.0, 0.05, 4800); // Quiet enough that tanh is nearly linear
+        let output = process(&mut saturation, &input);
+        let latency = saturation.latency();
+        assert_eq!(latency, 16);
+        for i in 1000..4800 {
+            assert!(
+                (output[i] - input[i - latency].tanh()).abs() < 1e-3,
+                "{:?} sample {}: {} vs {}",
+                oversampling, i, output[i], input[i - latency]
+            );
+        }
+    }
+}
+
+#[test]
+fn oversampling_reduces_aliasing() {
+    // A clipped 5 kHz sine has a 9th harmonic at 45 kHz, which folds back to 3 kHz at 48 kHz
+    let input = sine(5000.0, 1.0, SAMPLE_RATE as usize);
+    let alias = |oversampling| {
+        let mut saturation = Saturation::new(SaturationMode::HardClip, 12.0);
+        saturation.oversampling = oversampling;
+        let output = process(&mut saturation, &input);
+        amplitude_at(&output[4800..], 3000.0)
+    };
+    let plain = alias(Oversampling::Off);
+    let doubled = alias(Oversampling::X2);
+    let quadrupled = alias(Oversampling::X4);
+    assert!(plain > 0.01, "the plain clipper should alias audibly, got {}", plain);
+    assert!(doubled < plain / 4.0, "2x should cut the alias, got {} vs {}", doubled, plain);
+    assert!(quadrupled < plain / 10.0, "4x should cut the alias further, got {} vs {}", quadrupled, plain);
+}
+
+#[test]
+fn bitcrusher_quantizes_and_holds() {
+    let mut bitcrusher = Bitcrusher::new(3.0, SAMPLE_RATE / 4.0); // Four levels each side of zero
+    assert_eq!(bitcrusher.quantize(0.3), 0.25);
+    assert_eq!(bitcrusher.quantize(-0.9), -1.0);
+
+    let input: Vec<f32> = (0..64).map(|i| i as f32 / 64.0).collect();
+    let output = process(&mut bitcrusher, &input);
+    for (i, chunk) in output.chunks(4).enumerate() {
+        assert!(chunk.iter().all(|&sample| sample == chunk[0]), "each sample is held for four frames");
+        assert_eq!(chunk[0], bitcrusher.quantize(input[i * 4]));
+    }
+}
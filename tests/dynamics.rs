@@ -0,0 +1,135 @@
+use wave_crafter::dynamics::{Compressor, Gate, Limiter, MAX_LOOKAHEAD_MS};
+use wave_crafter::effects::{db_to_gain, gain_to_db, Effect, EffectContext};
+use wave_crafter::noise::Rng;
+
+const CONTEXT: EffectContext = EffectContext { sample_rate: 44100.0, tempo: 120.0 };
+
+#[test]
+fn limiter_never_exceeds_the_ceiling() {
+    let mut limiter = Limiter::new(-1.0);
+    limiter.prepare(44100.0);
+    let mut rng = Rng::new(9);
+    let mut left: Vec<f32> = (0..44100).map(|i| rng.next_bipolar() * if i % 5000 < 100 { 4.0 } else { 0.5 }).collect();
+    let mut right: Vec<f32> = left.iter().map(|s| -s * 1.5).collect();
+    for (left, right) in left.chunks_mut(128).zip(right.chunks_mut(128)) {
+        limiter.process(left, right, &CONTEXT);
+    }
+    let ceiling = db_to_gain(-1.0);
+    let peak = left.iter().chain(&right).fold(0.0f32, |peak, s| peak.max(s.abs()));
+    assert!(peak <= ceiling + 1e-6, "peak {} over ceiling {}", peak, ceiling);
+}
+
+#[test]
+fn limiter_delays_by_its_lookahead_and_leaves_quiet_audio_alone() {
+    let mut limiter = Limiter::new(0.0);
+    limiter.prepare(44100.0);
+    let mut left = vec![0.0; 1000];
+    left[0] = 0.5;
+    let mut right = vec![0.0; 1000];
+    limiter.process(&mut left, &mut right, &CONTEXT);
+    let latency = limiter.latency();
+    assert_eq!(latency, (0.005 * 44100.0) as usize);
+    assert!((left[latency] - 0.5).abs() < 1e-6);
+    assert!(left.iter().enumerate().all(|(i, &s)| i == latency || s == 0.0));
+}
+
+#[test]
+fn compressor_settles_on_the_static_curve() {
+    let mut compressor = Compressor::default();
+    (compressor.threshold, compressor.ratio, compressor.knee, compressor.makeup) = (-20.0, 4.0, 0.0, 3.0);
+    let input = db_to_gain(-8.0); // 12 dB over the threshold
+    let mut left = vec![input; 44100];
+    let mut right = left.clone();
+    compressor.process(&mut left, &mut right, &CONTEXT);
+    let expected = -20.0 + 12.0 / 4.0 + 3.0;
+    assert!((gain_to_db(left[44099]) - expected).abs() < 0.05, "{} dB", gain_to_db(left[44099]));
+    assert!((compressor.gain_reduction() - 9.0).abs() < 0.05);
+}
+
+#[test]
+fn soft_knee_is_continuous() {
+    let mut compressor = Compressor::default();
+    (compressor.threshold, compressor.ratio, compressor.knee) = (-20.0, 4.0, 10.0);
+    assert_eq!(compressor.static_curve(-30.0), -30.0); // Below the knee
+    assert!((compressor.static_curve(-10.0) - (-17.5)).abs() < 1e-4); // Above the knee
+    for edge in [-25.0, -15.0] {
+        let step = (compressor.static_curve(edge + 0.001) - compressor.static_curve(edge - 0.001)).abs();
+        assert!(step < 0.01, "jump at {} dB", edge);
+    }
+}
+
+#[test]
+fn hard_knee_handles_levels_at_the_threshold() {
+    for (threshold, sample) in [(-20.0, 0.1), (0.0, 1.0)] {
+        let mut compressor = Compressor::default();
+        (compressor.threshold, compressor.knee) = (threshold, 0.0);
+        assert_eq!(compressor.static_curve(threshold), threshold, "a hard knee meets the ratio line at the threshold");
+        let mut left = vec![sample; 100];
+        let mut right = left.clone();
+        compressor.process(&mut left, &mut right, &CONTEXT);
+        assert!(left.iter().all(|&s| (s - sample).abs() < 1e-6), "{} dB at a {} dB threshold passes unchanged", gain_to_db(sample), threshold);
+        left.fill(0.5 * sample);
+        compressor.process(&mut left, &mut right, &CONTEXT);
+        assert!(left.iter().all(|s| s.is_finite()), "the level at the threshold leaves no NaN behind");
+    }
+}
+
+#[test]
+fn sidechain_key_drives_the_compressor() {
+    let mut compressor = Compressor::default();
+    (compressor.threshold, compressor.ratio, compressor.knee) = (-20.0, 10.0, 0.0);
+    let quiet = db_to_gain(-30.0); // Below the threshold on its own
+    let mut left = vec![quiet; 44100];
+    let mut right = left.clone();
+    let key = vec![1.0; 44100]; // A loud key 20 dB over the threshold
+    compressor.process_sidechain(&mut left, &mut right, &key, &key, &CONTEXT);
+    let reduction = gain_to_db(quiet) - gain_to_db(left[44099]);
+    assert!((reduction - 18.0).abs() < 0.1, "reduced by {} dB", reduction);
+
+    compressor.reset();
+    let mut left = vec![quiet; 44100];
+    let mut right = left.clone();
+    compressor.process_sidechain(&mut left, &mut right, &key[..100], &key[..100], &CONTEXT);
+    assert!((left[44099] - quiet).abs() < 1e-5, "past the end of a short key the compressor recovers");
+}
+
+#[test]
+fn gate_closes_below_the_threshold() {
+    let mut gate = Gate::default();
+    (gate.threshold, gate.range) = (-40.0, 60.0);
+    let sine = |amplitude: f32| -> Vec<f32> {
+        (0..44100).map(|i| amplitude * (2.0 * std::f32::consts::PI * 100.0 * i as f32 / 44100.0).sin()).collect()
+    };
+    let mut left = sine(0.5);
+    let mut right = left.clone();
+    gate.process(&mut left, &mut right, &CONTEXT);
+    let peak = left[22050..].iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+    assert!((peak - 0.5).abs() < 0.01, "a loud signal should pass, peak {}", peak);
+
+    let mut left = sine(0.001); // -60 dBFS
+    let mut right = left.clone();
+    gate.process(&mut left, &mut right, &CONTEXT);
+    let peak = left[39690..].iter().fold(0.0f32, |peak, s| peak.max(s.abs())); // After the release has settled
+    assert!(gain_to_db(peak) < -115.0, "a quiet signal should drop by the range, peak {} dB", gain_to_db(peak));
+}
+
+#[test]
+fn limiter_lookahead_changes_stay_within_the_prepared_buffers() {
+    let mut limiter = Limiter::new(0.0);
+    limiter.prepare(44100.0);
+    limiter.lookahead = 100.0; // Past the longest lookahead the buffers hold
+    let mut left = vec![0.0; 2000];
+    left[0] = 2.0;
+    let mut right = left.clone();
+    limiter.process(&mut left, &mut right, &CONTEXT);
+    let latency = limiter.latency();
+    assert_eq!(latency, (MAX_LOOKAHEAD_MS * 0.001 * 44100.0) as usize);
+    assert!((left[latency] - 1.0).abs() < 1e-6, "the peak is limited to the ceiling, {}", left[latency]);
+
+    limiter.reset();
+    let mut left = vec![0.0; 2000];
+    left[0] = 0.5;
+    let mut right = left.clone();
+    limiter.process(&mut left, &mut right, &CONTEXT);
+    assert!(left.iter().enumerate().all(|(i, &s)| if i == latency { (s - 0.5).abs() < 1e-6 } else { s == 0.0 }), "a reset clears the delay line");
+}
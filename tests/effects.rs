@@ -0,0 +1,242 @@
+use wave_crafter::effects::{AutoPan, Chorus, Delay, DelayTime, Effect, EffectChain, EffectContext, EffectKind, Flanger, Phaser, Reverb, Tremolo};
+use wave_crafter::modulation::LfoRate;
+
+const SAMPLE_RATE: f32 = 1000.0;
+
+/// Feeds a unit impulse into the left input and returns the left and right outputs.
+fn impulse_response(delay: &mut Delay, length: usize) -> (Vec<f32>, Vec<f32>) {
+    delay.prepare(SAMPLE_RATE);
+    (0..length)
+        .map(|i| {
+            let input = if i == 0 { 1.0 } else { 0.0 };
+            delay.process_frame(input, 0.0, SAMPLE_RATE, 120.0)
+        })
+        .unzip()
+}
+
+#[test]
+fn impulse_response_has_decaying_echoes() {
+    let mut delay = Delay::new(DelayTime::Milliseconds(100.0), 0.5, 1.0);
+    let (left, right) = impulse_response(&mut delay, 500);
+    for (i, sample) in left.iter().enumerate() {
+        let expected = match i {
+            100 => 1.0,
+            200 => 0.5,
+            300 => 0.25,
+            400 => 0.125,
+            _ => 0.0,
+        };
+        assert!((sample - expected).abs() < 1e-6, "sample {} was {}, expected {}", i, sample, expected);
+    }
+    assert!(right.iter().all(|&sample| sample == 0.0), "a silent right input should stay silent");
+}
+
+#[test]
+fn mix_balances_dry_and_wet() {
+    let mut delay = Delay::new(DelayTime::Milliseconds(100.0), 0.0, 0.25);
+    let (left, _) = impulse_response(&mut delay, 200);
+    assert!((left[0] - 0.75).abs() < 1e-6); // Dry impulse
+    assert!((left[100] - 0.25).abs() < 1e-6); // Single echo
+    assert!(left[150].abs() < 1e-6);
+}
+
+#[test]
+fn synced_time_follows_tempo() {
+    let mut delay = Delay::new(DelayTime::Synced(0.5), 0.0, 1.0); // An eighth note at 120 BPM is 250 ms
+    let (left, _) = impulse_response(&mut delay, 300);
+    assert!((left[250] - 1.0).abs() < 1e-6);
+}
+
+#[test]
+fn ping_pong_alternates_channels() {
+    let mut delay = Delay::new(DelayTime::Milliseconds(100.0), 0.5, 1.0);
+    delay.ping_pong = true;
+    let (left, right) = impulse_response(&mut delay, 400);
+    assert!((left[100] - 0.5).abs() < 1e-6 && right[100].abs() < 1e-6); // The input is centered before entering the left side
+    assert!(left[200].abs() < 1e-6 && (right[200] - 0.25).abs() < 1e-6);
+    assert!((left[300] - 0.125).abs() < 1e-6 && right[300].abs() < 1e-6);
+}
+
+#[test]
+fn time_changes_glide_without_jumps() {
+    let sample_rate = 44100.0;
+    let mut delay = Delay::new(DelayTime::Milliseconds(100.0), 0.0, 1.0);
+    delay.prepare(sample_rate);
+    let sine = |i: usize| (2.0 * std::f32::consts::PI * 220.0 * i as f32 / sample_rate).sin();
+    let mut previous = 0.0;
+    let mut largest_step: f32 = 0.0;
+    for i in 0..sample_rate as usize {
+        if i == 22050 {
+            delay.time = DelayTime::Milliseconds(300.0); // Jump the setting mid-stream
+        }
+        let (output, _) = delay.process_frame(sine(i), sine(i), sample_rate, 120.0);
+        if i > 4410 {
+            largest_step = largest_step.max((output - previous).abs());
+        }
+        previous = output;
+    }
+    let sine_step = 2.0 * std::f32::consts::PI * 220.0 / sample_rate; // Largest step of the dry sine
+    assert!(largest_step < sine_step * 1.5, "output jumped by {}", largest_step);
+}
+
+#[test]
+fn chain_slots_honor_bypass_and_mix() {
+    let context = EffectContext { sample_rate: SAMPLE_RATE, tempo: 120.0 };
+    let mut chain = EffectChain::new();
+    chain.prepare(SAMPLE_RATE);
+    chain.add(EffectKind::Delay(Delay::new(DelayTime::Milliseconds(10.0), 0.0, 1.0))); // Prepared at the chain's rate
+    let impulse = |length: usize| {
+        let mut left = vec![0.0; length];
+        left[0] = 1.0;
+        (left, vec![0.0; length])
+    };
+
+    chain.slots[0].bypass = true;
+    let (mut left, mut right) = impulse(20);
+    chain.process(&mut left, &mut right, &context);
+    assert_eq!(left[0], 1.0); // Untouched
+    assert_eq!(left[10], 0.0);
+
+    chain.slots[0].bypass = false;
+    chain.slots[0].mix = 0.5;
+    chain.reset();
+    let (mut left, mut right) = impulse(20);
+    chain.process(&mut left, &mut right, &context);
+    assert!((left[0] - 0.5).abs() < 1e-6); // Half the dry impulse
+    assert!((left[10] - 0.5).abs() < 1e-6); // Half the echo
+}
+
+#[test]
+fn chain_can_be_reordered() {
+    let mut chain = EffectChain::default();
+    assert_eq!(chain.slots[0].effect.name(), "Delay");
+    chain.move_slot(0, 1);
+    assert_eq!(chain.slots[0].effect.name(), "Reverb");
+    assert_eq!(chain.slots[1].effect.name(), "Delay");
+    chain.remove(0);
+    assert_eq!(chain.slots.len(), 1);
+}
+
+#[test]
+fn chorus_without_depth_is_a_fixed_delay() {
+    let mut chorus = Chorus::default();
+    (chorus.depth, chorus.feedback, chorus.mix) = (0.0, 0.0, 1.0);
+    chorus.prepare(SAMPLE_RATE);
+    let mut left = vec![0.0; 50];
+    let mut right = vec![0.0; 50];
+    left[0] = 1.0;
+    chorus.process(&mut left, &mut right, &EffectContext { sample_rate: SAMPLE_RATE, tempo: 120.0 });
+    assert!((left[12] - 1.0).abs() < 1e-6, "the 12 ms base delay should hold the impulse"); // 1 sample per ms
+    assert!(left.iter().enumerate().all(|(i, &sample)| i == 12 || sample.abs() < 1e-6));
+}
+
+#[test]
+fn flanger_stays_stable_at_full_feedback() {
+    let mut flanger = Flanger::default();
+    (flanger.rate, flanger.depth, flanger.feedback) = (LfoRate::Hertz(2.0), 1.0, 1.0);
+    let context = EffectContext { sample_rate: 48000.0, tempo: 120.0 };
+    flanger.prepare(context.sample_rate);
+    let mut left: Vec<f32> = (0..48000).map(|i| (i as f32 * 0.37).sin()).collect();
+    let mut right = left.clone();
+    flanger.process(&mut left, &mut right, &context);
+    assert!(left.iter().chain(&right).all(|sample| sample.is_finite() && sample.abs() < 20.0), "feedback is clamped below 1");
+}
+
+#[test]
+fn phaser_stages_keep_the_level_of_the_wet_signal() {
+    let mut phaser = Phaser::default();
+    (phaser.feedback, phaser.mix, phaser.stages) = (0.0, 1.0, 8);
+    let context = EffectContext { sample_rate: 48000.0, tempo: 120.0 };
+    let input: Vec<f32> = (0..48000).map(|i| (2.0 * std::f32::consts::PI * 700.0 * i as f32 / 48000.0).sin()).collect();
+    let (mut left, mut right) = (input.clone(), input.clone());
+    phaser.process(&mut left, &mut right, &context);
+    let power = |samples: &[f32]| samples[24000..].iter().map(|s| s * s).sum::<f32>();
+    let ratio = power(&left) / power(&input);
+    assert!((ratio - 1.0).abs() < 0.02, "all-pass stages only shift the phase, power ratio was {}", ratio);
+
+    // Mixed with the dry signal, the copy shifted by half a cycle cancels, leaving a notch
+    let mut notched = Phaser::default();
+    (notched.depth, notched.feedback, notched.stages) = (0.0, 0.0, 2);
+    let input: Vec<f32> = (0..48000).map(|i| (2.0 * std::f32::consts::PI * 200.0 * i as f32 / 48000.0).sin()).collect();
+    let (mut left, mut right) = (input.clone(), input.clone());
+    notched.process(&mut left, &mut right, &context);
+    let attenuation = power(&left) / power(&input);
+    assert!(attenuation < 0.01, "two stages cancel at their 200 Hz corner, power ratio was {}", attenuation);
+}
+
+#[test]
+fn tremolo_dips_by_its_depth() {
+    let mut tremolo = Tremolo::default();
+    (tremolo.rate, tremolo.depth) = (LfoRate::Hertz(10.0), 0.6);
+    let mut left = vec![1.0; 1000];
+    let mut right = vec![1.0; 1000];
+    tremolo.process(&mut left, &mut right, &EffectContext { sample_rate: SAMPLE_RATE, tempo: 120.0 });
+    let lowest = left.iter().copied().fold(f32::MAX, f32::min);
+    let highest = left.iter().copied().fold(f32::MIN, f32::max);
+    assert!((lowest - 0.4).abs() < 1e-3 && (highest - 1.0).abs() < 1e-3, "range was {}..{}", lowest, highest);
+    assert_eq!(left, right);
+}
+
+#[test]
+fn auto_pan_keeps_constant_power() {
+    let mut auto_pan = AutoPan::default();
+    auto_pan.rate = LfoRate::Hertz(1.0);
+    let mut left = vec![1.0; 1000];
+    let mut right = vec![1.0; 1000];
+    auto_pan.process(&mut left, &mut right, &EffectContext { sample_rate: SAMPLE_RATE, tempo: 120.0 });
+    for (l, r) in left.iter().zip(&right) {
+        assert!((l * l + r * r - 2.0).abs() < 1e-4);
+    }
+    assert!((left[0] - 1.0).abs() < 1e-6 && (right[0] - 1.0).abs() < 1e-6, "starts in the centre");
+    assert!(left[250].abs() < 1e-3, "a quarter cycle later the sound is hard right");
+}
+
+#[test]
+fn reverb_tail_decays_and_follows_its_settings() {
+    let context = EffectContext { sample_rate: 44100.0, tempo: 120.0 };
+    let impulse = |reverb: &mut Reverb, seconds: f32| {
+        let length = (seconds * context.sample_rate) as usize;
+        let mut left = vec![0.0; length];
+        left[0] = 1.0;
+        let mut right = vec![0.0; length];
+        reverb.process(&mut left, &mut right, &context);
+        (left, right)
+    };
+    let energy = |samples: &[f32], from: f32, to: f32| {
+        samples[(from * 44100.0) as usize..(to * 44100.0) as usize].iter().map(|s| s * s).sum::<f32>()
+    };
+
+    let mut reverb = Reverb::new(0.5, 0.5, 1.0);
+    let (left, _) = impulse(&mut reverb, 0.1);
+    assert!(left[0] == 1.0 && left[1..].iter().all(|&sample| sample == 0.0), "an unprepared reverb passes audio through");
+    reverb.prepare(context.sample_rate);
+    let (left, right) = impulse(&mut reverb, 3.0);
+    let early = energy(&left, 0.1, 0.6);
+    assert!(early > 1e-4 && energy(&right, 0.1, 0.6) > 1e-4, "the impulse rings on both sides");
+    assert!(energy(&left, 1.5, 2.0) < early * 0.1 && energy(&left, 2.5, 3.0) < energy(&left, 1.5, 2.0), "the tail dies away");
+    let start = left.iter().position(|sample| sample.abs() > 1e-9).unwrap();
+
+    let mut largest = Reverb::new(1.0, 0.0, 1.0);
+    largest.prepare(context.sample_rate);
+    let (left, right) = impulse(&mut largest, 5.0);
+    assert!(left.iter().chain(&right).all(|sample| sample.is_finite() && sample.abs() < 1.0), "the largest room stays stable");
+
+    let mut delayed = Reverb::new(0.5, 0.5, 1.0);
+    delayed.pre_delay = 100.0;
+    delayed.prepare(context.sample_rate);
+    let (left, _) = impulse(&mut delayed, 0.5);
+    assert_eq!(left.iter().position(|sample| sample.abs() > 1e-9).unwrap(), start + 4410, "pre-delay holds the tail back");
+
+    reverb.mix = 0.0;
+    assert_eq!(impulse(&mut reverb, 0.1).0[0], 1.0);
+    reverb.mix = 0.5;
+    reverb.reset();
+    let (left, _) = impulse(&mut reverb, 0.5);
+    assert_eq!(left[0], 0.5, "half the dry impulse");
+    assert!(energy(&left, 0.1, 0.5) > 0.0);
+
+    reverb.reset();
+    let (mut left, mut right) = (vec![0.0; 4410], vec![0.0; 4410]);
+    reverb.process(&mut left, &mut right, &context);
+    assert!(left.iter().chain(&right).all(|&sample| sample == 0.0), "reset silences the tail");
+}
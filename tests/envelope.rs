@@ -0,0 +1,83 @@
+use wave_crafter::envelope::{Envelope, EnvelopeCurve, EnvelopeGenerator};
+
+const SAMPLE_RATE: f32 = 1000.0; // One sample per millisecond keeps the stage times easy to count
+
+fn envelope(curve: EnvelopeCurve) -> Envelope {
+    Envelope { attack: 0.01, decay: 0.02, sustain: 0.5, release: 0.04, curve }
+}
+
+/// Runs the generator for a number of samples and returns the levels.
+fn run(generator: &mut EnvelopeGenerator, envelope: &Envelope, samples: usize) -> Vec<f32> {
+    (0..samples).map(|_| generator.next_level(envelope, SAMPLE_RATE)).collect()
+}
+
+#[test]
+fn stages_take_their_set_times() {
+    let envelope = envelope(EnvelopeCurve::Linear);
+    let mut generator = EnvelopeGenerator::new();
+    generator.note_on();
+
+    let attack = run(&mut generator, &envelope, 10);
+    assert!((attack[4] - 0.5).abs() < 1e-4, "halfway through the attack, got {}", attack[4]);
+    assert!((attack[9] - 1.0).abs() < 1e-4, "the attack peaks after 10 ms");
+    assert!(attack.windows(2).all(|pair| pair[1] > pair[0]));
+
+    let decay = run(&mut generator, &envelope, 20);
+    assert!((decay[9] - 0.75).abs() < 1e-3, "halfway through the decay, got {}", decay[9]);
+    assert!((decay[19] - 0.5).abs() < 1e-4, "the decay reaches sustain after 20 ms");
+    assert!(run(&mut generator, &envelope, 100).iter().all(|&level| level == 0.5), "sustain holds while the gate is open");
+    assert!(generator.is_gate_open());
+
+    generator.note_off();
+    let release = run(&mut generator, &envelope, 41); // Summed sample periods may land one sample late
+    assert!((release[19] - 0.25).abs() < 1e-3, "halfway through the release, got {}", release[19]);
+    assert!(release[39] < 1e-3);
+    assert_eq!(release[40], 0.0);
+    assert!(!generator.is_active(), "the generator goes idle after the release");
+}
+
+#[test]
+fn release_from_mid_attack_starts_at_the_current_level() {
+    for curve in [EnvelopeCurve::Linear, EnvelopeCurve::Exponential] {
+        let envelope = envelope(curve);
+        let mut generator = EnvelopeGenerator::new();
+        generator.note_on();
+        let attack = run(&mut generator, &envelope, 4);
+        let reached = attack[3];
+        assert!(reached > 0.0 && reached < 1.0, "{:?} is still in the attack", curve);
+
+        generator.note_off();
+        let release = run(&mut generator, &envelope, 41);
+        assert!(release[0] <= reached && release[0] > 0.85 * reached, "{:?} releases without a jump", curve);
+        assert!(release.windows(2).all(|pair| pair[1] <= pair[0]), "{:?} never rises during the release", curve);
+        assert!(!generator.is_active());
+
+        generator.note_on(); // Retrigger from silence
+        assert!(run(&mut generator, &envelope, 1)[0] < 0.5);
+    }
+}
+
+#[test]
+fn clip_envelopes_match_the_real_time_generator() {
+    for curve in [EnvelopeCurve::Linear, EnvelopeCurve::Exponential] {
+        let envelope = envelope(curve);
+        let mut generator = EnvelopeGenerator::new();
+        generator.note_on();
+        let gate = 0.05;
+        for sample in 0..100 {
+            if sample == 50 {
+                generator.note_off();
+            }
+            let level = generator.next_level(&envelope, SAMPLE_RATE);
+            let expected = envelope.level_at((sample + 1) as f32 / SAMPLE_RATE, gate);
+            assert!((level - expected).abs() < 0.02, "{:?} sample {}: {} against {}", curve, sample, level, expected);
+        }
+    }
+    let envelope = envelope(EnvelopeCurve::Linear);
+    assert_eq!(envelope.level_at(-0.1, 1.0), 0.0);
+    let gate = 0.005; // Closes halfway through the 10 ms attack
+    assert!(
+        (envelope.level_at(0.005, gate) - 0.5).abs() < 1e-4,
+        "the linear attack is halfway up when the gate closes, so the release starts from 0.5"
+    );
+}
@@ -0,0 +1,111 @@
+use wave_crafter::effects::{Effect, EffectContext};
+use wave_crafter::eq::{BandType, EqBand, Equalizer};
+
+const SAMPLE_RATE: f32 = 48000.0;
+
+fn to_db(gain: f32) -> f32 {
+    20.0 * gain.max(1e-9).log10()
+}
+
+/// Evaluates the analog prototype of a band at the bilinear-warped frequency, which is what
+/// the cookbook biquads match exactly.
+fn analytic_gain(band: &EqBand, frequency: f32) -> f32 {
+    let warp = |f: f32| (std::f64::consts::PI * f as f64 / SAMPLE_RATE as f64).tan();
+    let w = warp(frequency) / warp(band.frequency); // s = jw
+    let a = 10f64.powf(band.gain as f64 / 40.0);
+    let q = band.q as f64;
+    // |c0 + c1 s + c2 s²| with s = jw
+    let polynomial = |c0: f64, c1: f64, c2: f64| (c0 - c2 * w * w).hypot(c1 * w);
+    let gain = match band.band_type {
+        BandType::LowPass => polynomial(1.0, 0.0, 0.0) / polynomial(1.0, 1.0 / q, 1.0),
+        BandType::HighPass => polynomial(0.0, 0.0, 1.0) / polynomial(1.0, 1.0 / q, 1.0),
+        BandType::Peaking => polynomial(1.0, a / q, 1.0) / polynomial(1.0, 1.0 / (a * q), 1.0),
+        BandType::LowShelf => a * polynomial(a, a.sqrt() / q, 1.0) / polynomial(1.0, a.sqrt() / q, a),
+        BandType::HighShelf => a * polynomial(1.0, a.sqrt() / q, a) / polynomial(a, a.sqrt() / q, 1.0),
+    };
+    gain as f32
+}
+
+/// Measures the steady-state gain of an equalizer for a sine at `frequency`.
+fn measured_gain(equalizer: &mut Equalizer, frequency: f32) -> f32 {
+    let length = SAMPLE_RATE as usize / 2;
+    let mut left: Vec<f32> = (0..length)
+        .map(|i| (2.0 * std::f32::consts::PI * frequency * i as f32 / SAMPLE_RATE).sin())
+        .collect();
+    let mut right = left.clone();
+    let input = left.clone();
+    let context = EffectContext { sample_rate: SAMPLE_RATE, tempo: 120.0 };
+    for (left, right) in left.chunks_mut(256).zip(right.chunks_mut(256)) {
+        equalizer.process(left, right, &context);
+    }
+    let settle = length / 2; // Skip the transient
+    let power = |samples: &[f32]| samples[settle..].iter().map(|&s| (s as f64).powi(2)).sum::<f64>();
+    (power(&left) / power(&input)).sqrt() as f32
+}
+
+#[test]
+fn curve_matches_analytic_response() {
+    for band_type in BandType::ALL {
+        for (gain, q) in [(-12.0, 0.5), (6.0, 0.707), (18.0, 4.0)] {
+            let band = EqBand::new(band_type, 1000.0, gain, q);
+            let equalizer = Equalizer::new(vec![band]);
+            for frequency in [30.0, 200.0, 700.0, 1000.0, 1500.0, 5000.0, 15000.0] {
+                let expected = to_db(analytic_gain(&band, frequency));
+                let actual = to_db(equalizer.magnitude_response(frequency, SAMPLE_RATE));
+                assert!(
+                    (actual - expected).abs() < 0.01,
+                    "{:?} gain {} Q {} at {} Hz: curve {:.3} dB, analytic {:.3} dB",
+                    band_type, gain, q, frequency, actual, expected
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn bands_reach_their_settings() {
+    let peak = Equalizer::new(vec![EqBand::new(BandType::Peaking, 2000.0, 9.0, 2.0)]);
+    assert!((to_db(peak.magnitude_response(2000.0, SAMPLE_RATE)) - 9.0).abs() < 0.01);
+
+    let low_shelf = Equalizer::new(vec![EqBand::new(BandType::LowShelf, 200.0, -6.0, 0.707)]);
+    assert!((to_db(low_shelf.magnitude_response(5.0, SAMPLE_RATE)) + 6.0).abs() < 0.05);
+    assert!(to_db(low_shelf.magnitude_response(10000.0, SAMPLE_RATE)).abs() < 0.05);
+
+    let high_shelf = Equalizer::new(vec![EqBand::new(BandType::HighShelf, 4000.0, 4.0, 0.707)]);
+    assert!((to_db(high_shelf.magnitude_response(23900.0, SAMPLE_RATE)) - 4.0).abs() < 0.05);
+    assert!(to_db(high_shelf.magnitude_response(50.0, SAMPLE_RATE)).abs() < 0.05);
+
+    let low_pass = Equalizer::new(vec![EqBand::new(BandType::LowPass, 1000.0, 0.0, 3.0)]);
+    assert!((low_pass.magnitude_response(1000.0, SAMPLE_RATE) - 3.0).abs() < 0.01); // Gain at cutoff equals Q
+}
+
+#[test]
+fn disabled_and_flat_bands_pass_audio_unchanged() {
+    let mut disabled = EqBand::new(BandType::HighPass, 5000.0, 0.0, 0.707);
+    disabled.enabled = false;
+    let equalizer = Equalizer::new(vec![disabled, EqBand::new(BandType::Peaking, 1000.0, 0.0, 1.0)]);
+    for frequency in [50.0, 1000.0, 10000.0] {
+        assert!((equalizer.magnitude_response(frequency, SAMPLE_RATE) - 1.0).abs() < 1e-5);
+    }
+    assert!((Equalizer::default().magnitude_response(1000.0, SAMPLE_RATE) - 1.0).abs() < 1e-5);
+}
+
+#[test]
+fn processed_audio_follows_curve() {
+    let bands = vec![
+        EqBand::new(BandType::HighPass, 60.0, 0.0, 0.707),
+        EqBand::new(BandType::LowShelf, 200.0, 5.0, 0.707),
+        EqBand::new(BandType::Peaking, 1200.0, -8.0, 1.5),
+        EqBand::new(BandType::HighShelf, 6000.0, 3.0, 0.707),
+    ];
+    let equalizer = Equalizer::new(bands);
+    for frequency in [40.0, 150.0, 1200.0, 3000.0, 12000.0] {
+        let expected = to_db(equalizer.magnitude_response(frequency, SAMPLE_RATE));
+        let measured = to_db(measured_gain(&mut equalizer.clone(), frequency));
+        assert!(
+            (measured - expected).abs() < 0.2,
+            "at {} Hz: measured {:.2} dB, curve {:.2} dB",
+            frequency, measured, expected
+        );
+    }
+}
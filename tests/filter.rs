@@ -0,0 +1,72 @@
+use wave_crafter::filter::{Filter, FilterMode, FilterSettings};
+
+const SAMPLE_RATE: f32 = 44100.0;
+
+/// Measures the steady-state gain of the filter for a sine at `frequency`.
+fn measured_gain(settings: &FilterSettings, frequency: f32) -> f32 {
+    let mut filter = Filter::new();
+    let length = SAMPLE_RATE as usize; // One second
+    let settle = length / 2; // Skip the transient
+    let (mut input_power, mut output_power) = (0.0f64, 0.0f64);
+    for i in 0..length {
+        let input = (2.0 * std::f32::consts::PI * frequency * i as f32 / SAMPLE_RATE).sin();
+        let output = filter.process(input, settings, SAMPLE_RATE);
+        if i >= settle {
+            input_power += (input as f64).powi(2);
+            output_power += (output as f64).powi(2);
+        }
+    }
+    (output_power / input_power).sqrt() as f32
+}
+
+fn to_db(gain: f32) -> f32 {
+    20.0 * gain.max(1e-9).log10()
+}
+
+#[test]
+fn measured_response_matches_analytic_response() {
+    for mode in [FilterMode::LowPass, FilterMode::HighPass, FilterMode::BandPass, FilterMode::Notch] {
+        for resonance in [0.0, 0.5, 0.9] {
+            let settings = FilterSettings { enabled: true, mode, cutoff: 1000.0, resonance };
+            for frequency in [100.0, 500.0, 1000.0, 2000.0, 8000.0] {
+                let expected = settings.magnitude_response(frequency, SAMPLE_RATE);
+                let measured = measured_gain(&settings, frequency);
+                if expected > 0.01 {
+                    assert!(
+                        (to_db(measured) - to_db(expected)).abs() < 0.5,
+                        "{:?} res {} at {} Hz: measured {:.2} dB, expected {:.2} dB",
+                        mode, resonance, frequency, to_db(measured), to_db(expected)
+                    );
+                } else {
+                    assert!(measured < 0.02, "{:?} at {} Hz should be strongly attenuated", mode, frequency);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn low_pass_has_expected_shape() {
+    let settings = FilterSettings { enabled: true, mode: FilterMode::LowPass, cutoff: 1000.0, resonance: 0.0 };
+    assert!((to_db(measured_gain(&settings, 50.0))).abs() < 0.1); // Flat passband
+    assert!((to_db(measured_gain(&settings, 1000.0)) + 6.0).abs() < 0.3); // Q = 0.5 gives -6 dB at cutoff
+    let octave_drop = to_db(measured_gain(&settings, 4000.0)) - to_db(measured_gain(&settings, 8000.0));
+    assert!((octave_drop - 12.0).abs() < 1.5, "slope was {:.1} dB/octave", octave_drop); // Two-pole roll-off
+}
+
+#[test]
+fn stays_stable_under_fast_cutoff_modulation() {
+    let mut filter = Filter::new();
+    let mut settings = FilterSettings { enabled: true, mode: FilterMode::LowPass, cutoff: 1000.0, resonance: 1.0 };
+    let mut peak: f32 = 0.0;
+    for i in 0..(SAMPLE_RATE as usize * 2) {
+        // Jump the cutoff across the whole range every few samples
+        settings.cutoff = if (i / 7) % 2 == 0 { 20.0 } else { 18000.0 };
+        settings.mode = [FilterMode::LowPass, FilterMode::BandPass][(i / 1000) % 2];
+        let input = if i % 100 < 50 { 1.0 } else { -1.0 };
+        let output = filter.process(input, &settings, SAMPLE_RATE);
+        assert!(output.is_finite());
+        peak = peak.max(output.abs());
+    }
+    assert!(peak < 100.0, "output grew to {}", peak);
+}
@@ -0,0 +1,87 @@
+use wave_crafter::fm::{FmAlgorithm, FmPatch, FmVoice, OPERATOR_COUNT};
+
+const SAMPLE_RATE: f32 = 8000.0;
+
+/// Renders one second of a 100 Hz note with fixed operator envelope levels.
+fn render(patch: &FmPatch, levels: [f32; OPERATOR_COUNT]) -> Vec<f32> {
+    let mut voice = FmVoice::new();
+    (0..SAMPLE_RATE as usize).map(|_| voice.render(patch, 100.0, SAMPLE_RATE, levels)).collect()
+}
+
+fn rising_crossings(samples: &[f32]) -> usize {
+    samples.windows(2).filter(|pair| pair[0] < 0.0 && pair[1] >= 0.0).count()
+}
+
+/// Returns a patch with every operator at full level, ratio 1 and no feedback.
+fn plain(algorithm: FmAlgorithm) -> FmPatch {
+    let mut patch = FmPatch { algorithm, feedback: 0.0, ..FmPatch::default() };
+    for operator in &mut patch.operators {
+        operator.ratio = 1.0;
+        operator.level = 1.0;
+    }
+    patch
+}
+
+#[test]
+fn operators_run_at_their_ratio() {
+    let mut patch = plain(FmAlgorithm::Additive);
+    for (ratio, expected) in [(1.0, 100), (3.0, 300), (0.5, 50)] {
+        patch.operators[1].ratio = ratio;
+        let output = render(&patch, [0.0, 1.0, 0.0, 0.0]);
+        let crossings = rising_crossings(&output);
+        assert!(crossings.abs_diff(expected) <= 1, "ratio {} gave {} cycles", ratio, crossings);
+        let peak = output.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        assert!((peak - 0.25).abs() < 1e-3, "one of four carriers is heard at a quarter level");
+    }
+}
+
+#[test]
+fn algorithms_hear_only_their_carriers() {
+    let carriers = [
+        (FmAlgorithm::Stack, [true, false, false, false]),
+        (FmAlgorithm::Branch, [true, false, false, false]),
+        (FmAlgorithm::TwoStacks, [true, false, true, false]),
+        (FmAlgorithm::ThreeToOne, [true, false, false, false]),
+        (FmAlgorithm::OneToThree, [true, true, true, false]),
+        (FmAlgorithm::StackAndSine, [true, true, false, false]),
+        (FmAlgorithm::Additive, [true, true, true, true]),
+    ];
+    for (algorithm, heard) in carriers {
+        let patch = plain(algorithm);
+        for (operator, &carrier) in heard.iter().enumerate() {
+            let mut levels = [0.0; OPERATOR_COUNT];
+            levels[operator] = 1.0;
+            let loudest = render(&patch, levels).iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+            assert_eq!(loudest > 0.1, carrier, "{:?} operator {}", algorithm, operator + 1);
+        }
+    }
+}
+
+#[test]
+fn modulators_shape_the_carrier() {
+    let patch = plain(FmAlgorithm::Stack);
+    let sine = render(&patch, [1.0, 0.0, 0.0, 0.0]);
+    let modulated = render(&patch, [1.0, 0.5, 0.0, 0.0]);
+    let difference = sine.iter().zip(&modulated).fold(0.0f32, |largest, (a, b)| largest.max((a - b).abs()));
+    assert!(difference > 0.5, "operator 2 modulates operator 1 in a stack");
+
+    let parallel = plain(FmAlgorithm::TwoStacks);
+    assert_eq!(render(&parallel, [1.0, 0.0, 0.0, 0.0]), render(&parallel, [1.0, 0.0, 0.0, 1.0]), "operator 4 only reaches operator 3");
+
+    let mut feedback = plain(FmAlgorithm::Additive);
+    let clean = render(&feedback, [0.0, 0.0, 0.0, 1.0]);
+    feedback.feedback = 1.0;
+    assert_ne!(render(&feedback, [0.0, 0.0, 0.0, 1.0]), clean, "feedback modulates operator 4 by itself");
+}
+
+#[test]
+fn release_follows_the_carriers() {
+    let mut patch = plain(FmAlgorithm::Stack);
+    patch.operators[0].envelope.release = 0.2;
+    patch.operators[3].envelope.release = 5.0;
+    assert_eq!(patch.release(), 0.2, "a modulator's long release does not hold the note");
+
+    patch.algorithm = FmAlgorithm::Additive;
+    assert_eq!(patch.release(), 5.0);
+    assert_eq!(FmPatch::bell().release(), 3.0);
+}
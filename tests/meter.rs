@@ -0,0 +1,90 @@
+use wave_crafter::meter::Meter;
+use wave_crafter::mixer::{PanMode, TrackSource};
+use wave_crafter::synthesizer::{Synthesizer, Waveform};
+
+const SAMPLE_RATE: f32 = 48000.0;
+
+/// Feeds a stereo 1 kHz sine at a level in dBFS to the meter, in blocks like the playback thread.
+fn feed_sine(meter: &mut Meter, level: f32, seconds: f32) {
+    let amplitude = 10f32.powf(level / 20.0);
+    let length = (seconds * SAMPLE_RATE) as usize;
+    let sine: Vec<f32> = (0..length).map(|i| amplitude * (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / SAMPLE_RATE).sin()).collect();
+    for block in sine.chunks(256) {
+        meter.process(block, block, SAMPLE_RATE);
+    }
+}
+
+#[test]
+fn loudness_matches_the_ebu_reference_signals() {
+    let mut meter = Meter::new();
+    feed_sine(&mut meter, -23.0, 20.0);
+    let reading = meter.reading();
+    for (name, value) in [("momentary", reading.momentary), ("short-term", reading.short_term), ("integrated", reading.integrated)] {
+        assert!((value + 23.0).abs() < 0.1, "{} loudness was {:.2} LUFS", name, value);
+    }
+    assert!((reading.peak + 23.0).abs() < 0.1);
+    assert!((reading.rms + 26.01).abs() < 0.1, "a sine's RMS is 3 dB under its peak, got {:.2}", reading.rms);
+
+    meter.reset();
+    assert_eq!(meter.reading().integrated, f32::NEG_INFINITY);
+    feed_sine(&mut meter, -36.0, 10.0);
+    feed_sine(&mut meter, -23.0, 60.0);
+    feed_sine(&mut meter, -36.0, 10.0);
+    let integrated = meter.reading().integrated;
+    assert!((integrated + 23.0).abs() < 0.1, "the relative gate drops the quiet parts, got {:.2} LUFS", integrated);
+    assert!((meter.reading().momentary + 36.0).abs() < 0.1);
+}
+
+#[test]
+fn true_peak_finds_peaks_between_samples() {
+    let mut meter = Meter::new();
+    // A quarter of the sample rate, sampled 45 degrees off its peaks, so every sample lands at 0.707 of the crest
+    let block: Vec<f32> = (0..4800).map(|i| 1.12 * (std::f32::consts::FRAC_PI_2 * i as f32 + std::f32::consts::FRAC_PI_4).sin()).collect();
+    meter.process(&block, &block, SAMPLE_RATE);
+
+    let values = meter.values();
+    let reading = std::thread::spawn(move || values.read()).join().unwrap(); // Read on another thread, as the UI does
+    assert!((reading.peak - 20.0 * (1.12f32 * std::f32::consts::FRAC_1_SQRT_2).log10()).abs() < 0.01);
+    assert!((reading.true_peak - 20.0 * 1.12f32.log10()).abs() < 0.2, "true peak was {:.2} dBTP", reading.true_peak);
+    assert!(reading.clipped, "a true peak over 0 dBTP lights the clip indicator");
+
+    meter.values().clear_clip();
+    meter.process(&block[..256], &block[..256], SAMPLE_RATE);
+    assert!(meter.reading().clipped, "it lights again on the next clip");
+    meter.process(&[0.5; 256], &[0.5; 256], SAMPLE_RATE); // Flushes the interpolator
+    meter.values().clear_clip();
+    meter.process(&[0.5; 256], &[0.5; 256], SAMPLE_RATE);
+    assert!(!meter.reading().clipped, "the held peak alone does not light it");
+}
+
+#[test]
+fn playback_meters_tracks_after_their_fader_and_the_master() {
+    let mut synth = Synthesizer::new(1000.0, 0.5, Waveform::Sine);
+    synth.set_sample_rate(SAMPLE_RATE);
+    synth.effects.slots.clear();
+    synth.add_track("Lead");
+    synth.mixer.tracks[0].source = TrackSource::Synth;
+    synth.mixer.tracks[0].pan_mode = PanMode::Balance;
+    synth.mixer.tracks[0].volume = 0.5;
+
+    let (mut left, mut right) = ([0.0; 256], [0.0; 256]);
+    for block in 0..100 {
+        synth.render_mixed_block(block as f64 * 256.0 / SAMPLE_RATE as f64, &mut left, &mut right);
+    }
+    let track = synth.mixer.tracks[0].meter.reading();
+    assert!((track.peak - 20.0 * 0.25f32.log10()).abs() < 0.1, "the track meter reads after the fader, got {:.2}", track.peak);
+    let master = synth.master_meter.reading();
+    assert!((master.peak - track.peak).abs() < 0.1, "the master meter reads the output");
+    assert!(master.momentary.is_finite() && !master.clipped);
+}
+
+#[test]
+fn true_peak_never_reads_below_the_sample_peak() {
+    let mut meter = Meter::new();
+    let mut block = vec![0.0; 256];
+    block[100] = 1.01; // A single full-scale sample, just over the top
+    meter.process(&block, &block, SAMPLE_RATE);
+    let reading = meter.reading();
+    assert!(reading.true_peak >= reading.peak, "true peak {:.2} dBTP under the sample peak {:.2} dBFS", reading.true_peak, reading.peak);
+    assert!(reading.clipped, "a sample over full scale lights the clip indicator");
+}
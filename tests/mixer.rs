@@ -0,0 +1,498 @@
+use wave_crafter::mixer::{AuxSend, Bus, BusKind, Mixer, Node, PanLaw, PanMode, RoutingError, SoloMode, TrackSource};
+use wave_crafter::synthesizer::{Synthesizer, Track, Waveform};
+use wave_crafter::effects::{EffectContext, EffectKind, EffectSlot};
+use wave_crafter::distortion::{Oversampling, Saturation, SaturationMode};
+use wave_crafter::dynamics::{Compressor, Gate};
+use wave_crafter::eq::Equalizer;
+
+fn to_db(gain: f32) -> f32 {
+    20.0 * gain.max(1e-9).log10()
+}
+
+#[test]
+fn pan_laws_set_the_centre_level() {
+    for (law, centre) in [(PanLaw::Linear, 0.0), (PanLaw::ConstantPower, -3.01), (PanLaw::Compromise, -4.52), (PanLaw::LinearSum, -6.02)] {
+        let (left, right) = law.gains(0.0);
+        assert_eq!(left, right, "{:?} should be symmetric", law);
+        assert!((to_db(left) - centre).abs() < 0.01, "{:?} centre was {:.2} dB", law, to_db(left));
+
+        assert_eq!(law.gains(-1.0), (1.0, 0.0), "{:?} hard left", law);
+        let (left, right) = law.gains(1.0);
+        assert!(left.abs() < 1e-6 && (right - 1.0).abs() < 1e-6, "{:?} hard right", law);
+    }
+    for pan in [-0.8, -0.3, 0.4, 0.9] {
+        let (left, right) = PanLaw::ConstantPower.gains(pan);
+        assert!((left * left + right * right - 1.0).abs() < 1e-5, "constant power keeps the total power");
+    }
+}
+
+#[test]
+fn balance_keeps_the_stereo_image() {
+    let mut track = Track::new("Pad");
+    track.pan = 0.5;
+    track.pan_mode = PanMode::Balance;
+    assert_eq!(track.pan_frame(0.8, 0.4, PanLaw::ConstantPower), (0.4, 0.4), "turns down only the left side");
+    track.pan = -1.0;
+    assert_eq!(track.pan_frame(0.8, 0.4, PanLaw::ConstantPower), (0.8, 0.0));
+
+    track.pan_mode = PanMode::Pan;
+    let (left, right) = track.pan_frame(0.8, 0.4, PanLaw::ConstantPower);
+    assert!((left - 0.6).abs() < 1e-6 && right.abs() < 1e-6, "pan folds to mono and places it hard left");
+}
+
+#[test]
+fn pan_keeps_a_centred_stereo_image() {
+    let track = Track::new("Pad");
+    assert_eq!(track.pan_mode, PanMode::Pan);
+    let (left, right) = track.pan_frame(0.8, 0.4, PanLaw::Linear);
+    assert!((left - 0.8).abs() < 1e-6 && (right - 0.4).abs() < 1e-6, "a centred stereo source is not folded to mono");
+    let (left, right) = track.pan_frame(0.8, 0.4, PanLaw::ConstantPower);
+    let centre = std::f32::consts::FRAC_1_SQRT_2;
+    assert!((left - 0.8 * centre).abs() < 1e-6 && (right - 0.4 * centre).abs() < 1e-6, "the pan law sets only the level");
+
+    let mut track = Track::new("Pad");
+    track.pan = 0.5;
+    let (mono_left, mono_right) = track.pan_frame(0.6, 0.6, PanLaw::ConstantPower);
+    let (gain_left, gain_right) = PanLaw::ConstantPower.gains(0.5);
+    assert_eq!((mono_left, mono_right), (0.6 * gain_left, 0.6 * gain_right), "mono sources follow the pan law as before");
+    let (left, right) = track.pan_frame(0.8, 0.4, PanLaw::ConstantPower);
+    assert!(left > mono_left && right < mono_right, "off centre the image narrows but keeps its sides");
+}
+
+#[test]
+fn pan_settings_are_saved_with_the_project() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("project.json");
+    let mut synth = Synthesizer::new(440.0, 0.5, Waveform::Sine);
+    synth.add_track("Bass");
+    synth.mixer.tracks[0].pan = -0.25;
+    synth.mixer.tracks[0].pan_mode = PanMode::Balance;
+    synth.mixer.pan_law = PanLaw::Compromise;
+    synth.save_project(path.to_str().unwrap()).unwrap();
+
+    let mut loaded = Synthesizer::new(440.0, 0.5, Waveform::Sine);
+    loaded.load_project(path.to_str().unwrap()).unwrap();
+    assert_eq!(loaded.mixer.tracks.len(), 1);
+    assert_eq!(loaded.mixer.tracks[0].id, "Bass");
+    assert_eq!(loaded.mixer.tracks[0].pan, -0.25);
+    assert_eq!(loaded.mixer.tracks[0].pan_mode, PanMode::Balance);
+    assert_eq!(loaded.mixer.pan_law, PanLaw::Compromise);
+}
+
+/// Returns the amplitude of one frequency in a signal, with the Goertzel algorithm.
+fn amplitude_at(samples: &[f32], frequency: f32, sample_rate: f32) -> f32 {
+    let coefficient = 2.0 * (2.0 * std::f64::consts::PI * frequency as f64 / sample_rate as f64).cos();
+    let (mut previous, mut before) = (0.0f64, 0.0f64);
+    for &sample in samples {
+        (previous, before) = (sample as f64 + coefficient * previous - before, previous);
+    }
+    let power = previous * previous + before * before - coefficient * previous * before;
+    (2.0 * power.sqrt() / samples.len() as f64) as f32
+}
+
+/// Renders one second of playback.
+fn render_second(synth: &mut Synthesizer) -> (Vec<f32>, Vec<f32>) {
+    let length = synth.sample_rate as usize;
+    let (mut left, mut right) = (vec![0.0; length], vec![0.0; length]);
+    for (block, (left, right)) in left.chunks_mut(256).zip(right.chunks_mut(256)).enumerate() {
+        synth.render_mixed_block(block as f64 * 256.0 / synth.sample_rate as f64, left, right);
+    }
+    (left, right)
+}
+
+#[test]
+fn tracks_mix_their_own_sources() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("tone.wav");
+    let spec = hound::WavSpec { channels: 1, sample_rate: 44100, bits_per_sample: 32, sample_format: hound::SampleFormat::Float };
+    let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+    for i in 0..88200 {
+        writer.write_sample(0.8 * (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / 44100.0).sin()).unwrap();
+    }
+    writer.finalize().unwrap();
+
+    let mut synth = Synthesizer::new(400.0, 0.5, Waveform::Sine);
+    synth.effects.slots.clear(); // Keep the delay and reverb out of the measurement
+    synth.add_track("Lead");
+    synth.mixer.tracks[0].source = TrackSource::Synth;
+    synth.mixer.tracks[0].volume = 1.0;
+    synth.mixer.tracks[0].pan = -1.0;
+    synth.add_file_track("Loop", path.to_str().unwrap()).unwrap();
+    synth.mixer.tracks[1].pan = 1.0; // Volume stays at the default 0.5
+
+    let (left, right) = render_second(&mut synth);
+    let rate = synth.sample_rate;
+    assert!((amplitude_at(&left, 400.0, rate) - 0.5).abs() < 0.01, "the synth track plays hard left at full volume");
+    assert!(amplitude_at(&left, 1000.0, rate) < 0.001);
+    assert!((amplitude_at(&right, 1000.0, rate) - 0.4).abs() < 0.01, "the file track plays hard right at half volume");
+    assert!(amplitude_at(&right, 400.0, rate) < 0.001, "the synth only reaches the output through its track");
+
+    synth.mixer.tracks[1].muted = true;
+    let (_, right) = render_second(&mut synth);
+    assert!(right[1000..].iter().all(|sample| sample.abs() < 1e-6), "a muted track is silent"); // After the limiter lookahead drains
+}
+
+#[test]
+fn clips_play_through_their_track() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sample.wav");
+    let spec = hound::WavSpec { channels: 1, sample_rate: 44100, bits_per_sample: 32, sample_format: hound::SampleFormat::Float };
+    let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+    for _ in 0..44100 {
+        writer.write_sample(0.5f32).unwrap();
+    }
+    writer.finalize().unwrap();
+
+    let mut synth = Synthesizer::new(440.0, 0.0, Waveform::Sine); // Silent live voice
+    synth.effects.slots.clear();
+    synth.add_track("Drums");
+    synth.mixer.tracks[0].pan = 1.0;
+    synth.add_sample_clip("Hit", 0.0, path.to_str().unwrap()).unwrap();
+    synth.timeline.clips[0].track = "Drums".to_string();
+
+    let (left, right) = render_second(&mut synth);
+    assert!(left[22050].abs() < 1e-6, "the track pans the clip away from the left");
+    assert!((right[22050] - 0.25).abs() < 1e-3, "the clip plays at the track volume, got {}", right[22050]);
+
+    synth.timeline.clips[0].track = String::new(); // Back to playing directly
+    let (left, right) = render_second(&mut synth);
+    assert!((left[22050] - 0.5).abs() < 1e-3 && (right[22050] - 0.5).abs() < 1e-3);
+}
+
+/// Builds a mixer of synth tracks named after their position.
+fn synth_tracks(count: usize) -> Mixer {
+    let mut mixer = Mixer::new();
+    for index in 0..count {
+        let mut track = Track::new(&index.to_string());
+        track.source = TrackSource::Synth;
+        track.volume = 1.0;
+        mixer.tracks.push(track);
+    }
+    mixer
+}
+
+fn audible(mixer: &Mixer) -> Vec<bool> {
+    (0..mixer.tracks.len()).map(|index| mixer.is_audible(index)).collect()
+}
+
+#[test]
+fn solo_modes_decide_what_is_heard() {
+    let mut mixer = synth_tracks(4);
+    mixer.tracks[3].solo_safe = true; // A return bus
+    assert_eq!(audible(&mixer), [true; 4]);
+
+    mixer.set_solo(0, true);
+    mixer.set_solo(1, true);
+    assert_eq!(audible(&mixer), [true, true, false, true], "additive solos add up and spare solo-safe tracks");
+    let (left, _) = mix_frame(&mut mixer);
+    assert!((left - 3.0 * std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-5, "the mix follows the solo rules");
+
+    mixer.solo_mode = SoloMode::Exclusive;
+    mixer.set_solo(2, true);
+    assert_eq!(audible(&mixer), [false, false, true, true], "an exclusive solo clears the others");
+
+    mixer.set_solo(2, false);
+    mixer.set_muted(3, true);
+    assert_eq!(audible(&mixer), [true, true, true, false], "solo-safe tracks can still be muted");
+}
+
+#[test]
+fn groups_mute_and_solo_together() {
+    let mut mixer = synth_tracks(4);
+    mixer.tracks[0].group = "Drums".to_string();
+    mixer.tracks[1].group = "Drums".to_string();
+    mixer.tracks[2].group = "Keys".to_string();
+
+    mixer.set_muted(1, true);
+    assert!(mixer.tracks[0].muted && mixer.tracks[1].muted && !mixer.tracks[2].muted && !mixer.tracks[3].muted);
+    mixer.set_muted(0, false);
+    assert!(mixer.tracks.iter().all(|track| !track.muted));
+
+    mixer.solo_mode = SoloMode::Exclusive;
+    mixer.set_solo(3, true);
+    mixer.set_solo(0, true);
+    assert_eq!(audible(&mixer), [true, true, false, false], "the whole group is soloed, replacing the other solo");
+}
+
+/// Mixes a single frame of a constant `(1.0, 1.0)` synth through the tracks and buses.
+fn mix_frame(mixer: &mut Mixer) -> (f32, f32) {
+    let context = EffectContext { sample_rate: 44100.0, tempo: 120.0 };
+    let (mut left, mut right) = ([0.0], [0.0]);
+    mixer.begin_block(1);
+    mixer.add_sources(0, 0.0, 1.0 / 44100.0, (1.0, 1.0));
+    mixer.apply_mixing(&mut left, &mut right, &context);
+    (left[0], right[0])
+}
+
+#[test]
+fn buses_sum_their_tracks_and_sends() {
+    let mut mixer = synth_tracks(2);
+    for track in &mut mixer.tracks {
+        track.pan_mode = PanMode::Balance; // Pass the frame through unchanged
+    }
+    let mut drums = Bus::new("Drums", BusKind::Group);
+    drums.volume = 0.5;
+    drums.pan = 1.0;
+    mixer.add_bus(drums).unwrap();
+    mixer.set_output(Node::Track(0), "Drums").unwrap();
+    mixer.set_output(Node::Track(1), "Drums").unwrap();
+    assert_eq!(mix_frame(&mut mixer), (0.0, 1.0), "both tracks reach the master through the group");
+
+    let mut aux = Bus::new("Aux", BusKind::Return);
+    aux.inserts.slots.clear(); // A plain return, so the level is easy to check
+    mixer.add_bus(aux).unwrap();
+    mixer.set_output(Node::Bus(0), "").unwrap();
+    mixer.tracks[0].volume = 0.25;
+    mixer.add_send(Node::Track(0), AuxSend::new("Aux", 1.0, false)).unwrap();
+    assert_eq!(mix_frame(&mut mixer), (0.25, 0.625 + 0.25), "a post-fader send follows the fader");
+    mixer.tracks[0].sends[0].pre_fader = true;
+    assert_eq!(mix_frame(&mut mixer), (1.0, 0.625 + 1.0), "a pre-fader send ignores it");
+
+    mixer.buses[1].muted = true;
+    assert_eq!(mix_frame(&mut mixer), (0.0, 0.625));
+    mixer.remove_bus(0);
+    assert_eq!(mixer.tracks[0].output, "", "tracks of a removed bus go to the master");
+    assert_eq!(mixer.buses.len(), 1);
+}
+
+#[test]
+fn routing_rejects_feedback_loops() {
+    let mut mixer = synth_tracks(1);
+    for id in ["A", "B", "C"] {
+        mixer.add_bus(Bus::new(id, BusKind::Group)).unwrap();
+    }
+    mixer.set_output(Node::Bus(0), "B").unwrap();
+    mixer.set_output(Node::Bus(1), "C").unwrap();
+    let error = mixer.set_output(Node::Bus(2), "A").unwrap_err();
+    assert_eq!(error, RoutingError::FeedbackLoop(vec!["A".into(), "B".into(), "C".into(), "A".into()]));
+    assert_eq!(mixer.buses[2].output, "", "a rejected change leaves the routing alone");
+    assert!(mixer.add_send(Node::Bus(1), AuxSend::new("A", 0.5, true)).is_err(), "sends can close a loop too");
+    assert!(mixer.buses[1].sends.is_empty());
+    assert!(mixer.set_output(Node::Bus(0), "A").is_err(), "a bus cannot feed itself");
+
+    assert_eq!(mixer.set_output(Node::Track(0), "D"), Err(RoutingError::UnknownBus("D".into())));
+    assert_eq!(mixer.add_bus(Bus::new("A", BusKind::Return)), Err(RoutingError::DuplicateBus("A".into())));
+    assert!(mixer.validate_routing().is_ok());
+
+    mixer.buses[2].output = "A".to_string(); // Edited behind the mixer's back
+    assert!(mixer.validate_routing().is_err());
+    mixer.set_output(Node::Track(0), "A").unwrap_err(); // Every change is refused until the loop is broken
+    mixer.tracks[0].output = "A".to_string();
+    assert_eq!(mix_frame(&mut mixer), (0.0, 0.0), "buses in a loop stay silent instead of feeding back");
+}
+
+#[test]
+fn routing_is_saved_with_the_project() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("project.json");
+    let mut synth = Synthesizer::new(440.0, 0.5, Waveform::Sine);
+    synth.add_track("Lead");
+    synth.mixer.add_bus(Bus::new("Keys", BusKind::Group)).unwrap();
+    synth.mixer.add_bus(Bus::new("Hall", BusKind::Return)).unwrap();
+    synth.mixer.set_output(Node::Track(0), "Keys").unwrap();
+    synth.mixer.add_send(Node::Bus(0), AuxSend::new("Hall", 0.3, true)).unwrap();
+    synth.save_project(path.to_str().unwrap()).unwrap();
+
+    let mut loaded = Synthesizer::new(440.0, 0.5, Waveform::Sine);
+    loaded.load_project(path.to_str().unwrap()).unwrap();
+    let mixer = &loaded.mixer;
+    assert_eq!(mixer.tracks[0].output, "Keys");
+    assert_eq!(mixer.buses.len(), 2);
+    assert_eq!(mixer.buses[1].kind, BusKind::Return);
+    assert_eq!(mixer.buses[1].inserts.slots.len(), 1, "the return keeps its reverb");
+    assert_eq!((mixer.buses[0].sends[0].bus.as_str(), mixer.buses[0].sends[0].level, mixer.buses[0].sends[0].pre_fader), ("Hall", 0.3, true));
+
+    synth.mixer.buses[1].output = "Keys".to_string(); // Keys sends to Hall, which would feed Keys
+    synth.save_project(path.to_str().unwrap()).unwrap();
+    let error = loaded.load_project(path.to_str().unwrap()).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData, "projects with a feedback loop are refused");
+    assert_eq!(loaded.mixer.buses[1].output, "", "the previous project stays loaded");
+}
+
+#[test]
+fn inserts_process_only_their_track() {
+    let mut mixer = synth_tracks(2);
+    for track in &mut mixer.tracks {
+        track.pan_mode = PanMode::Balance;
+    }
+    mixer.tracks[0].pan = -1.0;
+    mixer.tracks[1].pan = 1.0;
+    let mut saturation = Saturation::new(SaturationMode::HardClip, 0.0);
+    (saturation.output, saturation.oversampling) = (-6.0206, Oversampling::Off); // Halve the level, without latency
+    mixer.tracks[0].inserts.add(EffectKind::Saturation(saturation));
+    let (left, right) = mix_frame(&mut mixer);
+    assert!((left - 0.5).abs() < 1e-4, "the insert halves its own track, got {}", left);
+    assert_eq!(right, 1.0, "the other track is untouched");
+
+    mixer.tracks[0].inserts.slots[0].bypass = true;
+    assert_eq!(mix_frame(&mut mixer), (1.0, 1.0), "a bypassed insert passes the track through");
+}
+
+#[test]
+fn inserts_are_saved_with_the_project() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("project.json");
+    let mut synth = Synthesizer::new(440.0, 0.5, Waveform::Sine);
+    synth.add_track("Bass");
+    assert!(synth.mixer.tracks[0].inserts.slots.is_empty(), "new tracks start without inserts");
+    synth.mixer.tracks[0].inserts.add(EffectKind::Equalizer(Equalizer::default()));
+    synth.mixer.tracks[0].inserts.add(EffectKind::Compressor(Compressor::default()));
+    synth.mixer.tracks[0].inserts.slots[1].bypass = true;
+    synth.save_project(path.to_str().unwrap()).unwrap();
+
+    let mut loaded = Synthesizer::new(440.0, 0.5, Waveform::Sine);
+    loaded.load_project(path.to_str().unwrap()).unwrap();
+    let names: Vec<&str> = loaded.mixer.tracks[0].inserts.slots.iter().map(|slot| slot.effect.name()).collect();
+    assert_eq!(names, ["EQ", "Compressor"]);
+    assert!(loaded.mixer.tracks[0].inserts.slots[1].bypass);
+}
+
+/// Mixes one block of clip tracks, each fed a constant level, and returns the left output.
+fn mix_clip_block(mixer: &mut Mixer, levels: &[(&str, f32)], length: usize) -> Vec<f32> {
+    let context = EffectContext { sample_rate: 44100.0, tempo: 120.0 };
+    let (mut left, mut right) = (vec![0.0; length], vec![0.0; length]);
+    mixer.begin_block(length);
+    for frame in 0..length {
+        mixer.clear_clip_inputs();
+        for &(track, level) in levels {
+            mixer.add_clip_input(track, level);
+        }
+        mixer.add_sources(frame, 0.0, 1.0 / 44100.0, (0.0, 0.0));
+    }
+    mixer.apply_mixing(&mut left, &mut right, &context);
+    left
+}
+
+#[test]
+fn sidechains_follow_their_key_track() {
+    let mut mixer = Mixer::new();
+    for id in ["Bass", "Kick"] {
+        let mut track = Track::new(id);
+        (track.volume, track.pan_mode) = (1.0, PanMode::Balance);
+        mixer.tracks.push(track);
+    }
+    mixer.tracks[1].muted = true; // Used only as a key
+    let keyed_compressor = |sidechain: &str| {
+        let mut compressor = Compressor::default();
+        (compressor.threshold, compressor.ratio, compressor.knee) = (-20.0, 10.0, 0.0);
+        let mut slot = EffectSlot::new(EffectKind::Compressor(compressor));
+        slot.sidechain = sidechain.to_string();
+        slot
+    };
+    let quiet = 0.03; // About -30 dBFS, below the threshold on its own
+    mixer.tracks[0].inserts.slots.push(keyed_compressor("Kick"));
+    let output = mix_clip_block(&mut mixer, &[("Bass", quiet), ("Kick", 0.0)], 4410);
+    assert!((output[4409] - quiet).abs() < 1e-4, "a silent key leaves the track alone");
+    let output = mix_clip_block(&mut mixer, &[("Bass", quiet), ("Kick", 1.0)], 4410);
+    assert!(output[4409] < 0.2 * quiet, "the muted kick still ducks the bass, got {}", output[4409]);
+
+    mixer.tracks[0].inserts.slots.clear();
+    let mut group = Bus::new("Group", BusKind::Group);
+    group.inserts.slots.push(keyed_compressor("Snare"));
+    mixer.add_bus(group).unwrap();
+    mixer.set_output(Node::Track(0), "Group").unwrap();
+    let output = mix_clip_block(&mut mixer, &[("Bass", quiet), ("Kick", 1.0)], 4410);
+    assert!((output[4409] - quiet).abs() < 1e-4, "a missing key track leaves the compressor on its own input");
+    mixer.buses[0].inserts.slots[0].sidechain = "Kick".to_string();
+    let output = mix_clip_block(&mut mixer, &[("Bass", quiet), ("Kick", 1.0)], 4410);
+    assert!(output[4409] < 0.2 * quiet, "bus inserts can be keyed too");
+}
+
+#[test]
+fn buses_follow_solo() {
+    let mut mixer = synth_tracks(3);
+    for track in &mut mixer.tracks {
+        track.pan_mode = PanMode::Balance;
+    }
+    mixer.add_bus(Bus::new("Drums", BusKind::Group)).unwrap();
+    let mut aux = Bus::new("Aux", BusKind::Return);
+    aux.inserts.slots.clear();
+    mixer.add_bus(aux).unwrap();
+    assert!(!mixer.buses[0].solo_safe && mixer.buses[1].solo_safe, "only returns start solo-safe");
+    mixer.set_output(Node::Track(0), "Drums").unwrap();
+    mixer.set_output(Node::Track(1), "Drums").unwrap();
+    mixer.add_send(Node::Track(2), AuxSend::new("Aux", 0.5, true)).unwrap();
+    assert_eq!(mix_frame(&mut mixer), (3.5, 3.5));
+
+    mixer.set_bus_solo(0, true);
+    assert_eq!(audible(&mixer), [true, true, false], "soloing a group lets its tracks through");
+    assert_eq!(mix_frame(&mut mixer), (2.0, 2.0));
+
+    mixer.set_bus_solo(0, false);
+    mixer.set_solo(2, true);
+    assert!(!mixer.is_bus_audible(0) && mixer.is_bus_audible(1), "the group is silenced, the solo-safe return is not");
+    assert_eq!(mix_frame(&mut mixer), (1.5, 1.5));
+
+    mixer.set_solo(0, true);
+    mixer.set_solo(2, false);
+    assert!(mixer.is_bus_audible(0), "a soloed track keeps the group it feeds");
+    assert_eq!(mix_frame(&mut mixer), (1.0, 1.0));
+
+    mixer.solo_mode = SoloMode::Exclusive;
+    mixer.set_bus_solo(0, true);
+    assert!(!mixer.tracks[0].solo, "an exclusive bus solo clears track solos");
+
+    let old: Bus = serde_json::from_str(r#"{"id":"Old","kind":"Group","volume":1.0,"muted":false,"pan":0.0,"inserts":{"slots":[]}}"#).unwrap();
+    assert!(!old.solo && old.solo_safe, "buses from older projects keep playing through solos, as they used to");
+}
+
+#[test]
+fn solo_silences_what_plays_outside_the_mixer() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("dc.wav");
+    let spec = hound::WavSpec { channels: 1, sample_rate: 44100, bits_per_sample: 32, sample_format: hound::SampleFormat::Float };
+    let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+    for _ in 0..44100 {
+        writer.write_sample(0.25f32).unwrap();
+    }
+    writer.finalize().unwrap();
+
+    let mut synth = Synthesizer::new(400.0, 0.5, Waveform::Sine);
+    synth.effects.slots.clear();
+    synth.add_sample_clip("Hit", 0.0, path.to_str().unwrap()).unwrap(); // No track, so it plays directly
+    synth.add_track("Empty");
+    let (left, _) = render_second(&mut synth);
+    assert!(amplitude_at(&left, 400.0, synth.sample_rate) > 0.4, "the live voice plays directly");
+    assert!(left[10000..20000].iter().sum::<f32>() > 2000.0, "and so does the clip");
+
+    synth.mixer.set_solo(0, true);
+    let (left, right) = render_second(&mut synth);
+    assert!(left[1000..].iter().chain(&right[1000..]).all(|sample| sample.abs() < 1e-6), "a solo silences both");
+}
+
+#[test]
+fn new_ids_are_unused() {
+    let mut mixer = Mixer::new();
+    assert_eq!(mixer.new_bus_id(), "Bus 1");
+    for _ in 0..3 {
+        mixer.add_bus(Bus::new(&mixer.new_bus_id(), BusKind::Group)).unwrap();
+    }
+    mixer.remove_bus(0);
+    let id = mixer.new_bus_id();
+    assert_eq!(id, "Bus 4", "Bus 3 is still in use after Bus 1 is removed");
+    mixer.add_bus(Bus::new(&id, BusKind::Return)).unwrap();
+
+    mixer.tracks.push(Track::new("Track 2"));
+    assert_eq!(mixer.new_track_id(), "Track 3");
+    mixer.tracks.push(Track::new(&mixer.new_track_id()));
+    assert_eq!(mixer.new_track_id(), "Track 4");
+}
+
+#[test]
+fn gates_follow_their_key_track() {
+    let mut mixer = Mixer::new();
+    for id in ["Pad", "Hat"] {
+        let mut track = Track::new(id);
+        (track.volume, track.pan_mode) = (1.0, PanMode::Balance);
+        mixer.tracks.push(track);
+    }
+    mixer.tracks[1].muted = true;
+    let mut slot = EffectSlot::new(EffectKind::Gate(Gate::default()));
+    slot.sidechain = "Hat".to_string();
+    assert!(slot.effect.accepts_sidechain());
+    mixer.tracks[0].inserts.slots.push(slot);
+    let output = mix_clip_block(&mut mixer, &[("Pad", 0.5), ("Hat", 0.0)], 4410);
+    assert!(output[4409].abs() < 1e-3, "a silent key keeps the gate shut on a loud track, got {}", output[4409]);
+    let output = mix_clip_block(&mut mixer, &[("Pad", 0.5), ("Hat", 0.5)], 4410);
+    assert!((output[4409] - 0.5).abs() < 1e-3, "the key opens the gate, got {}", output[4409]);
+}
@@ -0,0 +1,115 @@
+use wave_crafter::effects::{AutoPan, Chorus, Delay, EffectKind, Flanger, Phaser, Reverb, Tremolo};
+use wave_crafter::modulation::{Lfo, LfoRate, LfoShape, ModMatrix, ModRoute, ModTarget};
+use wave_crafter::synthesizer::{Synthesizer, Waveform};
+
+const SAMPLE_RATE: f32 = 1000.0;
+
+/// Returns the LFO values over a number of samples, reading before each advance.
+fn values(lfo: &mut Lfo, samples: usize, tempo: f32) -> Vec<f32> {
+    (0..samples)
+        .map(|_| {
+            let value = lfo.value();
+            lfo.advance(SAMPLE_RATE, tempo);
+            value
+        })
+        .collect()
+}
+
+#[test]
+fn lfo_shapes_follow_their_waveforms() {
+    let mut sine = Lfo::new(LfoShape::Waveform(Waveform::Sine), LfoRate::Hertz(1.0));
+    let sine = values(&mut sine, 1000, 120.0);
+    assert!(sine[0].abs() < 1e-6 && (sine[250] - 1.0).abs() < 1e-4 && (sine[750] + 1.0).abs() < 1e-4);
+
+    let mut square = Lfo::new(LfoShape::Waveform(Waveform::Square), LfoRate::Hertz(1.0));
+    let square = values(&mut square, 1000, 120.0);
+    assert!(square[..500].iter().all(|&value| value == 1.0) && square[500..].iter().all(|&value| value == -1.0));
+
+    let mut saw = Lfo::new(LfoShape::Waveform(Waveform::Sawtooth), LfoRate::Hertz(1.0));
+    let saw = values(&mut saw, 1000, 120.0);
+    assert!(saw.windows(2).all(|pair| pair[1] > pair[0]), "the sawtooth rises through the cycle");
+
+    let mut held = Lfo::new(LfoShape::SampleAndHold, LfoRate::Hertz(4.0));
+    let held = values(&mut held, 1000, 120.0);
+    for cycle in held.chunks(250) {
+        assert!(cycle[1..].iter().all(|&value| value == cycle[1]), "sample-and-hold keeps one value per cycle");
+        assert!(cycle.iter().all(|value| (-1.0..=1.0).contains(value)));
+    }
+    assert_ne!(held[100], held[350], "and picks a new one for the next cycle");
+
+    let mut again = Lfo::new(LfoShape::SampleAndHold, LfoRate::Hertz(4.0));
+    assert_eq!(values(&mut again, 1000, 120.0), held, "the random sequence restarts with the LFO");
+}
+
+#[test]
+fn synced_rates_follow_the_tempo() {
+    assert_eq!(LfoRate::Synced(1.0).frequency(120.0), 2.0, "one beat at 120 BPM lasts half a second");
+    assert_eq!(LfoRate::Synced(4.0).frequency(60.0), 0.25);
+    assert_eq!(LfoRate::Hertz(3.0).frequency(200.0), 3.0, "free rates ignore the tempo");
+
+    let mut lfo = Lfo::new(LfoShape::Waveform(Waveform::Square), LfoRate::Synced(0.5));
+    let square = values(&mut lfo, 500, 240.0); // Half a beat at 240 BPM is 125 ms
+    let edges = square.windows(2).filter(|pair| pair[0] != pair[1]).count();
+    assert_eq!(edges, 7, "four cycles have eight edges, less the one at the very start");
+}
+
+#[test]
+fn routes_modulate_their_target_and_restore_it() {
+    let mut synth = Synthesizer::new(440.0, 0.5, Waveform::Sine);
+    synth.set_sample_rate(44100.0);
+    synth.effects.slots.clear();
+    synth.modulation.lfos.push(Lfo::new(LfoShape::Waveform(Waveform::Square), LfoRate::Hertz(1.0)));
+    synth.modulation.routes.push(ModRoute { source: 0, target: ModTarget::Amplitude, depth: 0.5 });
+
+    let output: Vec<f32> = (0..44100).map(|_| synth.generate_sample().0).collect();
+    let peak = |range: &[f32]| range.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+    assert!(peak(&output[2000..20000]) > 0.8, "the first half cycle raises the amplitude to full");
+    assert!(peak(&output[26000..43000]) < 1e-3, "the second half takes it down to silence");
+    assert_eq!(synth.amplitude, 0.5, "the unmodulated setting is kept");
+
+    let mut matrix = ModMatrix::default();
+    for _ in 0..3 {
+        matrix.lfos.push(Lfo::new(LfoShape::SampleAndHold, LfoRate::Hertz(1.0)));
+    }
+    matrix.routes.push(ModRoute { source: 0, target: ModTarget::Delay, depth: 1.0 });
+    matrix.routes.push(ModRoute { source: 2, target: ModTarget::Reverb, depth: 1.0 });
+    matrix.remove_lfo(0);
+    assert_eq!(matrix.routes, [ModRoute { source: 1, target: ModTarget::Reverb, depth: 1.0 }], "routes follow their LFO");
+}
+
+#[test]
+fn effect_targets_name_their_effects() {
+    let effects = [
+        (ModTarget::Delay, EffectKind::Delay(Delay::default())),
+        (ModTarget::Reverb, EffectKind::Reverb(Reverb::default())),
+        (ModTarget::Chorus, EffectKind::Chorus(Chorus::default())),
+        (ModTarget::Flanger, EffectKind::Flanger(Flanger::default())),
+        (ModTarget::Phaser, EffectKind::Phaser(Phaser::default())),
+        (ModTarget::Tremolo, EffectKind::Tremolo(Tremolo::default())),
+        (ModTarget::AutoPan, EffectKind::AutoPan(AutoPan::default())),
+    ];
+    for (target, mut effect) in effects {
+        assert_eq!(target.effect_name(), Some(effect.name()));
+        assert!(effect.mix_mut().is_some(), "{:?} has a mix to modulate", target);
+        assert_eq!(target.apply(0.8, 0.5), 1.0, "mixes stay in range");
+    }
+    assert_eq!(ModTarget::FilterCutoff.effect_name(), None);
+    assert_eq!(ModTarget::FilterCutoff.apply(1000.0, 1.0), 2000.0, "frequencies move in octaves");
+}
+
+#[test]
+fn modulation_is_saved_with_the_project() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("project.json");
+    let mut synth = Synthesizer::new(440.0, 0.5, Waveform::Sine);
+    synth.modulation.lfos.push(Lfo::new(LfoShape::SampleAndHold, LfoRate::Synced(0.25)));
+    synth.modulation.lfos.push(Lfo::new(LfoShape::Waveform(Waveform::Triangle), LfoRate::Hertz(5.0)));
+    synth.modulation.routes.push(ModRoute { source: 1, target: ModTarget::Phaser, depth: -0.5 });
+    synth.save_project(path.to_str().unwrap()).unwrap();
+
+    let mut loaded = Synthesizer::new(440.0, 0.5, Waveform::Sine);
+    loaded.load_project(path.to_str().unwrap()).unwrap();
+    let shapes: Vec<(LfoShape, LfoRate)> = loaded.modulation.lfos.iter().map(|lfo| (lfo.shape, lfo.rate)).collect();
+    assert_eq!(shapes, [(LfoShape::SampleAndHold, LfoRate::Synced(0.25)), (LfoShape::Waveform(Waveform::Triangle), LfoRate::Hertz(5.0))]);
+    assert_eq!(loaded.modulation.routes, synth.modulation.routes);
+}
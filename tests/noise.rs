@@ -0,0 +1,60 @@
+use rustfft::{FftPlanner, num_complex::Complex};
+use wave_crafter::noise::NoiseGenerator;
+use wave_crafter::synthesizer::{Synthesizer, Waveform};
+
+const SAMPLE_RATE: usize = 44100;
+
+type NoiseColor = fn(&mut NoiseGenerator) -> f32;
+
+#[test]
+fn noise_exports_are_reproducible() {
+    let dir = tempfile::tempdir().unwrap();
+    for waveform in [Waveform::WhiteNoise, Waveform::PinkNoise, Waveform::BrownNoise] {
+        let mut synth = Synthesizer::new(440.0, 0.5, waveform);
+        let first = dir.path().join("first.wav");
+        let second = dir.path().join("second.wav");
+        synth.export_to_wav(0.5, first.to_str().unwrap()).unwrap();
+        synth.export_to_wav(0.5, second.to_str().unwrap()).unwrap();
+        assert_eq!(std::fs::read(&first).unwrap(), std::fs::read(&second).unwrap(), "{:?} export differs", waveform);
+
+        // The two channels are seeded differently, so stereo noise is not mono in disguise
+        let mut reader = hound::WavReader::open(&first).unwrap();
+        let samples: Vec<i16> = reader.samples::<i16>().map(|s| s.unwrap()).collect();
+        assert!(samples.chunks(2).any(|frame| frame[0] != frame[1]));
+    }
+}
+
+/// Returns the energy in one octave band divided by the energy in an octave six octaves higher.
+fn low_to_high_octave_ratio(waveform: Waveform) -> f64 {
+    let mut synth = Synthesizer::new(440.0, 1.0, waveform);
+    let mut buffer: Vec<Complex<f64>> = (0..SAMPLE_RATE)
+        .map(|_| Complex { re: synth.generate_sample().0 as f64, im: 0.0 })
+        .collect();
+    FftPlanner::new().plan_fft_forward(SAMPLE_RATE).process(&mut buffer);
+    let band = |low: usize| -> f64 { buffer[low..2 * low].iter().map(|c| c.norm_sqr()).sum() }; // 1 Hz bins
+    band(100) / band(6400)
+}
+
+#[test]
+fn noise_colors_have_expected_spectral_tilt() {
+    // Per octave, white noise energy doubles, pink noise stays equal and brown noise halves
+    let white = low_to_high_octave_ratio(Waveform::WhiteNoise);
+    let pink = low_to_high_octave_ratio(Waveform::PinkNoise);
+    let brown = low_to_high_octave_ratio(Waveform::BrownNoise);
+    assert!(white < 0.05, "white ratio {}", white);
+    assert!((0.5..2.0).contains(&pink), "pink ratio {}", pink);
+    assert!(brown > 20.0, "brown ratio {}", brown);
+}
+
+#[test]
+fn noise_stays_within_full_scale() {
+    let colors: [(&str, NoiseColor); 3] =
+        [("white", NoiseGenerator::white), ("pink", NoiseGenerator::pink), ("brown", NoiseGenerator::brown)];
+    for (name, next) in colors {
+        let mut noise = NoiseGenerator::new(1);
+        let samples: Vec<f32> = (0..10 * SAMPLE_RATE).map(|_| next(&mut noise)).collect();
+        let peak = samples.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        assert!(peak <= 1.0, "{} noise peaked at {}, which would clip on export", name, peak);
+        assert!(peak > 0.5, "{} noise still uses most of the range, peaking at {}", name, peak);
+    }
+}
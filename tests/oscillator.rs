@@ -0,0 +1,110 @@
+use rustfft::{FftPlanner, num_complex::Complex};
+use wave_crafter::synthesizer::{Synthesizer, Waveform};
+
+const SAMPLE_RATE: usize = 44100;
+
+/// Naive (aliasing) reference waveforms, matching the shapes of the band-limited oscillator.
+fn naive_sample(waveform: Waveform, phase: f32) -> f32 {
+    match waveform {
+        Waveform::Square => if phase < 0.5 { 1.0 } else { -1.0 },
+        Waveform::Triangle => 4.0 * (phase - 0.5).abs() - 1.0,
+        Waveform::Sawtooth => 2.0 * phase - 1.0,
+        _ => (2.0 * std::f32::consts::PI * phase).sin(),
+    }
+}
+
+/// Returns the fraction of spectral energy that does not sit on a harmonic of `frequency`.
+///
+/// One second is rendered at an integer frequency, so every harmonic lands exactly on an
+/// FFT bin and anything in between is energy folded back from above Nyquist.
+fn aliasing_ratio(samples: &[f32], frequency: usize) -> f64 {
+    let mut buffer: Vec<Complex<f64>> = samples.iter().map(|&s| Complex { re: s as f64, im: 0.0 }).collect();
+    FftPlanner::new().plan_fft_forward(buffer.len()).process(&mut buffer);
+
+    let (mut total, mut aliased) = (0.0, 0.0);
+    for (bin, value) in buffer.iter().enumerate().take(SAMPLE_RATE / 2).skip(1) {
+        let energy = value.norm_sqr();
+        total += energy;
+        if bin % frequency != 0 {
+            aliased += energy;
+        }
+    }
+    aliased / total
+}
+
+fn to_db(ratio: f64) -> f64 {
+    10.0 * ratio.log10()
+}
+
+#[test]
+fn band_limited_oscillators_suppress_aliasing_across_sweep() {
+    for waveform in [Waveform::Square, Waveform::Triangle, Waveform::Sawtooth] {
+        for frequency in [220, 440, 880, 1250, 1660, 2000] {
+            let mut synth = Synthesizer::new(frequency as f32, 1.0, waveform);
+            let band_limited: Vec<f32> = (0..2 * SAMPLE_RATE)
+                .map(|_| synth.generate_sample().0)
+                .skip(SAMPLE_RATE) // Analyze the steady state after the envelope attack
+                .collect();
+            let naive: Vec<f32> = (0..SAMPLE_RATE)
+                .map(|i| naive_sample(waveform, ((i * frequency) % SAMPLE_RATE) as f32 / SAMPLE_RATE as f32))
+                .collect();
+
+            let band_limited_db = to_db(aliasing_ratio(&band_limited, frequency));
+            let naive_db = to_db(aliasing_ratio(&naive, frequency));
+            assert!(
+                band_limited_db < naive_db - 10.0 && band_limited_db < -25.0,
+                "{:?} at {} Hz: naive {:.1} dB, band-limited {:.1} dB",
+                waveform, frequency, naive_db, band_limited_db
+            );
+        }
+    }
+}
+
+/// A synthesizer playing only its live voice, with no effects.
+fn dry_synth(frequency: f32, waveform: Waveform) -> Synthesizer {
+    let mut synth = Synthesizer::new(frequency, 1.0, waveform);
+    synth.effects.slots.clear();
+    synth.master.slots.clear();
+    synth
+}
+
+fn largest_step(samples: &[f32]) -> f32 {
+    samples.windows(2).fold(0.0f32, |largest, pair| largest.max((pair[1] - pair[0]).abs()))
+}
+
+#[test]
+fn frequency_changes_do_not_click() {
+    let mut synth = dry_synth(440.0, Waveform::Sine);
+    for _ in 0..SAMPLE_RATE {
+        synth.generate_sample(); // Past the envelope attack
+    }
+    let before: Vec<f32> = (0..SAMPLE_RATE / 10).map(|_| synth.generate_sample().0).collect();
+    let limit = largest_step(&before) * 660.0 / 440.0 * 1.05; // The steepest slope a 660 Hz sine of this level has
+    let mut last = before[before.len() - 1];
+    for change in 0..20 {
+        synth.frequency_left = if change % 2 == 0 { 660.0 } else { 440.0 }; // Jump every few hundred samples
+        let mut after = vec![last];
+        after.extend((0..337).map(|_| synth.generate_sample().0));
+        assert!(largest_step(&after) < limit, "change {} jumped by {} (limit {})", change, largest_step(&after), limit);
+        last = after[after.len() - 1];
+    }
+}
+
+#[test]
+fn pitch_holds_an_hour_into_the_timeline() {
+    let render = |start_time: f64| -> Vec<f32> {
+        let mut synth = dry_synth(440.0, Waveform::Sine);
+        let (mut left, mut right) = (vec![0.0; SAMPLE_RATE], vec![0.0; SAMPLE_RATE]);
+        for (block, (left, right)) in left.chunks_mut(256).zip(right.chunks_mut(256)).enumerate() {
+            synth.render_mixed_block(start_time + block as f64 * 256.0 / SAMPLE_RATE as f64, left, right);
+        }
+        left
+    };
+    let start = render(0.0);
+    let late = render(3600.0);
+    let crossings = late.windows(2).filter(|pair| pair[0] < 0.0 && pair[1] >= 0.0).count();
+    assert!(crossings.abs_diff(440) <= 1, "an hour in, one second held {} cycles", crossings);
+    for (index, (a, b)) in start.iter().zip(&late).enumerate() {
+        assert!((a - b).abs() < 1e-6, "sample {} differs: {} at the start, {} an hour in", index, a, b);
+    }
+}
@@ -0,0 +1,54 @@
+use wave_crafter::audio::channel_sample;
+use wave_crafter::synthesizer::{Synthesizer, Waveform};
+
+/// Returns the amplitude of one frequency in a signal, with the Goertzel algorithm.
+fn amplitude_at(samples: &[f32], frequency: f32, sample_rate: f32) -> f32 {
+    let coefficient = 2.0 * (2.0 * std::f64::consts::PI * frequency as f64 / sample_rate as f64).cos();
+    let (mut previous, mut before) = (0.0f64, 0.0f64);
+    for &sample in samples {
+        (previous, before) = (sample as f64 + coefficient * previous - before, previous);
+    }
+    let power = previous * previous + before * before - coefficient * previous * before;
+    (2.0 * power.sqrt() / samples.len() as f64) as f32
+}
+
+#[test]
+fn playback_keeps_binaural_channels_apart() {
+    let mut synth = Synthesizer::new(440.0, 0.5, Waveform::Sine);
+    synth.effects.slots.clear(); // Keep the delay and reverb tails out of the measurement
+    synth.set_binaural_frequencies(400.0, 410.0);
+    let sample_rate = synth.sample_rate;
+    let length = sample_rate as usize; // One second, so both tones fall on exact bins
+    let (mut left, mut right) = (vec![0.0; length], vec![0.0; length]);
+    for (start, (left, right)) in left.chunks_mut(256).zip(right.chunks_mut(256)).enumerate() {
+        synth.render_mixed_block(start as f64 * 256.0 / sample_rate as f64, left, right);
+    }
+
+    assert!(amplitude_at(&left, 400.0, sample_rate) > 0.3, "the left channel plays the left frequency");
+    assert!(amplitude_at(&left, 410.0, sample_rate) < 0.01, "the right frequency stays out of the left channel");
+    assert!(amplitude_at(&right, 410.0, sample_rate) > 0.3, "the right channel plays the right frequency");
+    assert!(amplitude_at(&right, 400.0, sample_rate) < 0.01, "the left frequency stays out of the right channel");
+}
+
+#[test]
+fn mixed_frames_match_block_rendering() {
+    let mut by_frame = Synthesizer::new(440.0, 0.5, Waveform::Sine);
+    let mut by_block = Synthesizer::new(440.0, 0.5, Waveform::Sine);
+    by_frame.set_binaural_frequencies(300.0, 500.0);
+    by_block.set_binaural_frequencies(300.0, 500.0);
+    let (mut left, mut right) = (vec![0.0; 64], vec![0.0; 64]);
+    by_block.render_mixed_block(0.0, &mut left, &mut right);
+    for i in 0..64 {
+        let (frame_left, frame_right) = by_frame.generate_mixed_sample(i as f32 / by_frame.sample_rate);
+        assert!((frame_left - left[i]).abs() < 1e-6 && (frame_right - right[i]).abs() < 1e-6, "frame {} differs", i);
+    }
+}
+
+#[test]
+fn stereo_frames_map_onto_device_channels() {
+    assert_eq!(channel_sample(0.2, 0.6, 0, 1), 0.4, "mono devices get the sum");
+    assert_eq!(channel_sample(0.2, 0.6, 0, 2), 0.2);
+    assert_eq!(channel_sample(0.2, 0.6, 1, 2), 0.6);
+    assert_eq!(channel_sample(0.2, 0.6, 1, 6), 0.6, "surround devices carry stereo on the front pair");
+    assert_eq!(channel_sample(0.2, 0.6, 4, 6), 0.0);
+}
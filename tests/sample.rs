@@ -0,0 +1,159 @@
+use std::f32::consts::TAU;
+use std::path::Path;
+use std::sync::Arc;
+use wave_crafter::envelope::Envelope;
+use wave_crafter::sample::{AudioBuffer, SampleCache, SampleRegion};
+use wave_crafter::synthesizer::{SoundSource, Synthesizer, Waveform};
+use wave_crafter::voice::VoiceAllocator;
+
+const SAMPLE_RATE: f32 = 48000.0;
+
+/// Writes a mono float WAV file from a function of the frame index.
+fn write_wav(path: &Path, sample_rate: u32, frames: usize, sample: impl Fn(usize) -> f32) {
+    let spec = hound::WavSpec { channels: 1, sample_rate, bits_per_sample: 32, sample_format: hound::SampleFormat::Float };
+    let mut writer = hound::WavWriter::create(path, spec).unwrap();
+    for frame in 0..frames {
+        writer.write_sample(sample(frame)).unwrap();
+    }
+    writer.finalize().unwrap();
+}
+
+/// Writes one second of a sine at a frequency and file sample rate.
+fn write_sine(path: &Path, sample_rate: u32, frequency: f32) {
+    write_wav(path, sample_rate, sample_rate as usize, |frame| (TAU * frequency * frame as f32 / sample_rate as f32).sin());
+}
+
+fn rising_crossings(samples: &[f32]) -> usize {
+    samples.windows(2).filter(|pair| pair[0] < 0.0 && pair[1] >= 0.0).count()
+}
+
+fn peak(samples: &[f32]) -> f32 {
+    samples.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()))
+}
+
+/// A synthesizer that plays only its timeline, with no effects or live voice.
+fn timeline_only() -> Synthesizer {
+    let mut synth = Synthesizer::new(440.0, 0.0, Waveform::Sine);
+    synth.set_sample_rate(SAMPLE_RATE);
+    synth.effects.slots.clear();
+    synth.master.slots.clear();
+    synth
+}
+
+/// Renders a stretch of the timeline in blocks, as playback does.
+fn render(synth: &mut Synthesizer, start_time: f64, frames: usize) -> Vec<f32> {
+    let (mut left, mut right) = (vec![0.0; frames], vec![0.0; frames]);
+    for start in (0..frames).step_by(256) {
+        let end = (start + 256).min(frames);
+        synth.render_mixed_block(start_time + start as f64 / SAMPLE_RATE as f64, &mut left[start..end], &mut right[start..end]);
+    }
+    left
+}
+
+#[test]
+fn trimming_fits_the_clip_to_the_region() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("ramp.wav");
+    write_wav(&path, 48000, 48000, |frame| frame as f32 / 48000.0); // Each sample holds its own time
+    let mut synth = timeline_only();
+    synth.add_sample_clip("Ramp", 0.0, path.to_str().unwrap()).unwrap();
+    assert_eq!(synth.timeline.clips[0].duration, 1.0, "a new clip lasts as long as the file");
+
+    synth.timeline.clips[0].trim(0.25, 0.5);
+    assert_eq!(synth.timeline.clips[0].duration, 0.25, "trimming shortens the clip");
+    let output = render(&mut synth, 0.0, 24000);
+    assert!((output[4800] - 0.35).abs() < 1e-3, "playback starts at the trimmed start, got {}", output[4800]);
+    assert!(peak(&output[12000 + 2400 + 48..]) < 1e-6, "and stops after the trimmed length and its release");
+
+    synth.timeline.clips[0].trim(-1.0, 0.0);
+    assert_eq!(synth.timeline.clips[0].duration, 1.0, "negative trims are ignored");
+}
+
+#[test]
+fn gain_scales_the_region() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("dc.wav");
+    write_wav(&path, 48000, 4800, |_| 0.8);
+    let buffer = Arc::new(AudioBuffer::from_wav(path.to_str().unwrap()).unwrap());
+    let mut region = SampleRegion::new(path.to_str().unwrap(), buffer);
+    let increment = 1.0 / SAMPLE_RATE as f64;
+    assert!((region.sample_at(0.05, increment) - 0.8).abs() < 1e-6);
+    region.gain = 0.5;
+    assert!((region.sample_at(0.05, increment) - 0.4).abs() < 1e-6);
+    assert_eq!(region.frame_at(0.05, increment), (0.4, 0.4), "mono files play on both sides");
+    assert_eq!(region.sample_at(0.1, increment), 0.0, "silence past the end");
+    assert_eq!(region.sample_at(-0.01, increment), 0.0);
+}
+
+#[test]
+fn late_clips_stay_sample_accurate() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("saw.wav");
+    write_wav(&path, 48000, 48000, |frame| (frame % 100) as f32 / 100.0); // Steps of 0.01 per sample
+    let mut synth = timeline_only();
+    synth.add_sample_clip("Late", 3600.0, path.to_str().unwrap()).unwrap();
+    let output = render(&mut synth, 3600.5, 512);
+    for (index, sample) in output.iter().enumerate() {
+        let expected = ((24000 + index) % 100) as f32 / 100.0;
+        assert!((sample - expected).abs() < 1e-3, "an hour in, frame {} read {} instead of {}", index, sample, expected);
+    }
+}
+
+#[test]
+fn files_play_at_their_own_rate() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sine.wav");
+    write_sine(&path, 24000, 1000.0);
+    let mut synth = timeline_only();
+    synth.add_sample_clip("Sine", 0.0, path.to_str().unwrap()).unwrap();
+    let output = render(&mut synth, 0.0, 24000);
+    assert!(rising_crossings(&output).abs_diff(500) <= 1, "a 24 kHz file keeps its pitch at 48 kHz");
+
+    let buffer = Arc::new(AudioBuffer::from_wav(path.to_str().unwrap()).unwrap());
+    let source = SoundSource::Sample(SampleRegion::new(path.to_str().unwrap(), buffer));
+    let mut voices = VoiceAllocator::new(1);
+    voices.note_on(72, 1.0); // An octave above the root note
+    let envelope = Envelope::default();
+    let output: Vec<f32> = (0..24000).map(|_| voices.next_sample(&source, Waveform::Sine, &envelope, SAMPLE_RATE, &[])).collect();
+    assert!(rising_crossings(&output).abs_diff(1000) <= 1, "notes transpose the file from its root");
+}
+
+#[test]
+fn fast_reads_do_not_alias() {
+    let dir = tempfile::tempdir().unwrap();
+    let high = dir.path().join("high.wav");
+    write_sine(&high, 96000, 30000.0); // Above the engine's Nyquist frequency
+    let low = dir.path().join("low.wav");
+    write_sine(&low, 96000, 5000.0);
+    let mut synth = timeline_only();
+    synth.add_sample_clip("High", 0.0, high.to_str().unwrap()).unwrap();
+    let output = render(&mut synth, 0.0, 24000);
+    assert!(peak(&output[1000..]) < 0.01, "a 30 kHz tone would fold back to 18 kHz, got a peak of {}", peak(&output[1000..]));
+
+    synth.timeline.clips.clear();
+    synth.add_sample_clip("Low", 0.0, low.to_str().unwrap()).unwrap();
+    let output = render(&mut synth, 0.0, 24000);
+    assert!((peak(&output[1000..]) - 1.0).abs() < 0.02, "content below Nyquist passes unchanged");
+    assert!(rising_crossings(&output).abs_diff(2500) <= 1);
+}
+
+#[test]
+fn cache_shares_files_until_unused() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("shared.wav");
+    let filename = path.to_str().unwrap();
+    write_sine(&path, 48000, 440.0);
+    let mut cache = SampleCache::new();
+    let first = cache.load(filename).unwrap();
+    let second = cache.load(filename).unwrap();
+    assert!(Arc::ptr_eq(&first, &second), "the file is decoded once");
+
+    std::fs::remove_file(&path).unwrap();
+    drop(second);
+    cache.evict_unused();
+    assert!(Arc::ptr_eq(&cache.load(filename).unwrap(), &first), "files still in use stay cached");
+
+    drop(first);
+    cache.evict_unused();
+    assert!(cache.load(filename).is_err(), "unused files are dropped and read from disk again");
+}
@@ -0,0 +1,108 @@
+use wave_crafter::envelope::Envelope;
+use wave_crafter::synthesizer::{SoundSource, Waveform};
+use wave_crafter::voice::{VoiceAllocator, VoiceStealing};
+
+const SAMPLE_RATE: f32 = 44100.0;
+
+fn held_notes(voices: &VoiceAllocator) -> Vec<u8> {
+    let mut notes: Vec<u8> = voices.active_voices().filter(|voice| voice.is_held()).map(|voice| voice.note).collect();
+    notes.sort();
+    notes
+}
+
+/// Renders a number of samples of a sine voice pool.
+fn render(voices: &mut VoiceAllocator, samples: usize) -> Vec<f32> {
+    let envelope = Envelope::default();
+    (0..samples).map(|_| voices.next_sample(&SoundSource::Oscillator, Waveform::Sine, &envelope, SAMPLE_RATE, &[])).collect()
+}
+
+#[test]
+fn polyphony_limits_the_sounding_voices() {
+    let mut voices = VoiceAllocator::new(3);
+    for note in 60..66 {
+        voices.note_on(note, 1.0);
+        assert!(voices.active_voices().count() <= 3);
+    }
+    assert_eq!(held_notes(&voices), [63, 64, 65], "the oldest notes were stolen");
+
+    voices.set_polyphony(2);
+    assert_eq!(held_notes(&voices), [64, 65], "shrinking the pool fades out the oldest notes, as stealing would");
+    assert_eq!(VoiceAllocator::new(0).polyphony(), 1, "there is always at least one voice");
+}
+
+#[test]
+fn shrinking_the_pool_drops_finished_voices_first() {
+    let mut voices = VoiceAllocator::new(4);
+    for note in [60, 62, 64, 65] {
+        voices.note_on(note, 1.0);
+    }
+    voices.note_off(60);
+    voices.note_off(62);
+    render(&mut voices, (Envelope::default().release * SAMPLE_RATE) as usize + 10); // Both releases finish
+    assert_eq!(voices.active_voices().count(), 2, "the two oldest voices are finished but still in the pool");
+
+    voices.set_polyphony(2);
+    assert_eq!(held_notes(&voices), [64, 65], "the finished voices make room, so the played notes keep sounding");
+    voices.set_polyphony(1);
+    assert_eq!(held_notes(&voices), [65], "then the oldest sounding note goes");
+}
+
+#[test]
+fn note_off_releases_only_that_note() {
+    let mut voices = VoiceAllocator::new(4);
+    voices.note_on(60, 1.0);
+    voices.note_on(64, 1.0);
+    render(&mut voices, 500);
+    voices.note_off(60);
+    assert!(!voices.is_note_held(60) && voices.is_note_held(64));
+    assert_eq!(voices.active_voices().count(), 2, "the released note rings through its release");
+
+    render(&mut voices, (Envelope::default().release * SAMPLE_RATE) as usize + 10);
+    assert_eq!(held_notes(&voices), [64]);
+    assert_eq!(voices.active_voices().count(), 1, "the voice is free once the release ends");
+
+    voices.all_notes_off();
+    assert!(held_notes(&voices).is_empty());
+}
+
+#[test]
+fn stealing_policies_pick_their_voice() {
+    let mut voices = VoiceAllocator::new(2);
+    voices.stealing = VoiceStealing::Quietest;
+    voices.note_on(60, 1.0);
+    voices.note_on(62, 0.2);
+    render(&mut voices, 500);
+    voices.note_on(64, 1.0);
+    assert_eq!(held_notes(&voices), [60, 64], "the quiet note was taken over");
+
+    let mut voices = VoiceAllocator::new(2);
+    voices.stealing = VoiceStealing::SameNote;
+    voices.note_on(60, 1.0);
+    voices.note_on(62, 1.0);
+    voices.note_on(60, 0.5);
+    assert_eq!(held_notes(&voices), [60, 62], "a repeated note retriggers its own voice");
+    voices.note_on(64, 1.0);
+    assert_eq!(held_notes(&voices), [60, 64], "otherwise the oldest voice is stolen, and the retrigger renewed 60");
+
+    let mut voices = VoiceAllocator::new(2);
+    voices.note_on(60, 1.0);
+    voices.note_on(62, 1.0);
+    voices.note_off(60);
+    voices.note_on(60, 1.0);
+    assert_eq!(held_notes(&voices), [60, 62], "oldest stealing starts a second voice for the repeated note");
+}
+
+#[test]
+fn stolen_voices_fade_out_without_a_click() {
+    for offset in (0..100).step_by(10) {
+        let mut voices = VoiceAllocator::new(1);
+        voices.note_on(69, 1.0);
+        let mut output = render(&mut voices, 2000 + offset);
+        voices.note_on(81, 1.0); // Takes over the only voice
+        output.extend(render(&mut voices, 1000));
+
+        let largest_step = output.windows(2).map(|pair| (pair[1] - pair[0]).abs()).fold(0.0, f32::max);
+        assert!(largest_step < 0.15, "offset {}: the output jumped by {}", offset, largest_step);
+        assert!(output[2000 + offset + 400..].iter().any(|sample| sample.abs() > 0.5), "the new note plays");
+    }
+}
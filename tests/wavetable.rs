@@ -0,0 +1,100 @@
+use rustfft::{num_complex::Complex, FftPlanner};
+use std::f32::consts::TAU;
+use wave_crafter::wavetable::{Wavetable, DEFAULT_FRAME_SIZE};
+
+const SAMPLE_RATE: f32 = 48000.0;
+
+fn sawtooth() -> Wavetable {
+    let frame: Vec<f32> = (0..DEFAULT_FRAME_SIZE).map(|i| 2.0 * i as f32 / DEFAULT_FRAME_SIZE as f32 - 1.0).collect();
+    Wavetable::from_frames("saw", &[frame])
+}
+
+/// Plays the table for one second and returns the magnitude of each 1 Hz bin up to Nyquist.
+fn spectrum(table: &Wavetable, frequency: f32) -> Vec<f32> {
+    let increment = frequency / SAMPLE_RATE;
+    let mut samples: Vec<Complex<f32>> = (0..SAMPLE_RATE as usize)
+        .map(|i| Complex { re: table.sample((i as f32 * increment).fract(), 0.0, increment), im: 0.0 })
+        .collect();
+    FftPlanner::new().plan_fft_forward(samples.len()).process(&mut samples);
+    samples[..samples.len() / 2].iter().map(|bin| bin.norm() / SAMPLE_RATE * 2.0).collect()
+}
+
+/// Returns the energy in bins that are not harmonics of the frequency, relative to the total.
+fn inharmonic_energy(bins: &[f32], frequency: usize) -> f32 {
+    let total: f32 = bins.iter().map(|bin| bin * bin).sum();
+    let stray: f32 = bins.iter().enumerate().filter(|(index, _)| index % frequency != 0).map(|(_, bin)| bin * bin).sum();
+    stray / total
+}
+
+#[test]
+fn high_notes_use_band_limited_levels() {
+    let table = sawtooth();
+    let bins = spectrum(&table, 5000.0);
+    assert!(inharmonic_energy(&bins, 5000) < 1e-5, "harmonics past Nyquist would fold back between the real ones");
+    for harmonic in 1..=4 {
+        let expected = 2.0 / (std::f32::consts::PI * harmonic as f32); // A sawtooth's harmonics fall off as 1/n
+        assert!((bins[harmonic * 5000] - expected).abs() < 0.02 * expected, "harmonic {} was {}", harmonic, bins[harmonic * 5000]);
+    }
+
+    let naive = |i: usize| (i as f32 * 5000.0 / SAMPLE_RATE).fract() * 2.0 - 1.0; // The same saw without band limiting
+    let mut samples: Vec<Complex<f32>> = (0..SAMPLE_RATE as usize).map(|i| Complex { re: naive(i), im: 0.0 }).collect();
+    FftPlanner::new().plan_fft_forward(samples.len()).process(&mut samples);
+    let naive_bins: Vec<f32> = samples[..samples.len() / 2].iter().map(|bin| bin.norm() / SAMPLE_RATE * 2.0).collect();
+    assert!(inharmonic_energy(&naive_bins, 5000) > 1e-3, "the naive saw does alias, so the check above is meaningful");
+}
+
+#[test]
+fn low_notes_keep_their_upper_harmonics() {
+    let table = sawtooth();
+    let bins = spectrum(&table, 100.0);
+    assert!(inharmonic_energy(&bins, 100) < 1e-5);
+    let harmonic = 100;
+    let expected = 2.0 / (std::f32::consts::PI * harmonic as f32);
+    assert!((bins[harmonic * 100] - expected).abs() < 0.05 * expected, "the 100th harmonic at 10 kHz is still there");
+    assert!(bins[20000..].iter().all(|&bin| bin < 1e-4), "nothing is kept close to Nyquist");
+}
+
+#[test]
+fn wav_files_split_into_frames() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("table.wav");
+    let spec = hound::WavSpec { channels: 1, sample_rate: 48000, bits_per_sample: 32, sample_format: hound::SampleFormat::Float };
+    let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+    for gain in [1.0, 0.5, -1.0] {
+        for i in 0..DEFAULT_FRAME_SIZE {
+            writer.write_sample(gain * (TAU * i as f32 / DEFAULT_FRAME_SIZE as f32).sin()).unwrap();
+        }
+    }
+    writer.finalize().unwrap();
+
+    let table = Wavetable::from_wav(path.to_str().unwrap(), DEFAULT_FRAME_SIZE).unwrap();
+    assert_eq!(table.frame_count(), 3);
+    assert_eq!(table.name, path.to_str().unwrap(), "the file is kept to reload the table with the project");
+    let increment = 100.0 / SAMPLE_RATE;
+    for (position, expected) in [(0.0, 1.0), (0.25, 0.75), (0.5, 0.5), (1.0, -1.0)] {
+        let peak = table.sample(0.25, position, increment);
+        assert!((peak - expected).abs() < 1e-3, "position {} read {} at the crest", position, peak);
+    }
+}
+
+#[test]
+fn short_wav_files_are_one_cycle() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("cycle.wav");
+    let spec = hound::WavSpec { channels: 2, sample_rate: 44100, bits_per_sample: 16, sample_format: hound::SampleFormat::Int };
+    let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+    for i in 0..600 {
+        writer.write_sample((16384.0 * (TAU * i as f32 / 600.0).sin()) as i16).unwrap(); // Left holds the cycle
+        writer.write_sample(0i16).unwrap();
+    }
+    writer.finalize().unwrap();
+
+    let table = Wavetable::from_wav(path.to_str().unwrap(), DEFAULT_FRAME_SIZE).unwrap();
+    assert_eq!(table.frame_count(), 1);
+    let crest = table.sample(0.25, 0.0, 100.0 / SAMPLE_RATE);
+    assert!((crest - 0.5).abs() < 1e-3, "16-bit samples scale to full range, got {}", crest);
+
+    let empty = dir.path().join("empty.wav");
+    hound::WavWriter::create(&empty, spec).unwrap().finalize().unwrap();
+    assert!(Wavetable::from_wav(empty.to_str().unwrap(), DEFAULT_FRAME_SIZE).is_err());
+}